@@ -0,0 +1,320 @@
+use std::{
+    collections::BTreeSet,
+    io::{Read, Seek, Write},
+};
+
+use crate::{
+    checksum::crc32,
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::PageLayout,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Bytes in front of the bits: the reserved pages, the opening generation, the pages covered,
+/// the checksum of the bits and the closing generation
+const BITMAP_META_BYTES: usize = 32;
+
+/// A bit per page telling the live ones from the free ones, stored in pages reserved right
+/// after the file header. Operations that may change pages write an opening generation before
+/// touching them and the bits followed by a matching closing generation once done, so a bitmap
+/// whose generations differ was left halfway by a crash and gets rebuilt from the pages.
+pub(crate) struct LiveBitmap {
+    /// Bits of every page, kept up to date with the free list after each operation
+    bits: Vec<u8>,
+    /// Bytes of the bits the reserved pages hold right now
+    stored: Vec<u8>,
+    /// Most pages the reserved pages can cover, the ones past it are found by reading them
+    capacity: usize,
+    generation: u64,
+    /// Whether the opening generation of the running operation was written
+    open: bool,
+}
+
+/// Iterator over the indexes of the live pages, in ascending order
+pub struct BitmapIter<'a> {
+    bits: Option<&'a [u8]>,
+    free: &'a BTreeSet<usize>,
+    page: usize,
+    end: usize,
+}
+
+impl Iterator for BitmapIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.page < self.end {
+            let page = self.page;
+            match self.bits {
+                Some(bits) if page.is_multiple_of(8) && bits[page / 8] == 0 => self.page += 8,
+                Some(bits) => {
+                    self.page += 1;
+                    if is_set(bits, page) {
+                        return Some(page);
+                    }
+                }
+                None => {
+                    self.page += 1;
+                    if !self.free.contains(&page) {
+                        return Some(page);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Keeps a bitmap of the live pages in `pages` pages reserved after the file header of an
+    /// empty store, turning the free list on. Reopening loads the free list from it instead of
+    /// reading every page, so the list stays on.
+    pub fn reserve_live_bitmap(&mut self, pages: usize) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            let reserve_error = |reason: &str| {
+                Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    format!("Could not reserve a live bitmap: {}", reason),
+                ))
+            };
+            if !bookworm.pager.has_header() {
+                return reserve_error("the store has no file header");
+            }
+            if bookworm.bitmap.is_some() {
+                return reserve_error("the store keeps one already");
+            }
+            if bookworm.pager.stored_bytes()? != bookworm.pager.data_offset() {
+                return reserve_error("the store is not empty");
+            }
+            if bookworm.pager.layout == PageLayout::Chained {
+                return reserve_error("chained pages aren't supported");
+            }
+            let bytes = pages * bookworm.pager.page_size;
+            if bytes <= BITMAP_META_BYTES {
+                return reserve_error(&format!(
+                    "{} pages of {} bytes can't hold it",
+                    pages, bookworm.pager.page_size
+                ));
+            }
+            bookworm.pager.reserve_bitmap(pages)?;
+            let mut meta = [0; BITMAP_META_BYTES];
+            meta[..4].copy_from_slice(&(pages as u32).to_le_bytes());
+            meta[20..24].copy_from_slice(&crc32(&[]).to_le_bytes());
+            bookworm.pager.write_reserved(0, &meta)?;
+            bookworm.pager.free.clear();
+            bookworm.free_list = true;
+            bookworm.bitmap = Some(LiveBitmap {
+                bits: Vec::new(),
+                stored: Vec::new(),
+                capacity: (bytes - BITMAP_META_BYTES) * 8,
+                generation: 0,
+                open: false,
+            });
+            Ok(())
+        })
+    }
+    /// Whether `page` holds a record, answered from the live bitmap or the free list without
+    /// reading the page
+    pub fn is_live(&self, page: usize) -> bool {
+        if page >= self.pager.pages_count {
+            return false;
+        }
+        match &self.bitmap {
+            Some(bitmap) => is_set(&bitmap.bits, page),
+            None => !self.pager.free.contains(&page),
+        }
+    }
+    /// Number of live pages, counted over the live bitmap when there is one
+    pub fn count_live(&self) -> usize {
+        match &self.bitmap {
+            Some(bitmap) => bitmap
+                .bits
+                .iter()
+                .map(|byte| byte.count_ones() as usize)
+                .sum(),
+            None => self.pager.pages_count - self.pager.free.len(),
+        }
+    }
+    pub fn live_pages(&self) -> BitmapIter<'_> {
+        BitmapIter {
+            bits: self.bitmap.as_ref().map(|bitmap| &bitmap.bits[..]),
+            free: &self.pager.free,
+            page: 0,
+            end: self.pager.pages_count,
+        }
+    }
+    /// Derives the free list and the live bitmap again by reading every page, returning how
+    /// many pages the stored bitmap had wrong. Opening does it by itself when the generations
+    /// or the checksum of the stored bitmap don't match.
+    pub fn rebuild_bitmap(&mut self) -> BookwormResult<usize> {
+        self.in_context(OpKind::Write, |bookworm| {
+            let Some(capacity) = bookworm.bitmap.as_ref().map(|bitmap| bitmap.capacity) else {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not rebuild the live bitmap: the store keeps none".to_owned(),
+                ));
+            };
+            let stored = bookworm.read_bitmap()?;
+            bookworm.scan_free()?;
+            let bits = live_bits(bookworm.pager.pages_count, &bookworm.pager.free);
+            let covered = stored.covered.max(bookworm.pager.pages_count).min(capacity);
+            let wrong = (0..covered)
+                .filter(|page| is_set(&stored.bits, *page) != is_set(&bits, *page))
+                .count();
+            if let Some(bitmap) = &mut bookworm.bitmap {
+                bitmap.stored = stored.bits;
+            }
+            Ok(wrong)
+        })
+    }
+    /// Loads the free list from the live bitmap on open, reading only the pages past the ones
+    /// it covers and dropping those left free at the end, or rebuilds it when it was left
+    /// halfway or covers pages the store doesn't hold
+    pub(crate) fn load_bitmap(&mut self) -> BookwormResult<()> {
+        let bitmap_pages = self.pager.bitmap_pages();
+        if bitmap_pages == 0 {
+            return Ok(());
+        }
+        let bytes = bitmap_pages * self.pager.page_size;
+        if bytes <= BITMAP_META_BYTES {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Could not load the live bitmap: {} pages of {} bytes can't hold it",
+                    bitmap_pages, self.pager.page_size
+                ),
+            ));
+        }
+        let stored = self.read_bitmap()?;
+        let capacity = (bytes - BITMAP_META_BYTES) * 8;
+        let pages_count = self.pager.pages_count;
+        let consistent = stored.opening == stored.closing
+            && stored.checksum == crc32(&stored.bits)
+            && stored.covered <= pages_count.min(capacity);
+        self.free_list = true;
+        self.bitmap = Some(LiveBitmap {
+            bits: Vec::new(),
+            stored: stored.bits.clone(),
+            capacity,
+            generation: stored.opening.max(stored.closing),
+            open: false,
+        });
+        if !consistent {
+            return self.rebuild_bitmap().map(|_| ());
+        }
+        self.pager.free = (0..stored.covered)
+            .filter(|page| !is_set(&stored.bits, *page))
+            .collect();
+        for page in stored.covered..pages_count {
+            if self.pager.is_slot_empty(page)? {
+                self.pager.free.insert(page);
+            }
+        }
+        if let Some(bitmap) = &mut self.bitmap {
+            bitmap.bits = live_bits(pages_count, &self.pager.free);
+        }
+        if self.pager.free.last() == pages_count.checked_sub(1).as_ref() {
+            self.in_context(OpKind::Pop, Self::drop_free_tail)?;
+        }
+        Ok(())
+    }
+    /// Writes the opening generation before an operation of `kind` that may change pages,
+    /// making it durable first as the durability says
+    pub(crate) fn open_bitmap(&mut self, kind: OpKind) -> BookwormResult<()> {
+        let Some(bitmap) = &mut self.bitmap else {
+            return Ok(());
+        };
+        if !kind.changes_pages() || bitmap.open {
+            return Ok(());
+        }
+        let opening = bitmap.generation + 1;
+        self.pager.write_reserved(4, &opening.to_le_bytes())?;
+        if let Some(bitmap) = &mut self.bitmap {
+            bitmap.open = true;
+        }
+        self.after_write(kind)
+    }
+    /// Brings the live bitmap up to date with the free list once an operation is done, writing
+    /// the bytes that changed and the closing generation. A poisoned bookworm leaves it open.
+    pub(crate) fn store_bitmap(&mut self) -> BookwormResult<()> {
+        if self.poisoned {
+            return Ok(());
+        }
+        let Some(bitmap) = &self.bitmap else {
+            return Ok(());
+        };
+        if !bitmap.open {
+            return Ok(());
+        }
+        let pages_count = self.pager.pages_count;
+        let bits = live_bits(pages_count, &self.pager.free);
+        let covered = pages_count.min(bitmap.capacity);
+        let mut stored = bits[..covered.div_ceil(8)].to_vec();
+        if let Some(last) = stored.last_mut().filter(|_| !covered.is_multiple_of(8)) {
+            *last &= (1 << (covered % 8)) - 1;
+        }
+        let from = stored
+            .iter()
+            .zip(&bitmap.stored)
+            .take_while(|(new, old)| new == old)
+            .count();
+        let closing = bitmap.generation + 1;
+        if from < stored.len() {
+            self.pager
+                .write_reserved((BITMAP_META_BYTES + from) as u64, &stored[from..])?;
+        }
+        let mut meta = [0; BITMAP_META_BYTES - 12];
+        meta[..8].copy_from_slice(&(covered as u64).to_le_bytes());
+        meta[8..12].copy_from_slice(&crc32(&stored).to_le_bytes());
+        meta[12..].copy_from_slice(&closing.to_le_bytes());
+        self.pager.write_reserved(12, &meta)?;
+        if let Some(bitmap) = &mut self.bitmap {
+            bitmap.bits = bits;
+            bitmap.stored = stored;
+            bitmap.generation = closing;
+            bitmap.open = false;
+        }
+        Ok(())
+    }
+    fn read_bitmap(&mut self) -> BookwormResult<StoredBitmap> {
+        let mut region = vec![0; self.pager.bitmap_pages() * self.pager.page_size];
+        self.pager.read_reserved(0, &mut region)?;
+        let field = |at: usize| u64::from_le_bytes(region[at..at + 8].try_into().unwrap());
+        let capacity = (region.len() - BITMAP_META_BYTES) * 8;
+        let covered = (field(12) as usize).min(capacity);
+        Ok(StoredBitmap {
+            opening: field(4),
+            closing: field(24),
+            covered: field(12) as usize,
+            checksum: u32::from_le_bytes(region[20..24].try_into().unwrap()),
+            bits: region[BITMAP_META_BYTES..BITMAP_META_BYTES + covered.div_ceil(8)].to_vec(),
+        })
+    }
+}
+
+/// What the pages reserved for the live bitmap hold
+struct StoredBitmap {
+    opening: u64,
+    closing: u64,
+    covered: usize,
+    checksum: u32,
+    bits: Vec<u8>,
+}
+
+/// Bits of the first `pages_count` pages, set for the ones that aren't free
+fn live_bits(pages_count: usize, free: &BTreeSet<usize>) -> Vec<u8> {
+    let mut bits = vec![0xff; pages_count / 8];
+    if !pages_count.is_multiple_of(8) {
+        bits.push((1 << (pages_count % 8)) - 1);
+    }
+    for page in free.range(..pages_count) {
+        bits[page / 8] &= !(1 << (page % 8));
+    }
+    bits
+}
+
+fn is_set(bits: &[u8], page: usize) -> bool {
+    bits.get(page / 8)
+        .is_some_and(|byte| byte & (1 << (page % 8)) != 0)
+}
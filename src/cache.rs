@@ -0,0 +1,101 @@
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    ops::RangeBounds,
+};
+
+/// Keys of cached entries, each tied to the page it was read from
+pub(crate) trait CacheKey: Copy + Eq + Hash {
+    fn page(&self) -> usize;
+}
+
+impl CacheKey for usize {
+    fn page(&self) -> usize {
+        *self
+    }
+}
+
+/// A decoded value is cached per page and per type it was decoded as
+impl CacheKey for (usize, TypeId) {
+    fn page(&self) -> usize {
+        self.0
+    }
+}
+
+/// Raw pages kept in memory, or values decoded from them, dropping the least recently used
+/// one once there are more than the capacity
+pub(crate) struct PageCache<K = usize, V = Vec<u8>> {
+    capacity: usize,
+    /// Each cached entry along with the tick it was last used at
+    pages: HashMap<K, (u64, V)>,
+    /// Cached entries by the tick they were last used at, oldest first
+    recency: BTreeMap<u64, K>,
+    tick: u64,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl<K: CacheKey, V> PageCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            pages: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+    /// Looks an entry up, counting the hit or miss and marking it as the most recently used
+    pub fn get(&mut self, key: K) -> Option<&V> {
+        let Some((used_at, data)) = self.pages.get_mut(&key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        self.recency.remove(used_at);
+        self.tick += 1;
+        *used_at = self.tick;
+        self.recency.insert(self.tick, key);
+        Some(data)
+    }
+    pub fn insert(&mut self, key: K, data: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((used_at, _)) = self.pages.insert(key, (self.tick, data)) {
+            self.recency.remove(&used_at);
+        }
+        self.recency.insert(self.tick, key);
+        self.evict();
+    }
+    /// Changes how many entries are kept, dropping the least recently used ones past it
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+    /// Drops the cached entries of `pages`, whose stored bytes changed
+    pub fn invalidate(&mut self, pages: impl RangeBounds<usize>) {
+        let recency = &mut self.recency;
+        self.pages.retain(|key, (used_at, _)| {
+            let keep = !pages.contains(&key.page());
+            if !keep {
+                recency.remove(used_at);
+            }
+            keep
+        });
+    }
+    fn evict(&mut self) {
+        while self.pages.len() > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.pages.remove(&oldest);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt::{Debug, Display};
+
+/// Bytes taken by the widest digest an algorithm can compute
+pub const MAX_DIGEST_BYTES: usize = 32;
+
+/// A digest as stored in the header of a checksummed page, most significant byte first
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest {
+    len: u8,
+    bytes: [u8; MAX_DIGEST_BYTES],
+}
+
+impl Digest {
+    /// Takes the first `MAX_DIGEST_BYTES` bytes at most
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(MAX_DIGEST_BYTES);
+        let mut digest = Self {
+            len: len as u8,
+            bytes: [0; MAX_DIGEST_BYTES],
+        };
+        digest.bytes[..len].copy_from_slice(&bytes[..len]);
+        digest
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+/// Shows the digest in hex
+impl Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_bytes()
+            .iter()
+            .try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl Debug for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Digest({})", self)
+    }
+}
+
+/// Digests the data of checksummed pages
+pub trait Checksum {
+    /// Bytes taken by every digest it computes, at most `MAX_DIGEST_BYTES`
+    fn digest_len(&self) -> usize;
+    fn digest(&self, data: &[u8]) -> Digest;
+}
+
+/// CRC-32 as used by zlib, fast and enough to catch torn or flipped bits
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32;
+
+impl Checksum for Crc32 {
+    fn digest_len(&self) -> usize {
+        4
+    }
+    fn digest(&self, data: &[u8]) -> Digest {
+        Digest::from_bytes(&crc32(data).to_be_bytes())
+    }
+}
+
+/// XXH64 with a zero seed, as fast as CRC-32 with far fewer collisions
+#[derive(Debug, Clone, Copy)]
+pub struct XxHash64;
+
+impl Checksum for XxHash64 {
+    fn digest_len(&self) -> usize {
+        8
+    }
+    fn digest(&self, data: &[u8]) -> Digest {
+        Digest::from_bytes(&xxh64(data).to_be_bytes())
+    }
+}
+
+/// SHA-256, slower but a page can't be changed to match its digest on purpose
+#[derive(Debug, Clone, Copy)]
+pub struct Sha256;
+
+impl Checksum for Sha256 {
+    fn digest_len(&self) -> usize {
+        32
+    }
+    fn digest(&self, data: &[u8]) -> Digest {
+        Digest::from_bytes(&sha256(data))
+    }
+}
+
+/// The checksum algorithms a store can be created with, the one in use is recorded in the file
+/// header
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Crc32,
+    XxHash64,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub const ALL: [ChecksumAlgorithm; 3] = [
+        ChecksumAlgorithm::Crc32,
+        ChecksumAlgorithm::XxHash64,
+        ChecksumAlgorithm::Sha256,
+    ];
+
+    pub fn checksum(self) -> &'static dyn Checksum {
+        match self {
+            ChecksumAlgorithm::Crc32 => &Crc32,
+            ChecksumAlgorithm::XxHash64 => &XxHash64,
+            ChecksumAlgorithm::Sha256 => &Sha256,
+        }
+    }
+    pub(crate) fn to_tag(self) -> u8 {
+        match self {
+            ChecksumAlgorithm::Crc32 => 0,
+            ChecksumAlgorithm::XxHash64 => 1,
+            ChecksumAlgorithm::Sha256 => 2,
+        }
+    }
+    pub(crate) fn from_tag(tag: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.to_tag() == tag)
+    }
+}
+
+impl Checksum for ChecksumAlgorithm {
+    fn digest_len(&self) -> usize {
+        self.checksum().digest_len()
+    }
+    fn digest(&self, data: &[u8]) -> Digest {
+        self.checksum().digest(data)
+    }
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+const XXH_PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const XXH_PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const XXH_PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const XXH_PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const XXH_PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+fn xxh64_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(XXH_PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(XXH_PRIME_1)
+}
+
+fn xxh64(data: &[u8]) -> u64 {
+    let read_u64 = |bytes: &[u8]| u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let mut rest = data;
+    let mut hash = if data.len() >= 32 {
+        let mut lanes = [
+            XXH_PRIME_1.wrapping_add(XXH_PRIME_2),
+            XXH_PRIME_2,
+            0,
+            XXH_PRIME_1.wrapping_neg(),
+        ];
+        while rest.len() >= 32 {
+            for (lane, input) in lanes.iter_mut().zip(rest.chunks_exact(8)) {
+                *lane = xxh64_round(*lane, read_u64(input));
+            }
+            rest = &rest[32..];
+        }
+        let mut hash = lanes[0]
+            .rotate_left(1)
+            .wrapping_add(lanes[1].rotate_left(7))
+            .wrapping_add(lanes[2].rotate_left(12))
+            .wrapping_add(lanes[3].rotate_left(18));
+        for lane in lanes {
+            hash = (hash ^ xxh64_round(0, lane))
+                .wrapping_mul(XXH_PRIME_1)
+                .wrapping_add(XXH_PRIME_4);
+        }
+        hash
+    } else {
+        XXH_PRIME_5
+    };
+    hash = hash.wrapping_add(data.len() as u64);
+    while rest.len() >= 8 {
+        hash ^= xxh64_round(0, read_u64(rest));
+        hash = hash
+            .rotate_left(27)
+            .wrapping_mul(XXH_PRIME_1)
+            .wrapping_add(XXH_PRIME_4);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        let input = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+        hash ^= input.wrapping_mul(XXH_PRIME_1);
+        hash = hash
+            .rotate_left(23)
+            .wrapping_mul(XXH_PRIME_2)
+            .wrapping_add(XXH_PRIME_3);
+        rest = &rest[4..];
+    }
+    for byte in rest {
+        hash ^= (*byte as u64).wrapping_mul(XXH_PRIME_5);
+        hash = hash.rotate_left(11).wrapping_mul(XXH_PRIME_1);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(XXH_PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(XXH_PRIME_3);
+    hash ^ (hash >> 32)
+}
+
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    // the data, a one bit, zeroes up to a whole number of blocks and the bit length
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    padded.resize(padded.len().div_ceil(64) * 64, 0);
+    if padded.len() - data.len() < 9 {
+        padded.resize(padded.len() + 64, 0);
+    }
+    let bits = (data.len() as u64).wrapping_mul(8);
+    let end = padded.len();
+    padded[end - 8..].copy_from_slice(&bits.to_be_bytes());
+
+    for block in padded.chunks_exact(64) {
+        let mut schedule = [0u32; 64];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7)
+                ^ schedule[i - 15].rotate_right(18)
+                ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17)
+                ^ schedule[i - 2].rotate_right(19)
+                ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (constant, word) in SHA256_ROUND_CONSTANTS.iter().zip(schedule) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*constant)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
@@ -0,0 +1,86 @@
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Turns records into the bytes stored in pages and back
+pub trait Codec: Clone {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, Self::Error>;
+    /// Decodes a record from the start of `bytes`, ignoring whatever padding follows it and
+    /// reading no more than `limit` bytes
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8], limit: u64) -> Result<T, Self::Error>;
+    /// Same as `deserialize`, letting the record borrow strings and byte slices from `bytes`
+    fn deserialize_borrowed<'de, T: Deserialize<'de>>(
+        &self,
+        bytes: &'de [u8],
+        limit: u64,
+    ) -> Result<T, Self::Error>;
+}
+
+/// The bytes a borrowing decode may look at, since decoding from a slice ignores the limit
+fn limited(bytes: &[u8], limit: u64) -> &[u8] {
+    &bytes[..bytes.len().min(limit.try_into().unwrap_or(usize::MAX))]
+}
+
+/// Bincode with fixed size integers, the format pages have always been stored in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    type Error = bincode::Error;
+
+    fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, Self::Error> {
+        bincode::serialize(value)
+    }
+    /// Decoding from a slice ignores the limit, so the bytes are read as a stream instead
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8], limit: u64) -> Result<T, Self::Error> {
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(limit)
+            .deserialize_from(bytes)
+    }
+    fn deserialize_borrowed<'de, T: Deserialize<'de>>(
+        &self,
+        bytes: &'de [u8],
+        limit: u64,
+    ) -> Result<T, Self::Error> {
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .deserialize(limited(bytes, limit))
+    }
+}
+
+/// Bincode with variable length integers, which makes records with small numbers a lot shorter
+#[cfg(feature = "varint-codec")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VarintCodec;
+
+#[cfg(feature = "varint-codec")]
+impl Codec for VarintCodec {
+    type Error = bincode::Error;
+
+    fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, Self::Error> {
+        bincode::DefaultOptions::new()
+            .with_varint_encoding()
+            .serialize(value)
+    }
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8], limit: u64) -> Result<T, Self::Error> {
+        bincode::DefaultOptions::new()
+            .with_varint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(limit)
+            .deserialize_from(bytes)
+    }
+    fn deserialize_borrowed<'de, T: Deserialize<'de>>(
+        &self,
+        bytes: &'de [u8],
+        limit: u64,
+    ) -> Result<T, Self::Error> {
+        bincode::DefaultOptions::new()
+            .with_varint_encoding()
+            .allow_trailing_bytes()
+            .deserialize(limited(bytes, limit))
+    }
+}
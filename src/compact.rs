@@ -0,0 +1,34 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormResult, OpKind},
+    pager,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// What a compaction gave back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactReport {
+    pub pages_reclaimed: usize,
+    pub bytes_reclaimed: u64,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Removes every dead page, moving the live ones forward in a single pass that holds one
+    /// page at a time. A page is dead when it holds no payload as measured by `page_fill`, so
+    /// a record serializing to nothing but zeroes counts as dead too.
+    pub fn compact(&mut self) -> BookwormResult<CompactReport> {
+        self.in_context(OpKind::Compact, |bookworm| {
+            let page_size = bookworm.pager.page_size;
+            let layout = bookworm.pager.layout;
+            let pages_reclaimed =
+                bookworm.compact_pages(|raw_page| Ok(pager::payload_len(layout, raw_page) > 0))?;
+            Ok(CompactReport {
+                pages_reclaimed,
+                bytes_reclaimed: (pages_reclaimed * page_size) as u64,
+            })
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Where the pages count of a store with a file header comes from when it is opened
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CountSource {
+    /// Every whole page the file holds, zeroed or half written ones included
+    #[default]
+    FileLength,
+    /// The count the file header records, rewritten by every operation that changes it and
+    /// made durable along with the pages
+    Header,
+}
+
+/// How the pages count recorded in the file header compared with the file length on open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountCheck {
+    /// The header records no count, the file length was used
+    Unrecorded,
+    /// The file holds exactly the pages the header counts
+    Matches,
+    /// The file holds whole pages past the count, left by preallocation or by a push that
+    /// didn't get to update the header. They are left out and overwritten by later pushes.
+    TrailingPages,
+    /// The file ends before the counted pages do, it was cut short behind the store's back.
+    /// Reading the missing pages fails.
+    MissingPages,
+}
+
+/// What opening a store found about its pages count, see `Bookworm::open_integrity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenIntegrity {
+    pub check: CountCheck,
+    pub recorded_pages: Option<usize>,
+    /// Whole pages the file held
+    pub stored_pages: usize,
+    /// Bytes past the last whole page, left by a write torn halfway
+    pub torn_bytes: u64,
+}
+
+impl OpenIntegrity {
+    pub(crate) fn new(recorded_pages: Option<usize>, stored_pages: usize, torn_bytes: u64) -> Self {
+        let check = match recorded_pages {
+            None => CountCheck::Unrecorded,
+            Some(pages) if pages == stored_pages => CountCheck::Matches,
+            Some(pages) if pages < stored_pages => CountCheck::TrailingPages,
+            Some(_) => CountCheck::MissingPages,
+        };
+        Self {
+            check,
+            recorded_pages,
+            stored_pages,
+            torn_bytes,
+        }
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Keeps the pages count in the file header from now on, or goes back to taking it from
+    /// the file length. The choice is recorded in the header, reopening keeps it.
+    pub fn set_count_source(&mut self, source: CountSource) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            if !bookworm.pager.has_header() {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not keep the pages count in the header: the store has no file header"
+                        .to_owned(),
+                ));
+            }
+            if source == bookworm.pager.count_source {
+                return Ok(());
+            }
+            bookworm.pager.count_source = source;
+            bookworm.pager.write_header()
+        })
+    }
+    pub fn count_source(&self) -> CountSource {
+        self.pager.count_source
+    }
+    /// What opening found about the pages count, `None` for stores without a file header
+    pub fn open_integrity(&self) -> Option<OpenIntegrity> {
+        self.integrity
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    codec::{BincodeCodec, Codec},
+    error::{BookwormError, BookwormResult, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Builds a replacement record from the page index, its raw bytes and the decode error
+pub type Substitute<'a, T> = Box<dyn Fn(usize, &[u8], &BookwormError) -> Option<T> + 'a>;
+
+/// What a scan does with a page that can't be decoded
+pub enum OnDecodeError<'a, T> {
+    /// Yields the error and ends the scan
+    Strict,
+    /// Moves on to the next page, counting the skipped one
+    Skip,
+    /// Yields the replacement built by the closure, skipping the page when it returns `None`
+    Substitute(Substitute<'a, T>),
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Iterates over the decoded pages, ending with an error at the first page that can't be
+    /// read or decoded
+    pub fn try_iter<T: DeserializeOwned>(&mut self) -> DecodeIter<'_, 'static, S, T, C, H> {
+        self.iter_with_policy(OnDecodeError::Strict)
+    }
+    /// Iterates over the decoded pages, handling the ones that can't be decoded as `policy`
+    /// says. Pages that can't be read always end the scan with an error, and so do chained
+    /// pages.
+    pub fn iter_with_policy<'p, T: DeserializeOwned>(
+        &mut self,
+        policy: OnDecodeError<'p, T>,
+    ) -> DecodeIter<'_, 'p, S, T, C, H> {
+        let buf = vec![0; self.pager.page_size];
+        DecodeIter {
+            bookworm: self,
+            policy,
+            curr_pos: 0,
+            buf,
+            skipped: 0,
+            finished: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+pub struct DecodeIter<
+    'a,
+    'p,
+    S: Read + Write + Seek,
+    T: DeserializeOwned,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    policy: OnDecodeError<'p, T>,
+    curr_pos: usize,
+    buf: Vec<u8>,
+    skipped: usize,
+    finished: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<S: Read + Write + Seek, T: DeserializeOwned, C: Codec, H: SharedStorage<S>>
+    DecodeIter<'_, '_, S, T, C, H>
+{
+    /// Number of pages left out because they couldn't be decoded
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl<S, T, C, H> Iterator for DecodeIter<'_, '_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned,
+{
+    type Item = BookwormResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished && self.curr_pos < self.bookworm.pager.pages_count {
+            let page = self.curr_pos;
+            self.curr_pos += 1;
+            if self.bookworm.pager.free.contains(&page) {
+                continue;
+            }
+            let buf = &mut self.buf;
+            let mut decoding = false;
+            let err = match self.bookworm.in_context(OpKind::Scan, |bookworm| {
+                bookworm.check_unchained("decode page by page")?;
+                bookworm
+                    .pager
+                    .read_page_into(page, buf)
+                    .map_err(|err| err.on_read(page))?;
+                decoding = true;
+                bookworm
+                    .pager
+                    .deserialize::<T>(buf)
+                    .map_err(|err| err.on_parse(page))
+            }) {
+                Ok(record) => return Some(Ok(record)),
+                Err(err) if !decoding => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Err(err) => err,
+            };
+            match &self.policy {
+                OnDecodeError::Strict => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                OnDecodeError::Skip => self.skipped += 1,
+                OnDecodeError::Substitute(substitute) => match substitute(page, &self.buf, &err) {
+                    Some(record) => return Some(Ok(record)),
+                    None => self.skipped += 1,
+                },
+            }
+        }
+        None
+    }
+}
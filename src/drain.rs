@@ -0,0 +1,144 @@
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    io::{Read, Seek, Write},
+    marker::PhantomData,
+    ops::Range,
+    rc::Rc,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    codec::{BincodeCodec, Codec},
+    error::{BookwormResult, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Removes the pages in `range`, yielding their records. The gap is closed once the
+    /// iterator is dropped, whether it was consumed or not. The end of the range is clamped to
+    /// the pages count.
+    pub fn drain<T: DeserializeOwned + Debug>(
+        &mut self,
+        range: Range<usize>,
+    ) -> BookwormResult<DrainIter<'_, S, T, C, H>> {
+        self.in_context(OpKind::Delete, |bookworm| {
+            bookworm.check_dense()?;
+            bookworm.check_unchained("drain")?;
+            if range.start != bookworm.pager.pages_count {
+                bookworm.check_page(range.start)?;
+            }
+            Ok(())
+        })?;
+        let end = range.end.min(self.pager.pages_count).max(range.start);
+        self.invalidate_decoded(range.start..);
+        Ok(DrainIter {
+            buf: vec![0; self.pager.page_size],
+            bookworm: self,
+            pages: range.start..end,
+            front: range.start,
+            back: end,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Yields the records of drained pages, shifting the following pages over them when dropped.
+/// Should the shift fail, the bookworm is left poisoned.
+pub struct DrainIter<
+    'a,
+    S: Read + Write + Seek,
+    T: DeserializeOwned + Debug,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    pages: Range<usize>,
+    front: usize,
+    /// One past the next page from the back
+    back: usize,
+    buf: Vec<u8>,
+    _marker: PhantomData<T>,
+}
+
+impl<S, T, C, H> Iterator for DrainIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned + Debug,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let record = self.bookworm.decode_page(self.front, &mut self.buf).ok()?;
+        self.front += 1;
+        Some(record)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<S, T, C, H> ExactSizeIterator for DrainIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned + Debug,
+{
+}
+
+impl<S, T, C, H> DoubleEndedIterator for DrainIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned + Debug,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let record = self
+            .bookworm
+            .decode_page(self.back - 1, &mut self.buf)
+            .ok()?;
+        self.back -= 1;
+        Some(record)
+    }
+}
+
+impl<S, T, C, H> Drop for DrainIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned + Debug,
+{
+    fn drop(&mut self) {
+        if self.pages.is_empty() {
+            return;
+        }
+        let bookworm = &mut *self.bookworm;
+        let pages_count = bookworm.pager.pages_count;
+        if bookworm
+            .move_run(self.pages.end..pages_count, self.pages.start)
+            .is_err()
+        {
+            bookworm.poisoned = true;
+            return;
+        }
+        bookworm.pager.pages_count -= self.pages.len();
+        bookworm.poisoned = bookworm
+            .pager
+            .zero_pages(bookworm.pager.pages_count..pages_count)
+            .is_err();
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, Write},
+};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormResult, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Storages that can push their writes all the way to the disk
+pub trait SyncAll {
+    fn sync_all(&mut self) -> std::io::Result<()>;
+}
+
+impl SyncAll for File {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        File::sync_all(self)
+    }
+}
+
+/// Memory has nothing to sync to
+impl<T> SyncAll for Cursor<T> {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// When the data source is made to hold what operations wrote
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// Writes stay wherever the storage buffers them until the bookworm is closed or dropped
+    #[default]
+    None,
+    /// Flushes the data source after every operation that changes pages
+    FlushEveryWrite,
+    /// Flushes and syncs the data source after every operation that changes pages
+    SyncEveryWrite,
+    /// Only `flush` and `sync_all` make writes durable, dropping doesn't flush either
+    Manual,
+}
+
+impl<S: Read + Write + Seek + SyncAll, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Sets when the data source is made to hold what operations wrote, `Durability::None`
+    /// by default
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self.sync = Some(<S as SyncAll>::sync_all);
+        self
+    }
+    /// Writes back the dirty pages, then flushes and syncs both storages
+    pub fn sync_all(&mut self) -> BookwormResult<()> {
+        self.in_context(OpKind::Flush, |bookworm| {
+            bookworm.flush_storages()?;
+            bookworm.pager.sync_with(<S as SyncAll>::sync_all)?;
+            bookworm.swap.sync_with(<S as SyncAll>::sync_all)
+        })
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Makes the data source hold what an operation of `kind` wrote, as the durability says
+    pub(crate) fn after_write(&mut self, kind: OpKind) -> BookwormResult<()> {
+        if !kind.changes_pages() {
+            return Ok(());
+        }
+        match self.durability {
+            Durability::None | Durability::Manual => Ok(()),
+            Durability::FlushEveryWrite => {
+                self.pager.write_back()?;
+                self.pager.flush()
+            }
+            Durability::SyncEveryWrite => {
+                self.pager.write_back()?;
+                self.pager.flush()?;
+                match self.sync {
+                    Some(sync) => self.pager.sync_with(sync),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
@@ -1,22 +1,205 @@
-use std::error::Error;
+use std::{
+    error::Error,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::checksum::Digest;
+
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    Read,
+    Write,
+    Push,
+    Insert,
+    Pop,
+    Delete,
+    Truncate,
+    Compact,
+    Scan,
+    Flush,
+}
+
+impl OpKind {
+    /// Whether operations of this kind may write pages
+    pub(crate) fn changes_pages(self) -> bool {
+        !matches!(self, OpKind::Read | OpKind::Scan | OpKind::Flush)
+    }
+}
+
+/// Identifies the operation an error was produced in, ids are unique and increase
+/// monotonically over the life of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpContext {
+    id: u64,
+    kind: OpKind,
+}
+
+impl OpContext {
+    pub fn new(kind: OpKind) -> Self {
+        Self {
+            id: NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed),
+            kind,
+        }
+    }
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    pub fn kind(&self) -> OpKind {
+        self.kind
+    }
+}
+
+/// A page whose data doesn't match the checksum stored along with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    pub page: usize,
+    pub stored: Digest,
+    pub computed: Digest,
+}
+
+/// What went wrong, so callers can tell failures apart without matching on messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The page or range asked for is past the last page
+    PageOutOfRange,
+    /// A record doesn't fit in a page
+    DataTooLarge,
+    Serialization,
+    Deserialization,
+    /// The data source or the swap failed to read, write or seek
+    Io,
+    /// Stored bytes don't hold what the layout or format says they should
+    Corrupted,
+    /// The operation can't run with these arguments or in the current state
+    InvalidInput,
+}
 
 #[derive(Debug)]
 pub struct BookwormError {
+    kind: ErrorKind,
     message: String,
+    page: Option<usize>,
+    context: Option<OpContext>,
+    /// Boxed as digests are wide and errors are passed around a lot
+    checksum_mismatch: Option<Box<ChecksumMismatch>>,
+    source: Option<Box<dyn Error + Send + Sync>>,
 }
 
+/// Shows the message followed by the error that caused it, if any
 impl std::fmt::Display for BookwormError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        match &self.source {
+            Some(source) => write!(f, "{}: {}", self.message, source),
+            None => write!(f, "{}", self.message),
+        }
     }
 }
 
 impl BookwormError {
-    pub fn new(message: String) -> Self {
-        Self { message }
+    pub fn new(kind: ErrorKind, message: String) -> Self {
+        Self {
+            kind,
+            message,
+            page: None,
+            context: None,
+            checksum_mismatch: None,
+            source: None,
+        }
+    }
+    /// Keeps the error that caused this one, reachable through `Error::source`
+    pub(crate) fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+    /// Records the page the error happened on
+    pub(crate) fn at_page(mut self, page: usize) -> Self {
+        self.page = Some(page);
+        self
+    }
+    pub(crate) fn checksum_mismatch(page: usize, stored: Digest, computed: Digest) -> Self {
+        Self {
+            page: Some(page),
+            checksum_mismatch: Some(Box::new(ChecksumMismatch {
+                page,
+                stored,
+                computed,
+            })),
+            ..Self::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Checksum mismatch on page {}: stored {} but computed {}",
+                    page, stored, computed
+                ),
+            )
+        }
+    }
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+    /// The page and checksums involved, if this error comes from a failed checksum check
+    pub fn mismatch(&self) -> Option<ChecksumMismatch> {
+        self.checksum_mismatch.as_deref().copied()
+    }
+    /// Names the page a read failed on, unless the error is a checksum mismatch, which
+    /// already does
+    pub(crate) fn on_read(self, page: usize) -> Self {
+        match self.checksum_mismatch {
+            Some(_) => self,
+            None => Self {
+                message: format!("Could not read page {}", page),
+                page: Some(page),
+                ..self
+            },
+        }
+    }
+    /// Names the page a record failed to parse on, keeping what went wrong as the cause
+    pub(crate) fn on_parse(self, page: usize) -> Self {
+        Self {
+            message: format!("Could not parse page {}", page),
+            page: Some(page),
+            ..self
+        }
+    }
+    /// The page the error happened on, when it's known
+    pub fn page(&self) -> Option<usize> {
+        self.page
+    }
+    /// The operation this error was produced in, if it went through the public api
+    pub fn context(&self) -> Option<OpContext> {
+        self.context
+    }
+    /// Attaches the operation context, replacing the one of any inner operation
+    pub(crate) fn with_context(mut self, context: OpContext) -> Self {
+        self.context = Some(context);
+        self
     }
 }
 
-impl Error for BookwormError {}
+impl Error for BookwormError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+impl From<std::io::Error> for BookwormError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(ErrorKind::Io, "Could not access data source".to_string()).with_source(err)
+    }
+}
+
+impl From<bincode::Error> for BookwormError {
+    fn from(err: bincode::Error) -> Self {
+        let kind = match *err {
+            bincode::ErrorKind::Io(_) => ErrorKind::Io,
+            bincode::ErrorKind::SequenceMustHaveLength => ErrorKind::Serialization,
+            _ => ErrorKind::Deserialization,
+        };
+        Self::new(kind, "Could not encode or decode data".to_string()).with_source(err)
+    }
+}
 
 pub type BookwormResult<T> = Result<T, BookwormError>;
@@ -0,0 +1,90 @@
+use std::{
+    cell::RefCell,
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    codec::BincodeCodec,
+    error::{BookwormError, BookwormResult, ErrorKind},
+    Bookworm,
+};
+
+impl Bookworm<File> {
+    /// Opens the file at `path` as a data source without a file header, creating it when
+    /// missing, along with a swap next to it at `path` with a `.swap` extension appended
+    pub fn open_file(path: impl AsRef<Path>, page_size: usize) -> BookwormResult<Self> {
+        let path = path.as_ref();
+        Self::open_with_swap(path, swap_path_for(path), page_size)
+    }
+    /// Same as `Bookworm::open_file` with the swap at `swap_path`. A data file that doesn't
+    /// hold a whole number of pages is refused. Whatever an interrupted operation left in the
+    /// swap is finished before the bookworm is returned.
+    pub fn open_with_swap(
+        path: impl AsRef<Path>,
+        swap_path: impl AsRef<Path>,
+        page_size: usize,
+    ) -> BookwormResult<Self> {
+        if page_size == 0 {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not open data source: page size can't be zero".to_owned(),
+            ));
+        }
+        let path = path.as_ref();
+        let file = open_read_write(path)?;
+        let len = file
+            .metadata()
+            .map(|metadata| metadata.len())
+            .map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Io,
+                    format!("Could not read the length of {}", path.display()),
+                )
+                .with_source(err)
+            })?;
+        if len % page_size as u64 != 0 {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Could not open {}: its {} bytes are not a whole number of {} byte pages",
+                    path.display(),
+                    len,
+                    page_size
+                ),
+            ));
+        }
+        let swap = open_read_write(swap_path.as_ref())?;
+        let mut bookworm = Self::assemble(
+            page_size,
+            Rc::new(RefCell::new(file)),
+            Rc::new(RefCell::new(swap)),
+            BincodeCodec,
+        );
+        bookworm.recover_swap()?;
+        Ok(bookworm)
+    }
+}
+
+/// Where the swap of the data file at `path` lives unless told otherwise
+pub(crate) fn swap_path_for(path: &Path) -> OsString {
+    let mut swap_path = path.as_os_str().to_owned();
+    swap_path.push(".swap");
+    swap_path
+}
+
+/// Opens a file for reading and writing, creating it when missing but keeping its contents
+fn open_read_write(path: &Path) -> BookwormResult<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not open {}", path.display()))
+                .with_source(err)
+        })
+}
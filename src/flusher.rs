@@ -0,0 +1,119 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind},
+    pager::{self, DirtyPages},
+    Bookworm,
+};
+
+/// A thread writing the dirty pages back every interval until it's told to stop or the
+/// bookworm is dropped
+#[derive(Default)]
+pub(crate) struct Flusher {
+    running: Option<(Sender<()>, JoinHandle<()>)>,
+    /// Last error the thread ran into, until it's taken
+    error: Arc<Mutex<Option<BookwormError>>>,
+}
+
+impl Flusher {
+    fn stop(&mut self) {
+        if let Some((stop, thread)) = self.running.take() {
+            let _ = stop.send(());
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Flusher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<S: Read + Write + Seek + Send + 'static, C: Codec> Bookworm<S, C, Arc<Mutex<S>>> {
+    /// Writes the dirty pages back and flushes the data source every `interval` from another
+    /// thread, also syncing it when a durability was set through `with_durability`. Errors are
+    /// kept for `take_flush_error` and the next interval tries again.
+    pub fn start_background_flush(&mut self, interval: Duration) -> BookwormResult<()> {
+        if self.flusher.running.is_some() {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not start flushing: a background flush is already running".to_owned(),
+            ));
+        }
+        let (stop, stopped) = mpsc::channel();
+        let (dirty, data_offset) = self.pager.dirty_handle();
+        let data_source = self.pager.data_source.clone();
+        let page_size = self.pager.page_size;
+        let sync = self.sync;
+        let error = self.flusher.error.clone();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(err) = flush_dirty(&dirty, &data_source, data_offset, page_size, sync) {
+                    *error.lock().unwrap_or_else(PoisonError::into_inner) = Some(err);
+                }
+            }
+        });
+        self.flusher.running = Some((stop, thread));
+        Ok(())
+    }
+    /// Stops the background flush, waiting for the thread to end, then flushes one last time
+    pub fn stop_background_flush(&mut self) -> BookwormResult<()> {
+        self.flusher.stop();
+        self.flush()
+    }
+    pub fn background_flush_running(&self) -> bool {
+        self.flusher.running.is_some()
+    }
+    /// The last error the background flush ran into since it was last taken
+    pub fn take_flush_error(&mut self) -> Option<BookwormError> {
+        self.flusher
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+}
+
+/// Writes the dirty pages back and flushes the data source, putting its stream back where it
+/// was so that the pager and its iterators can keep relying on it
+fn flush_dirty<S: Read + Write + Seek>(
+    dirty: &DirtyPages,
+    data_source: &Arc<Mutex<S>>,
+    data_offset: u64,
+    page_size: usize,
+    sync: Option<fn(&mut S) -> std::io::Result<()>>,
+) -> BookwormResult<()> {
+    let mut dirty = dirty.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut data_source = data_source.lock().unwrap_or_else(PoisonError::into_inner);
+    let io_error = |message: &str| {
+        let message = message.to_owned();
+        move |err| BookwormError::new(ErrorKind::Io, message).with_source(err)
+    };
+    let position = data_source
+        .stream_position()
+        .map_err(io_error("Could not read data source position"))?;
+    let written = pager::write_dirty(&mut dirty, &mut *data_source, data_offset, page_size);
+    let flushed = written.and_then(|_| {
+        data_source
+            .flush()
+            .map_err(io_error("Could not flush data source"))?;
+        match sync {
+            Some(sync) => sync(&mut data_source).map_err(io_error("Could not sync data source")),
+            None => Ok(()),
+        }
+    });
+    data_source
+        .seek(SeekFrom::Start(position))
+        .map_err(io_error("Could not seek data source"))?;
+    flushed
+}
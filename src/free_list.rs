@@ -0,0 +1,97 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::PageLayout,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// With a free list `delete` only zeroes the page and marks it free, pushes fill free
+    /// pages before growing the data source and iterators skip them, so pages no longer keep
+    /// their order. Enabling it marks every page without payload as free, which is how the
+    /// list survives reopening. Operations that shift pages need `compact` to fold the free
+    /// pages first, and the list can only be turned off once it is empty and no live bitmap was
+    /// reserved.
+    pub fn set_free_list(&mut self, enabled: bool) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            if !enabled {
+                if bookworm.bitmap.is_some() {
+                    return Err(BookwormError::new(
+                        ErrorKind::InvalidInput,
+                        "Could not turn the free list off: the store keeps a live bitmap"
+                            .to_owned(),
+                    ));
+                }
+                bookworm.check_dense()?;
+                bookworm.free_list = false;
+                return Ok(());
+            }
+            if bookworm.pager.layout == PageLayout::Chained {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not use a free list: chained pages aren't supported".to_owned(),
+                ));
+            }
+            bookworm.scan_free()?;
+            bookworm.free_list = true;
+            Ok(())
+        })
+    }
+    /// Marks every page without payload as free, reading each of them
+    pub(crate) fn scan_free(&mut self) -> BookwormResult<()> {
+        self.pager.free.clear();
+        for page in 0..self.pager.pages_count {
+            if self.pager.is_slot_empty(page)? {
+                self.pager.free.insert(page);
+            }
+        }
+        self.drop_free_tail()?;
+        self.invalidate_decoded(..);
+        Ok(())
+    }
+    /// Indexes of the free pages, in ascending order
+    pub fn free_pages(&self) -> Vec<usize> {
+        self.pager.free.iter().copied().collect()
+    }
+    pub(crate) fn free_page(&mut self, page: usize) -> BookwormResult<()> {
+        self.check_page(page)?;
+        self.invalidate_decoded(page..=page);
+        self.pager.zero_page(page)?;
+        self.pager.free.insert(page);
+        self.drop_free_tail()
+    }
+    /// Writes already serialized data into a free page, taking it off the list
+    pub(crate) fn reuse_free(&mut self, page: usize, data: &[u8]) -> BookwormResult<()> {
+        self.invalidate_decoded(page..=page);
+        self.pager.write_raw_page(page, data)?;
+        self.pager.free.remove(&page);
+        Ok(())
+    }
+    /// Pops the free pages at the end, so the data source doesn't hold on to them
+    pub(crate) fn drop_free_tail(&mut self) -> BookwormResult<()> {
+        while let Some(&last) = self.pager.free.last() {
+            if last + 1 != self.pager.pages_count {
+                break;
+            }
+            self.pager.free.pop_last();
+            self.pager.pop()?;
+        }
+        Ok(())
+    }
+    /// Refuses operations that shift pages around while some are free
+    pub(crate) fn check_dense(&self) -> BookwormResult<()> {
+        if !self.pager.free.is_empty() {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Could not move pages: {} pages are free, compact them first",
+                    self.pager.free.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    codec::{BincodeCodec, Codec},
+    error::{BookwormResult, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Decodes a page into a guard that writes it back once it's dropped, if it was mutated.
+    /// Reading and writing back go through the same paths as `get_page` and `set`.
+    pub fn get_mut<T: Serialize + DeserializeOwned>(
+        &mut self,
+        page: usize,
+    ) -> BookwormResult<PageGuard<'_, T, S, C, H>> {
+        #[cfg(feature = "wal")]
+        let value = match self.wal_mode() {
+            true => self.in_logged_context(OpKind::Read, |bookworm| bookworm.logged_page(page))?,
+            false => self.in_context(OpKind::Read, |bookworm| bookworm.pager.get_page(page))?,
+        };
+        #[cfg(not(feature = "wal"))]
+        let value = self.in_context(OpKind::Read, |bookworm| bookworm.pager.get_page(page))?;
+        Ok(PageGuard {
+            bookworm: self,
+            page,
+            value,
+            dirty: false,
+        })
+    }
+}
+
+/// A decoded page that gets written back when dropped. Dropping can't report failures, so a
+/// write back that fails there poisons the bookworm, use `commit` to get the error instead.
+pub struct PageGuard<
+    'a,
+    T: Serialize,
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    page: usize,
+    value: T,
+    /// Set on any mutable access, whether or not the value actually changed
+    dirty: bool,
+}
+
+impl<T: Serialize, S: Read + Write + Seek, C: Codec, H: SharedStorage<S>>
+    PageGuard<'_, T, S, C, H>
+{
+    /// Writes the page back if it was mutated
+    pub fn commit(mut self) -> BookwormResult<()> {
+        self.write_back()
+    }
+    fn write_back(&mut self) -> BookwormResult<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.dirty = false;
+        self.bookworm.set(self.page, &self.value)
+    }
+}
+
+impl<T: Serialize, S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Deref
+    for PageGuard<'_, T, S, C, H>
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Serialize, S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> DerefMut
+    for PageGuard<'_, T, S, C, H>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty = true;
+        &mut self.value
+    }
+}
+
+impl<T: Serialize, S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Drop
+    for PageGuard<'_, T, S, C, H>
+{
+    fn drop(&mut self) {
+        if self.write_back().is_err() {
+            self.bookworm.poisoned = true;
+        }
+    }
+}
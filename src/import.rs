@@ -0,0 +1,111 @@
+use std::io::{Read, Seek, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::PageLayout,
+    storage::SharedStorage,
+    Bookworm, COPY_BATCH_PAGES,
+};
+
+/// How the records read by `import_from` are framed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Records in the format of the bookworm's codec, each preceded by its length as a little
+    /// endian u32
+    LengthPrefixed,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Appends every record read from `src`, decoding each as a `T` and writing them a batch of
+    /// pages at a time. Returns how many were imported. A record that can't be read, decoded
+    /// or doesn't fit in a page fails the import naming its index in the stream, the records
+    /// before it stay imported.
+    pub fn import_from<T: Serialize + DeserializeOwned, R: Read>(
+        &mut self,
+        mut src: R,
+        format: ImportFormat,
+    ) -> BookwormResult<usize> {
+        let ImportFormat::LengthPrefixed = format;
+        self.in_context(OpKind::Push, |bookworm| {
+            let page_size = bookworm.pager.page_size;
+            let start = bookworm.pager.pages_count;
+            bookworm.invalidate_decoded(start..);
+            let mut staged = Vec::with_capacity(page_size * COPY_BATCH_PAGES);
+            let mut imported = 0;
+            loop {
+                let record = match read_record::<T, _, _, _, _>(bookworm, &mut src) {
+                    Ok(Some(record)) => record,
+                    Ok(None) => break,
+                    Err(err) => {
+                        bookworm.pager.append_pages(&staged)?;
+                        return Err(BookwormError::new(
+                            err.kind(),
+                            format!("Could not import record {}", imported),
+                        )
+                        .with_source(err));
+                    }
+                };
+                let serialized = bookworm.pager.serialize(&record)?;
+                bookworm.pager.stage_page(&serialized, &mut staged)?;
+                imported += 1;
+                if staged.len() >= page_size * COPY_BATCH_PAGES {
+                    bookworm.pager.append_pages(&staged)?;
+                    staged.clear();
+                }
+            }
+            bookworm.pager.append_pages(&staged)?;
+            Ok(imported)
+        })
+    }
+}
+
+/// Reads and decodes the next length prefixed record, `None` once `src` ends between records
+fn read_record<T, S, C, H, R>(
+    bookworm: &Bookworm<S, C, H>,
+    src: &mut R,
+) -> BookwormResult<Option<T>>
+where
+    T: DeserializeOwned,
+    S: Read + Write + Seek,
+    C: Codec,
+    H: SharedStorage<S>,
+    R: Read,
+{
+    let read_error = |err| {
+        BookwormError::new(ErrorKind::Io, "Stream could not be read".to_owned()).with_source(err)
+    };
+    let mut prefix = [0; 4];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match src.read(&mut prefix[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Stream ends within a record length".to_owned(),
+                ))
+            }
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(read_error(err)),
+        }
+    }
+    let len = u32::from_le_bytes(prefix) as usize;
+    let pager = &bookworm.pager;
+    if pager.layout != PageLayout::Chained && len > pager.payload_capacity() {
+        return Err(BookwormError::new(
+            ErrorKind::DataTooLarge,
+            format!(
+                "Record is {} bytes long but pages hold {}",
+                len,
+                pager.payload_capacity()
+            ),
+        ));
+    }
+    let mut record = vec![0; len];
+    src.read_exact(&mut record).map_err(read_error)?;
+    pager.decode_record(&record).map(Some)
+}
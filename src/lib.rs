@@ -2,109 +2,1769 @@
 pub mod tests;
 
 use std::{
+    any::TypeId,
     cell::RefCell,
-    fmt::Debug,
+    fmt::{Debug, Display},
     io::{Read, Seek, Write},
+    marker::PhantomData,
+    ops::{Bound, Range, RangeBounds},
+    panic::{self, AssertUnwindSafe},
     rc::Rc,
+    sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
-use error::BookwormResult;
+use cache::PageCache;
+use error::{BookwormError, BookwormResult, ErrorKind, OpContext, OpKind};
 use pager::{Pager, PagerIterator, RawPagerIterator};
-use serde::{de::DeserializeOwned, ser::Serialize};
+use recovery::{DeleteMarker, DELETE_MARKER_BYTES};
 
+pub use bitmap::BitmapIter;
+pub use checksum::{Checksum, ChecksumAlgorithm, Crc32, Digest, Sha256, XxHash64};
+#[cfg(feature = "varint-codec")]
+pub use codec::VarintCodec;
+pub use codec::{BincodeCodec, Codec};
+pub use compact::CompactReport;
+pub use count::{CountCheck, CountSource, OpenIntegrity};
+pub use decode::{DecodeIter, OnDecodeError};
+pub use drain::DrainIter;
+pub use durability::{Durability, SyncAll};
+pub use guard::PageGuard;
+pub use import::ImportFormat;
+pub use manifest::{DigestAlgorithm, Manifest, ManifestDiff};
+pub use pager::{
+    FalliblePagerIter, FallibleRawPagerIter, FileHeader, FillSummary, PageFill, PageInfo,
+    PageLayout, PagerIter, RawPagerIter,
+};
+#[cfg(unix)]
+pub use parallel::parallel_load;
+pub use recovery::{RecoveryAction, RecoveryReport};
+pub use refresh::RefreshOutcome;
+pub use sequence::SequenceIter;
+use serde::{
+    de::{Deserialize, DeserializeOwned},
+    ser::Serialize,
+};
+pub use spill::OversizePolicy;
+pub use storage::SharedStorage;
+pub use transaction::Transaction;
+pub use truncate::Truncate;
+pub use ttl::UnexpiredIter;
+pub use verify::{Check, PageVerification};
+pub use view::PagesView;
+#[cfg(feature = "wal")]
+pub use wal::CheckpointReport;
+
+mod bitmap;
+mod cache;
+mod checksum;
+mod codec;
+mod compact;
+mod count;
+mod decode;
+mod drain;
+mod durability;
 pub mod error;
+mod file;
+#[cfg(feature = "background-flush")]
+mod flusher;
+mod free_list;
+mod guard;
+mod import;
+mod manifest;
 mod pager;
+#[cfg(unix)]
+mod parallel;
+mod recovery;
+mod refresh;
+mod repage;
+mod search;
+mod sequence;
+mod snapshot;
+mod sort;
+mod spill;
+mod storage;
+mod transaction;
+mod truncate;
+mod ttl;
+mod verify;
+mod view;
+#[cfg(feature = "wal")]
+mod wal;
+
+/// Pages moved per read and vectored write when shifting runs of pages
+const COPY_BATCH_PAGES: usize = 64;
+/// Pages an external sort holds in memory unless configured otherwise
+const DEFAULT_SORT_MEMORY_PAGES: usize = 1024;
+/// Bytes `delete` stages in memory instead of the swap unless configured otherwise
+const DEFAULT_SHIFT_MEMORY_BYTES: usize = 4 << 20;
+/// Decoded values `get_page_cached` keeps around unless configured otherwise
+const DEFAULT_DECODED_CACHE_ENTRIES: usize = 256;
+
+pub struct Bookworm<
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    pager: Pager<S, C, H>,
+    swap: Pager<S, C, H>,
+    decoded_cache: PageCache<(usize, TypeId), H::Decoded>,
+    metrics: Metrics,
+    clock: Box<dyn Fn() -> SystemTime + Send>,
+    closed: bool,
+    /// Set while pages are being rewritten in place, so an interrupted rewrite blocks any
+    /// further use instead of being silently built upon
+    poisoned: bool,
+    /// Pages `sort_by_key` may hold in memory at once
+    sort_memory: usize,
+    /// Whether `delete` frees pages instead of shifting the following ones
+    free_list: bool,
+    /// Most bytes `delete` stages in memory before falling back to the swap
+    shift_memory: usize,
+    /// Whether `get_page` checks the page the way `verify_page` does first
+    verify_on_read: bool,
+    /// First page of every record when spilling oversized records, up to `indexed_pages`
+    record_starts: Vec<usize>,
+    indexed_pages: usize,
+    /// What opening found about the pages count, for stores with a file header
+    integrity: Option<OpenIntegrity>,
+    /// Live pages as stored after the file header, when pages were reserved for them
+    bitmap: Option<bitmap::LiveBitmap>,
+    durability: Durability,
+    /// Syncs the data source, only known once the storage turned out to support it
+    sync: Option<fn(&mut S) -> std::io::Result<()>>,
+    #[cfg(feature = "wal")]
+    wal: Option<wal::Wal>,
+    /// Log size in pages past which appending an entry checkpoints
+    #[cfg(feature = "wal")]
+    wal_checkpoint_pages: Option<usize>,
+    #[cfg(feature = "background-flush")]
+    flusher: flusher::Flusher,
+}
 
-pub struct Bookworm<S: Read + Write + Seek> {
-    pager: Pager<S>,
-    swap: Pager<S>,
-    page_size: usize,
+/// Counters describing the work done by a bookworm since it was created
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    /// Largest number of pages a single operation staged in the swap
+    pub peak_swap_pages: usize,
+    /// Seeks an append mode push had to issue because the stream was moved since the last one
+    pub corrective_seeks: usize,
+    /// Vectored writes issued while moving runs of pages around
+    pub vectored_batches: usize,
+    /// Page reads served by the page cache and the ones that had to go to the data source
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    /// Transaction journals found committed in the swap and applied when opening
+    pub replayed_journals: usize,
 }
-impl<'a, S: Read + Write + Seek> Bookworm<S> {
+/// Size figures of a bookworm and its storages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookwormStats {
+    pub pages: usize,
+    pub page_size: usize,
+    /// Byte length of the data source, zeroed slots past the pages included
+    pub storage_bytes: u64,
+    pub swap_pages: usize,
+    pub swap_bytes: u64,
+}
+
+impl<S: Read + Write + Seek> Bookworm<S> {
+    /// Opens a data source without a file header, the way stores were written before headers
+    /// existed, see `Bookworm::open`
     pub fn new(page_size: usize, data_source: Rc<RefCell<S>>, swap: Rc<RefCell<S>>) -> Self {
-        Self {
+        Self::with_codec(page_size, data_source, swap, BincodeCodec)
+    }
+    /// Opens a data source starting with a file header, writing it when the data source is
+    /// empty. Data sources that aren't bookworm files, or were written with another format
+    /// version, page size or layout, are refused. A pages count the header records wins over
+    /// the file length, see `open_integrity`.
+    pub fn open(
+        page_size: usize,
+        layout: PageLayout,
+        data_source: Rc<RefCell<S>>,
+        swap: Rc<RefCell<S>>,
+    ) -> BookwormResult<Self> {
+        Self::open_with_codec(page_size, layout, data_source, swap, BincodeCodec)
+    }
+    /// Opens a data source that already starts with a file header, taking the page size and
+    /// layout it records instead of being told them
+    pub fn open_existing(
+        data_source: Rc<RefCell<S>>,
+        swap: Rc<RefCell<S>>,
+    ) -> BookwormResult<Self> {
+        let header = FileHeader::read_from(&mut *data_source.borrow_mut())?;
+        Self::open(header.page_size, header.layout, data_source, swap)
+    }
+    /// Creates a bookworm over an empty data source already sized for `pages` pages
+    pub fn with_capacity(
+        page_size: usize,
+        pages: usize,
+        data_source: Rc<RefCell<S>>,
+        swap: Rc<RefCell<S>>,
+    ) -> BookwormResult<Self> {
+        let mut bookworm = Self::new(page_size, data_source, swap);
+        bookworm.pager.preallocate(pages)?;
+        Ok(bookworm)
+    }
+}
+
+impl<S: Read + Write + Seek> Bookworm<S, BincodeCodec, Arc<Mutex<S>>> {
+    /// Same as `Bookworm::new` over storages behind a mutex, so the bookworm can be sent to
+    /// another thread
+    pub fn new_shared(page_size: usize, data_source: Arc<Mutex<S>>, swap: Arc<Mutex<S>>) -> Self {
+        Self::with_codec(page_size, data_source, swap, BincodeCodec)
+    }
+    /// Same as `Bookworm::open` over storages behind a mutex
+    pub fn open_shared(
+        page_size: usize,
+        layout: PageLayout,
+        data_source: Arc<Mutex<S>>,
+        swap: Arc<Mutex<S>>,
+    ) -> BookwormResult<Self> {
+        Self::open_with_codec(page_size, layout, data_source, swap, BincodeCodec)
+    }
+    /// Same as `Bookworm::new_shared` over storages nothing else holds, which
+    /// `into_owned` gives back
+    pub fn from_owned(page_size: usize, data_source: S, swap: S) -> Self {
+        Self::new_shared(
             page_size,
-            pager: Pager::new(page_size, data_source),
-            swap: Pager::new(page_size, swap),
+            Arc::new(Mutex::new(data_source)),
+            Arc::new(Mutex::new(swap)),
+        )
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec> Bookworm<S, C, Rc<RefCell<S>>> {
+    /// Reads a page and keeps the decoded value around until the page is written or the value
+    /// is evicted, repeated reads share the same allocation
+    pub fn get_page_cached<T: DeserializeOwned + 'static>(
+        &mut self,
+        page: usize,
+    ) -> BookwormResult<Rc<T>> {
+        self.cached_page(
+            page,
+            |cached| cached.downcast().ok(),
+            |value: T| {
+                let value = Rc::new(value);
+                (value.clone(), value)
+            },
+        )
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec> Bookworm<S, C, Arc<Mutex<S>>> {
+    /// Same as `get_page_cached` over `Rc<RefCell<_>>` storages, sharing the value through an
+    /// `Arc` so the bookworm can still be sent to another thread
+    pub fn get_page_cached<T: DeserializeOwned + Send + Sync + 'static>(
+        &mut self,
+        page: usize,
+    ) -> BookwormResult<Arc<T>> {
+        self.cached_page(
+            page,
+            |cached| cached.downcast().ok(),
+            |value: T| {
+                let value = Arc::new(value);
+                (value.clone(), value)
+            },
+        )
+    }
+    /// Hands the data source and the swap back, or the bookworm if a storage is still shared,
+    /// which one given to `Bookworm::from_owned` never is
+    #[allow(clippy::result_large_err)]
+    pub fn into_owned(self) -> Result<(S, S), Self> {
+        self.try_unwrap_inner()
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Creates a bookworm storing its records in the format of `codec`, which must match the
+    /// one the data source was written with. A committed transaction journal left in the swap
+    /// is replayed, a replay that fails poisons the bookworm.
+    pub fn with_codec(page_size: usize, data_source: H, swap: H, codec: C) -> Self {
+        let mut bookworm = Self::assemble(page_size, data_source, swap, codec);
+        if bookworm.recover_swap().is_err() {
+            bookworm.poisoned = true;
+        }
+        bookworm
+    }
+    fn assemble(page_size: usize, data_source: H, swap: H, codec: C) -> Self {
+        let mut swap = Pager::with_codec(page_size, swap, codec.clone());
+        swap.reserve_front(DELETE_MARKER_BYTES);
+        swap.clear();
+        Self {
+            pager: Pager::with_codec(page_size, data_source, codec),
+            swap,
+            decoded_cache: PageCache::new(DEFAULT_DECODED_CACHE_ENTRIES),
+            metrics: Metrics::default(),
+            clock: Box::new(SystemTime::now),
+            closed: false,
+            poisoned: false,
+            sort_memory: DEFAULT_SORT_MEMORY_PAGES,
+            free_list: false,
+            shift_memory: DEFAULT_SHIFT_MEMORY_BYTES,
+            verify_on_read: false,
+            record_starts: Vec::new(),
+            indexed_pages: 0,
+            integrity: None,
+            bitmap: None,
+            durability: Durability::default(),
+            sync: None,
+            #[cfg(feature = "wal")]
+            wal: None,
+            #[cfg(feature = "wal")]
+            wal_checkpoint_pages: None,
+            #[cfg(feature = "background-flush")]
+            flusher: flusher::Flusher::default(),
+        }
+    }
+    /// Same as `Bookworm::open`, storing records in the format of `codec`
+    pub fn open_with_codec(
+        page_size: usize,
+        layout: PageLayout,
+        data_source: H,
+        swap: H,
+        codec: C,
+    ) -> BookwormResult<Self> {
+        let mut bookworm = Self::assemble(page_size, data_source, swap, codec);
+        bookworm.set_layout(layout);
+        bookworm.integrity = Some(bookworm.pager.open_header()?);
+        bookworm.recover_swap()?;
+        bookworm.pager.record_count()?;
+        bookworm.load_bitmap()?;
+        bookworm.index_records(OpKind::Read);
+        Ok(bookworm)
+    }
+    /// Keeps the `capacity_pages` most recently read pages in memory, so reading them again
+    /// skips the data source. Writes drop the cached copies of the pages they touch.
+    pub fn with_cache(mut self, capacity_pages: usize) -> Self {
+        self.pager.enable_cache(capacity_pages);
+        self
+    }
+    /// Number of pages held by the page cache
+    pub fn cached_pages(&self) -> usize {
+        self.pager.cached_pages()
+    }
+    /// Number of pages, leaving out the free ones
+    pub fn len(&self) -> usize {
+        #[cfg(feature = "wal")]
+        if let Some(len) = self.logged_len() {
+            return len;
         }
+        if self.spilling() {
+            return self.record_starts.len();
+        }
+        self.pager.pages_count - self.pager.free.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn page_size(&self) -> usize {
+        self.pager.page_size
+    }
+    /// Byte length of the data source, read without moving its stream
+    pub fn storage_bytes(&mut self) -> BookwormResult<u64> {
+        self.in_logged_context(OpKind::Read, |bookworm| bookworm.pager.stored_bytes())
+    }
+    pub fn stats(&mut self) -> BookwormResult<BookwormStats> {
+        self.in_context(OpKind::Read, |bookworm| {
+            Ok(BookwormStats {
+                pages: bookworm.pager.pages_count,
+                page_size: bookworm.pager.page_size,
+                storage_bytes: bookworm.pager.stored_bytes()?,
+                swap_pages: bookworm.swap.pages_count,
+                swap_bytes: bookworm.swap.stored_bytes()?,
+            })
+        })
+    }
+    /// Number of pages the data source can hold without growing
+    pub fn capacity(&self) -> usize {
+        self.pager.capacity.max(self.pager.pages_count)
+    }
+    pub fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.pager.pages_count
+    }
+    pub fn metrics(&self) -> Metrics {
+        let (cache_hits, cache_misses) = self.pager.cache_counts();
+        Metrics {
+            corrective_seeks: self.pager.corrective_seeks,
+            vectored_batches: self.pager.vectored_batches + self.swap.vectored_batches,
+            cache_hits,
+            cache_misses,
+            ..self.metrics
+        }
+    }
+    /// Raises or lowers how many bytes decoding a single record may read, the page size by
+    /// default
+    pub fn set_decode_limit(&mut self, bytes: u64) {
+        self.pager.decode_limit = bytes;
+    }
+    /// Bounds how many pages `sort_by_key` holds in memory, which is also the length of the
+    /// runs it sorts in memory and how many of them it merges at once. At least two are used.
+    pub fn set_sort_memory(&mut self, pages: usize) {
+        self.sort_memory = pages.max(2);
+    }
+    /// Bounds how many bytes of following pages `delete` keeps in memory to copy them back
+    /// from, shifts moving more than that read them back from the swap. The swap gets a copy
+    /// either way, so that an interrupted delete can be finished.
+    pub fn set_shift_memory(&mut self, bytes: usize) {
+        self.shift_memory = bytes;
+    }
+    /// Sets how data is laid out within pages, for the data source and the swap alike. Pages
+    /// are padded by default, a store must keep being opened with the layout it was written
+    /// with.
+    pub fn set_layout(&mut self, layout: PageLayout) {
+        self.pager.layout = layout;
+        self.swap.layout = layout;
+        self.invalidate_decoded(..);
+    }
+    /// In append mode pushes write each page with a single write and skip seeking to the tail
+    /// while the stream is still where the previous push left it
+    pub fn append_mode(&mut self, enabled: bool) {
+        self.pager.append_mode = enabled;
     }
     pub fn get_page<T: DeserializeOwned + Debug>(&mut self, page: usize) -> BookwormResult<T> {
-        self.pager.get_page(page)
+        let page = self.record_page(page);
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| {
+                bookworm.verify_read(page)?;
+                bookworm.logged_page(page)
+            });
+        }
+        self.in_context(OpKind::Read, |bookworm| {
+            bookworm.verify_read(page)?;
+            bookworm.pager.get_page(page)
+        })
+    }
+    /// Reads a page into `buf` and decodes a record borrowing its strings and byte slices from
+    /// there, reusing the buffer across reads saves every allocation
+    pub fn get_page_ref<'de, T: Deserialize<'de>>(
+        &mut self,
+        page: usize,
+        buf: &'de mut Vec<u8>,
+    ) -> BookwormResult<T> {
+        let page = self.record_page(page);
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self
+                .in_logged_context(OpKind::Read, |bookworm| bookworm.logged_page_ref(page, buf));
+        }
+        self.in_context(OpKind::Read, |bookworm| {
+            bookworm.pager.get_page_ref(page, buf)
+        })
+    }
+    /// Bounds how many decoded values `get_page_cached` keeps around, dropping the least
+    /// recently read ones past it
+    pub fn set_decoded_cache_capacity(&mut self, entries: usize) {
+        self.decoded_cache.set_capacity(entries);
+    }
+    /// Looks a decoded value up in the cache, decoding the page and caching it on a miss.
+    /// `share` wraps a freshly decoded value into what gets returned and what gets cached.
+    fn cached_page<T: DeserializeOwned + 'static, P>(
+        &mut self,
+        page: usize,
+        downcast: impl FnOnce(H::Decoded) -> Option<P>,
+        share: impl FnOnce(T) -> (P, H::Decoded),
+    ) -> BookwormResult<P> {
+        let page = self.record_page(page);
+        let read = |bookworm: &mut Self| {
+            let key = (page, TypeId::of::<T>());
+            if let Some(value) = bookworm.decoded_cache.get(key).cloned().and_then(downcast) {
+                return Ok(value);
+            }
+            #[cfg(feature = "wal")]
+            let value = match bookworm.wal_mode() {
+                true => bookworm.logged_page(page)?,
+                false => bookworm.pager.get_page(page)?,
+            };
+            #[cfg(not(feature = "wal"))]
+            let value = bookworm.pager.get_page(page)?;
+            let (value, cached) = share(value);
+            bookworm.decoded_cache.insert(key, cached);
+            Ok(value)
+        };
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, read);
+        }
+        self.in_context(OpKind::Read, read)
     }
     pub fn get_raw_page(&mut self, page: usize) -> BookwormResult<Vec<u8>> {
-        self.pager.get_raw_page(page)
+        let page = self.record_page(page);
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| bookworm.logged_raw_page(page));
+        }
+        self.in_context(OpKind::Read, |bookworm| bookworm.pager.get_raw_page(page))
+    }
+    pub fn first<T: DeserializeOwned + Debug>(&mut self) -> BookwormResult<Option<T>> {
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| match bookworm.len() {
+                0 => Ok(None),
+                _ => bookworm.logged_page(0).map(Some),
+            });
+        }
+        self.in_context(OpKind::Read, |bookworm| match bookworm.first_live_page() {
+            None => Ok(None),
+            Some(page) => bookworm.pager.get_page(page).map(Some),
+        })
+    }
+    pub fn last<T: DeserializeOwned + Debug>(&mut self) -> BookwormResult<Option<T>> {
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| match bookworm.len() {
+                0 => Ok(None),
+                len => bookworm.logged_page(len - 1).map(Some),
+            });
+        }
+        self.in_context(OpKind::Read, |bookworm| {
+            match bookworm.last_live_page()? {
+                None => Ok(None),
+                Some(page) => bookworm.pager.get_page(page).map(Some),
+            }
+        })
+    }
+    pub fn first_raw(&mut self) -> BookwormResult<Option<Vec<u8>>> {
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| match bookworm.len() {
+                0 => Ok(None),
+                _ => bookworm.logged_raw_page(0).map(Some),
+            });
+        }
+        self.in_context(OpKind::Read, |bookworm| match bookworm.first_live_page() {
+            None => Ok(None),
+            Some(page) => bookworm.pager.get_raw_page(page).map(Some),
+        })
+    }
+    pub fn last_raw(&mut self) -> BookwormResult<Option<Vec<u8>>> {
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| match bookworm.len() {
+                0 => Ok(None),
+                len => bookworm.logged_raw_page(len - 1).map(Some),
+            });
+        }
+        self.in_context(OpKind::Read, |bookworm| {
+            match bookworm.last_live_page()? {
+                None => Ok(None),
+                Some(page) => bookworm.pager.get_raw_page(page).map(Some),
+            }
+        })
+    }
+    fn first_live_page(&self) -> Option<usize> {
+        let pages_count = self.pager.pages_count;
+        Some(pager::first_live(&self.pager.free, 0, pages_count)).filter(|page| *page < pages_count)
+    }
+    /// Start of the last record that isn't free
+    fn last_live_page(&mut self) -> BookwormResult<Option<usize>> {
+        match pager::last_live_end(&self.pager.free, 0, self.pager.pages_count) {
+            0 => Ok(None),
+            end => self.pager.record_start(end).map(Some),
+        }
+    }
+    /// Reads a page into the start of `buf` without allocating, returning how many bytes
+    /// were filled
+    pub fn get_raw_page_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<usize> {
+        let page = self.record_page(page);
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, |bookworm| {
+                bookworm.logged_raw_page_into(page, buf)
+            });
+        }
+        self.in_context(OpKind::Read, |bookworm| {
+            bookworm.pager.get_raw_page_into(page, buf)
+        })
+    }
+    /// Reads a page decoding it with `decode` instead of the default format
+    pub fn get_page_with<T, E, F>(&mut self, page: usize, decode: F) -> BookwormResult<T>
+    where
+        F: FnOnce(&[u8]) -> Result<T, E>,
+        E: Display,
+    {
+        self.in_context(OpKind::Read, |bookworm| {
+            let raw_page = bookworm.pager.get_raw_page(page)?;
+            decode(&raw_page).map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Deserialization,
+                    format!("Could not decode page {}: {}", page, err),
+                )
+            })
+        })
+    }
+    /// Pushes the bytes produced by `encode` instead of serializing with the default format
+    pub fn push_with<E, F>(&mut self, encode: F) -> BookwormResult<()>
+    where
+        F: FnOnce() -> Result<Vec<u8>, E>,
+        E: Display,
+    {
+        self.in_context(OpKind::Push, |bookworm| {
+            let page = bookworm.pager.pages_count;
+            let data = encode().map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Serialization,
+                    format!("Could not encode page {}: {}", page, err),
+                )
+            })?;
+            bookworm.invalidate_decoded(page..);
+            bookworm.pager.push_raw(&data)
+        })
+    }
+    /// Iterates over the metadata of every page without reading the payloads
+    pub fn page_info_iter(&mut self) -> impl Iterator<Item = BookwormResult<PageInfo>> + '_ {
+        (0..self.len()).map(move |page| {
+            self.in_context(OpKind::Read, |bookworm| bookworm.pager.page_info(page))
+        })
+    }
+    /// Reads `len` bytes at `offset` within the payload of a page without touching the rest of it
+    pub fn read_at(&mut self, page: usize, offset: usize, len: usize) -> BookwormResult<Vec<u8>> {
+        self.in_context(OpKind::Read, |bookworm| {
+            bookworm.pager.read_at(page, offset, len)
+        })
+    }
+    /// Overwrites the bytes at `offset` within the payload of a page, leaving the rest untouched
+    pub fn write_at(&mut self, page: usize, offset: usize, data: &[u8]) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.pager.write_at(page, offset, data)?;
+            bookworm.invalidate_decoded(page..=page);
+            Ok(())
+        })
+    }
+    pub fn page_fill(&mut self, page: usize) -> BookwormResult<PageFill> {
+        self.in_context(OpKind::Read, |bookworm| bookworm.pager.page_fill(page))
+    }
+    /// Aggregates the fill level of every page in a single pass
+    pub fn fill_summary(&mut self) -> BookwormResult<FillSummary> {
+        self.in_context(OpKind::Scan, Self::summarize_fill)
+    }
+    fn summarize_fill(&mut self) -> BookwormResult<FillSummary> {
+        let mut buf = vec![0; self.pager.page_size];
+        let mut summary = FillSummary {
+            pages: self.pager.pages_count,
+            min: 0,
+            avg: 0.0,
+            max: 0,
+            exact: true,
+        };
+        let mut total = 0;
+        for page in 0..self.pager.pages_count {
+            self.pager.read_page_into(page, &mut buf)?;
+            let fill = self.pager.fill_of(&buf);
+            summary.min = if page == 0 {
+                fill.payload_bytes
+            } else {
+                summary.min.min(fill.payload_bytes)
+            };
+            summary.max = summary.max.max(fill.payload_bytes);
+            summary.exact &= fill.exact;
+            total += fill.payload_bytes;
+        }
+        if summary.pages > 0 {
+            summary.avg = total as f64 / summary.pages as f64;
+        }
+        Ok(summary)
+    }
+    /// Whether a page holds no payload, popped and deleted slots that are still physically
+    /// present read as empty. See `Pager::is_slot_empty` for the headerless ambiguity.
+    pub fn is_page_empty(&mut self, page: usize) -> BookwormResult<bool> {
+        self.in_context(OpKind::Read, |bookworm| bookworm.pager.is_slot_empty(page))
     }
-    pub fn into_raw_iter(self) -> RawPageIterator<S> {
+    /// Finds the first empty page at or after `from` among the physically present pages
+    pub fn find_first_empty_page(&mut self, from: usize) -> BookwormResult<Option<usize>> {
+        self.in_context(OpKind::Scan, |bookworm| {
+            let mut buf = vec![0; bookworm.pager.page_size];
+            for page in from..bookworm.capacity() {
+                bookworm.pager.read_slot_into(page, &mut buf)?;
+                if bookworm.pager.fill_of(&buf).payload_bytes == 0 {
+                    return Ok(Some(page));
+                }
+            }
+            Ok(None)
+        })
+    }
+    /// Decodes every page into a vec, failing with the index of the first page that can't be
+    /// read or decoded instead of stopping short like the iterators do
+    pub fn to_vec<T: DeserializeOwned>(&mut self) -> BookwormResult<Vec<T>> {
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Scan, |bookworm| {
+                (0..bookworm.len())
+                    .map(|page| bookworm.logged_page(page))
+                    .collect()
+            });
+        }
+        self.in_context(OpKind::Scan, |bookworm| {
+            if bookworm.spilling() {
+                let starts = bookworm.record_starts.clone();
+                return starts
+                    .into_iter()
+                    .map(|page| bookworm.pager.get_page(page))
+                    .collect();
+            }
+            bookworm.check_unchained("decode page by page")?;
+            let mut records = Vec::with_capacity(bookworm.pager.pages_count);
+            let mut buf = vec![0; bookworm.pager.page_size];
+            for page in 0..bookworm.pager.pages_count {
+                if !bookworm.pager.free.contains(&page) {
+                    records.push(bookworm.decode_page(page, &mut buf)?);
+                }
+            }
+            Ok(records)
+        })
+    }
+    /// Collects every page as raw bytes, padding included unless pages are length prefixed
+    pub fn to_raw_vec(&mut self) -> BookwormResult<Vec<Vec<u8>>> {
+        self.in_context(OpKind::Scan, |bookworm| {
+            (0..bookworm.pager.pages_count)
+                .map(|page| {
+                    bookworm
+                        .pager
+                        .get_raw_page(page)
+                        .map_err(|err| err.on_read(page))
+                })
+                .collect()
+        })
+    }
+    /// Iterates over the decoded pages from `start` while keeping the bookworm around, ending
+    /// at the pages count or at the first page that can't be decoded
+    pub fn iter<T: DeserializeOwned + Debug>(&mut self, start: usize) -> PagerIter<'_, S, T, C, H> {
+        let start = self.iter_start(start);
+        self.pager.iter(start)
+    }
+    /// Iterates over the raw pages from `start` while keeping the bookworm around
+    pub fn raw_iter(&mut self, start: usize) -> RawPagerIter<'_, S, C, H> {
+        let start = self.iter_start(start);
+        self.pager.raw_iter(start)
+    }
+    /// Iterates over the decoded pages from `start`, yielding an error for each page that can't
+    /// be read or decoded instead of stopping there
+    pub fn iter_fallible<T: DeserializeOwned>(
+        &mut self,
+        start: usize,
+    ) -> FalliblePagerIter<'_, S, T, C, H> {
+        let start = self.iter_start(start);
+        self.pager.iter_fallible(start)
+    }
+    /// Same as `iter_fallible`, yielding raw pages
+    pub fn raw_iter_fallible(&mut self, start: usize) -> FallibleRawPagerIter<'_, S, C, H> {
+        let start = self.iter_start(start);
+        self.pager.raw_iter_fallible(start)
+    }
+    /// Iterates over the decoded pages in `range`, seeking once to its start and reading on
+    /// from there. The range is cut short at the pages count.
+    pub fn iter_range<'a, T: DeserializeOwned + 'a>(
+        &'a mut self,
+        range: impl RangeBounds<usize> + 'a,
+    ) -> impl DoubleEndedIterator<Item = T> + ExactSizeIterator + 'a {
+        let pages = pages_in(range);
+        let start = self.iter_start(pages.start);
+        let end = self.record_page(pages.end);
+        self.pager.iterator_range(start..end)
+    }
+    /// Same as `iter_range`, yielding raw pages
+    pub fn raw_iter_range<'a>(
+        &'a mut self,
+        range: impl RangeBounds<usize> + 'a,
+    ) -> impl DoubleEndedIterator<Item = Vec<u8>> + ExactSizeIterator + 'a {
+        let pages = pages_in(range);
+        let start = self.iter_start(pages.start);
+        let end = self.record_page(pages.end);
+        self.pager.raw_iterator_range(start..end)
+    }
+    /// Borrows the bookworm as something to loop over with decoded pages, `&mut bookworm`
+    /// loops over raw pages instead
+    pub fn typed<T: DeserializeOwned + Debug>(&mut self) -> Typed<'_, S, T, C, H> {
+        Typed {
+            bookworm: self,
+            _marker: PhantomData,
+        }
+    }
+    pub fn into_raw_iter(self) -> RawPageIterator<S, H> {
         self.into()
     }
-    pub fn into_iter<T: DeserializeOwned>(self) -> PageIterator<S, T> {
+    /// Turns the bookworm into an iterator over its decoded pages, which stops for good at the
+    /// first page that can't be read or decoded
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T: DeserializeOwned>(self) -> PageIterator<S, T, C, H> {
         self.into()
     }
+    /// Pushes a record, into the first free page when there is one
     pub fn push<T: Serialize>(&mut self, data: &T) -> BookwormResult<()> {
-        self.pager.push(data)
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Push, |bookworm| bookworm.log_push(data));
+        }
+        self.in_context(OpKind::Push, |bookworm| {
+            if let Some(&page) = bookworm.pager.free.first() {
+                let serialized = bookworm.pager.serialize(data)?;
+                return bookworm.reuse_free(page, &serialized);
+            }
+            bookworm.invalidate_decoded(bookworm.pager.pages_count..);
+            bookworm.pager.push(data)
+        })
+    }
+    /// Pushes bytes as they are, padded with zeroes up to the page size, into the first free
+    /// page when there is one
+    pub fn push_raw(&mut self, data: &[u8]) -> BookwormResult<()> {
+        self.in_context(OpKind::Push, |bookworm| {
+            if let Some(&page) = bookworm.pager.free.first() {
+                return bookworm.reuse_free(page, data);
+            }
+            bookworm.invalidate_decoded(bookworm.pager.pages_count..);
+            bookworm.pager.push_raw(data)
+        })
     }
+    /// Appends every item, staging whole pages in memory and writing them in large chunks
+    /// after a single seek. Returns how many pages were added. When an item can't be
+    /// serialized or doesn't fit in a page, only the items before it are written.
+    pub fn push_all<T, I>(&mut self, items: I) -> BookwormResult<usize>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        self.in_context(OpKind::Push, |bookworm| {
+            let page_size = bookworm.pager.page_size;
+            let start = bookworm.pager.pages_count;
+            bookworm.invalidate_decoded(start..);
+            let mut staged = Vec::with_capacity(page_size * COPY_BATCH_PAGES);
+            for item in items {
+                let serialized = match bookworm.pager.serialize(&item) {
+                    Ok(serialized) => serialized,
+                    Err(err) => {
+                        bookworm.pager.append_pages(&staged)?;
+                        let page = bookworm.pager.pages_count;
+                        return Err(BookwormError::new(
+                            err.kind(),
+                            format!("Could not push page {}", page),
+                        )
+                        .with_source(err)
+                        .at_page(page));
+                    }
+                };
+                bookworm.pager.stage_page(&serialized, &mut staged)?;
+                if staged.len() == staged.capacity() {
+                    bookworm.pager.append_pages(&staged)?;
+                    staged.clear();
+                }
+            }
+            bookworm.pager.append_pages(&staged)?;
+            Ok(bookworm.pager.pages_count - start)
+        })
+    }
+    /// Copies every page of `other` to the end of this bookworm without decoding them, a batch
+    /// of pages per write. Returns how many pages were copied.
+    pub fn append<S2: Read + Write + Seek>(
+        &mut self,
+        other: &mut Bookworm<S2>,
+    ) -> BookwormResult<usize> {
+        self.in_context(OpKind::Push, |bookworm| {
+            let page_size = bookworm.pager.page_size;
+            if other.pager.page_size != page_size {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Could not append: pages are {} bytes long but the appended ones are {}",
+                        page_size, other.pager.page_size
+                    ),
+                ));
+            }
+            bookworm.invalidate_decoded(bookworm.pager.pages_count..);
+            other.in_context(OpKind::Read, |other| {
+                let pages_count = other.pager.pages_count;
+                let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(pages_count)];
+                for start in (0..pages_count).step_by(COPY_BATCH_PAGES) {
+                    let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(pages_count - start)];
+                    other.pager.read_pages_into(start, run)?;
+                    bookworm.pager.append_pages(run)?;
+                }
+                Ok(pages_count)
+            })
+        })
+    }
+    /// Removes the last page, along with the free pages right before it
     pub fn pop(&mut self) -> BookwormResult<()> {
-        self.pager.pop()
+        self.in_context(OpKind::Pop, |bookworm| {
+            bookworm.drop_free_tail()?;
+            bookworm.pager.pop()?;
+            bookworm.drop_free_tail()?;
+            bookworm.invalidate_decoded(bookworm.pager.pages_count..);
+            Ok(())
+        })
     }
+    /// Removes a page, or with chained pages the record starting at it along with its chain.
+    /// With a free list the page is only zeroed and marked free.
     pub fn delete(&mut self, page: usize) -> BookwormResult<()> {
-        let remaining_content_iter = self.pager.raw_iter(page + 1);
-        for data in remaining_content_iter {
-            self.swap.push_raw(&data)?;
+        let page = self.record_page(page);
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Delete, |bookworm| bookworm.log_delete(page));
+        }
+        self.in_context(OpKind::Delete, |bookworm| {
+            if bookworm.free_list {
+                return bookworm.free_page(page);
+            }
+            if bookworm.pager.layout == PageLayout::Chained {
+                return bookworm.delete_chain(page);
+            }
+            bookworm.shift_out(
+                page,
+                &mut |_, _| RecoveryAction::AbortOperation,
+                &mut RecoveryReport::default(),
+            )
+        })
+    }
+    /// Same as `delete`, but shifts the following pages down directly on the data source a
+    /// batch at a time, never touching the swap. An interrupted shift leaves the bookworm
+    /// poisoned, as pages may be left duplicated.
+    pub fn delete_in_place(&mut self, page: usize) -> BookwormResult<()> {
+        self.in_context(OpKind::Delete, |bookworm| {
+            bookworm.check_page(page)?;
+            bookworm.check_dense()?;
+            let (_, span) = bookworm.pager.record_at(page)?;
+            let pages_count = bookworm.pager.pages_count;
+            let page_size = bookworm.pager.page_size;
+            bookworm.invalidate_decoded(page..);
+            let following = page + span..pages_count;
+            let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(following.len())];
+            bookworm.poisoned = true;
+            for start in following.clone().step_by(COPY_BATCH_PAGES) {
+                let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(following.end - start)];
+                bookworm.pager.read_pages_into(start, run)?;
+                bookworm.pager.write_raw_pages(start - span, run)?;
+            }
+            bookworm.pager.pages_count -= span;
+            bookworm.pager.zero_pages(pages_count - span..pages_count)?;
+            bookworm.poisoned = false;
+            Ok(())
+        })
+    }
+    /// Removes a page by moving the last page into its slot, touching two pages whatever the
+    /// length and never using the swap. The last page changes position, so the order of the
+    /// pages isn't preserved.
+    pub fn swap_remove(&mut self, page: usize) -> BookwormResult<()> {
+        self.in_context(OpKind::Delete, |bookworm| {
+            bookworm.check_dense()?;
+            bookworm.check_page(page)?;
+            let last = bookworm.pager.pages_count - 1;
+            if page != last {
+                let moved = bookworm.pager.get_raw_page(last)?;
+                bookworm.invalidate_decoded(page..=page);
+                bookworm.pager.write_raw_page(page, &moved)?;
+            }
+            bookworm.pager.pop()?;
+            bookworm.invalidate_decoded(last..);
+            Ok(())
+        })
+    }
+    /// Keeps only the pages whose record matches `predicate`, moving them forward in a single
+    /// pass. Returns how many pages were removed. Should the predicate panic or a page fail to
+    /// parse, every page from there on is kept, so the pages rejected until then are the only
+    /// ones removed and the operation can simply be run again.
+    pub fn retain<T, F>(&mut self, mut predicate: F) -> BookwormResult<usize>
+    where
+        T: DeserializeOwned + Debug,
+        F: FnMut(&T) -> bool,
+    {
+        self.in_context(OpKind::Compact, |bookworm| {
+            bookworm.compact_decoded(|record: T| predicate(&record))
+        })
+    }
+    /// Removes pages whose bytes are identical to the page before them, in a single pass.
+    /// Returns how many pages were removed.
+    pub fn dedup_by_raw(&mut self) -> BookwormResult<usize> {
+        self.in_context(OpKind::Compact, |bookworm| {
+            let mut previous: Option<Vec<u8>> = None;
+            bookworm.compact_pages(|raw_page| {
+                if previous.as_deref() == Some(raw_page) {
+                    return Ok(false);
+                }
+                previous = Some(raw_page.to_vec());
+                Ok(true)
+            })
+        })
+    }
+    /// Removes pages for which `same_bucket(record, previous)` holds, `previous` being the
+    /// last kept record, like `Vec::dedup_by`. Every page is decoded once. Returns how many
+    /// pages were removed, failures are handled like in `retain`.
+    pub fn dedup_by<T, F>(&mut self, mut same_bucket: F) -> BookwormResult<usize>
+    where
+        T: DeserializeOwned + Debug,
+        F: FnMut(&T, &T) -> bool,
+    {
+        self.in_context(OpKind::Compact, |bookworm| {
+            let mut previous: Option<T> = None;
+            bookworm.compact_decoded(|record: T| {
+                if previous
+                    .as_ref()
+                    .is_some_and(|previous| same_bucket(&record, previous))
+                {
+                    return false;
+                }
+                previous = Some(record);
+                true
+            })
+        })
+    }
+    /// Compacts the pages whose decoded record is accepted by `keep`. Once `keep` panics or
+    /// a page fails to parse, the remaining pages are kept and the failure is raised after
+    /// the pass, leaving a consistent store behind.
+    fn compact_decoded<T, F>(&mut self, mut keep: F) -> BookwormResult<usize>
+    where
+        T: DeserializeOwned,
+        F: FnMut(T) -> bool,
+    {
+        let decode_limit = self.pager.decode_limit;
+        let layout = self.pager.layout;
+        let codec = self.pager.codec.clone();
+        let free = self.pager.free.clone();
+        let mut next_page = 0;
+        let mut parse_error = None;
+        let mut panic_payload = None;
+        let removed = self.compact_pages(|raw_page| {
+            while free.contains(&next_page) {
+                next_page += 1;
+            }
+            let page = next_page;
+            next_page += 1;
+            if parse_error.is_some() || panic_payload.is_some() {
+                return Ok(true);
+            }
+            let record = pager::payload_of(layout, raw_page).and_then(|payload| {
+                codec
+                    .deserialize::<T>(payload, decode_limit)
+                    .map_err(|err| {
+                        BookwormError::new(
+                            ErrorKind::Deserialization,
+                            "Could not parse data".to_string(),
+                        )
+                        .with_source(err)
+                    })
+            });
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    parse_error = Some(err.on_parse(page));
+                    return Ok(true);
+                }
+            };
+            panic::catch_unwind(AssertUnwindSafe(|| keep(record))).or_else(|payload| {
+                panic_payload = Some(payload);
+                Ok(true)
+            })
+        })?;
+        if let Some(payload) = panic_payload {
+            panic::resume_unwind(payload);
+        }
+        match parse_error {
+            Some(err) => Err(err),
+            None => Ok(removed),
+        }
+    }
+    /// Moves the pages after the chain starting at `page` over it, links are relative so the
+    /// moved chains stay intact
+    fn delete_chain(&mut self, page: usize) -> BookwormResult<()> {
+        self.check_page(page)?;
+        let (_, span) = self.pager.record_at(page)?;
+        let pages_count = self.pager.pages_count;
+        self.invalidate_decoded(page..);
+        self.move_run(page + span..pages_count, page)?;
+        self.pager.pages_count -= span;
+        self.poisoned = false;
+        self.pager.zero_pages(self.pager.pages_count..pages_count)
+    }
+    /// Removes a page by staging the following ones in the swap and copying them back one page
+    /// earlier, from a copy kept in memory when they take no more than the shift memory.
+    /// Unreadable pages are handled by `recover` before anything is rewritten.
+    fn shift_out(
+        &mut self,
+        page: usize,
+        recover: &mut impl FnMut(usize, &BookwormError) -> RecoveryAction,
+        report: &mut RecoveryReport,
+    ) -> BookwormResult<()> {
+        self.check_dense()?;
+        self.invalidate_decoded(page..);
+        let page_size = self.pager.page_size;
+        let pages_count = self.pager.pages_count;
+        let following = pages_count.saturating_sub(page + 1);
+        let mut in_memory = (following * page_size <= self.shift_memory).then(Vec::new);
+        let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(pages_count.saturating_sub(page))];
+        let mut swap = self.swap.clear_on_drop();
+        for start in (page + 1..pages_count).step_by(COPY_BATCH_PAGES) {
+            let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(pages_count - start)];
+            if self.pager.read_pages_into(start, run).is_ok() {
+                match &mut in_memory {
+                    Some(staged) => staged.extend_from_slice(run),
+                    None => swap.push_raw_pages(run)?,
+                }
+                continue;
+            }
+            // go page by page to find out which ones can't be read
+            for (current, slot) in (start..).zip(run.chunks_mut(page_size)) {
+                let err = match self.pager.read_page_into(current, slot) {
+                    Ok(()) => {
+                        match &mut in_memory {
+                            Some(staged) => staged.extend_from_slice(slot),
+                            None => swap.push_raw(slot)?,
+                        }
+                        continue;
+                    }
+                    Err(err) => err,
+                };
+                match recover(current, &err) {
+                    RecoveryAction::SkipPage => report.skipped.push((current, err)),
+                    RecoveryAction::AbortOperation => return Err(err),
+                    RecoveryAction::ReplaceWith(data) => {
+                        match &mut in_memory {
+                            Some(staged) => self.pager.stage_page(&data, staged)?,
+                            None => swap.push_raw(&data)?,
+                        }
+                        report.replaced.push(current);
+                    }
+                }
+            }
         }
-        let swap_iter = self.swap.raw_iter(0);
-        for (i, data) in swap_iter.enumerate() {
-            self.pager.write_raw_page(i + page, &data)?;
+        // Pages staged in memory are written to the swap all the same, it holds the only
+        // whole copy of them while they are copied back and the marker lets a crash there get
+        // the delete finished on reopen
+        if let Some(staged) = &in_memory {
+            swap.push_raw_pages(staged)?;
         }
-        self.pager.pages_count -= 1;
-        self.swap.clear();
+        self.metrics.peak_swap_pages = self.metrics.peak_swap_pages.max(swap.pages_count);
+        swap.persist(self.sync)?;
+        let marker = DeleteMarker::new(page, swap.pages_count, pages_count);
+        swap.write_front(&marker.to_bytes())?;
+        swap.persist(self.sync)?;
+        self.poisoned = true;
+        match &in_memory {
+            Some(staged) => self.pager.write_raw_pages(page, staged)?,
+            None => {
+                for start in (0..swap.pages_count).step_by(COPY_BATCH_PAGES) {
+                    let run =
+                        &mut buf[..page_size * COPY_BATCH_PAGES.min(swap.pages_count - start)];
+                    swap.read_pages_into(start, run)?;
+                    self.pager.write_raw_pages(page + start, run)?;
+                }
+            }
+        }
+        self.pager.pages_count -= 1 + report.skipped.len();
+        self.pager.zero_pages(self.pager.pages_count..pages_count)?;
+        self.pager.persist(self.sync)?;
+        swap.write_front(&[0; DELETE_MARKER_BYTES as usize])?;
+        swap.persist(self.sync)?;
+        self.poisoned = false;
+        Ok(())
+    }
+    /// Overwrites an existing page with a record
+    pub fn set<T: Serialize>(&mut self, page: usize, data: &T) -> BookwormResult<()> {
+        let page = self.record_page(page);
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Write, |bookworm| bookworm.log_set(page, data));
+        }
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_page(page)?;
+            bookworm.check_unspilled(page)?;
+            bookworm.pager.write_page(page, data)?;
+            bookworm.invalidate_decoded(page..=page);
+            Ok(())
+        })
+    }
+    /// Overwrites an existing page with raw bytes, padded with zeroes up to the page size
+    pub fn set_raw(&mut self, page: usize, data: &[u8]) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_page(page)?;
+            bookworm.pager.write_raw_page(page, data)?;
+            bookworm.invalidate_decoded(page..=page);
+            Ok(())
+        })
+    }
+    /// Overwrites page `to` with the raw contents of page `from`
+    pub fn copy_page(&mut self, from: usize, to: usize) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_page(from)?;
+            bookworm.check_page(to)?;
+            if from == to {
+                return Ok(());
+            }
+            let page = bookworm.pager.get_raw_page(from)?;
+            bookworm.pager.write_raw_page(to, &page)?;
+            bookworm.invalidate_decoded(to..=to);
+            Ok(())
+        })
+    }
+    /// Appends a raw copy of page `from`, returning the index of the copy
+    pub fn copy_page_to_end(&mut self, from: usize) -> BookwormResult<usize> {
+        self.in_context(OpKind::Push, |bookworm| {
+            bookworm.check_page(from)?;
+            let page = bookworm.pager.get_raw_page(from)?;
+            bookworm.invalidate_decoded(bookworm.pager.pages_count..);
+            bookworm.pager.push_raw(&page)?;
+            Ok(bookworm.pager.pages_count - 1)
+        })
+    }
+    /// Moves a page to `to`, shifting the pages between the two positions by one to fill the
+    /// gap. Only the pages in between go through the swap.
+    pub fn move_page(&mut self, from: usize, to: usize) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_page(from)?;
+            bookworm.check_page(to)?;
+            if from == to {
+                return Ok(());
+            }
+            let moved = bookworm.pager.get_raw_page(from)?;
+            bookworm.invalidate_decoded(from.min(to)..=from.max(to));
+            if from < to {
+                bookworm.move_run(from + 1..to + 1, from)?;
+            } else {
+                bookworm.move_run(to..from, to + 1)?;
+            }
+            bookworm.pager.write_raw_page(to, &moved)?;
+            bookworm.poisoned = false;
+            Ok(())
+        })
+    }
+    /// Copies the pages in `pages` so they start at `to` by staging them in the swap. The
+    /// bookworm is left poisoned once the copy back starts, for the caller to finish its
+    /// rewrite and clear it.
+    fn move_run(&mut self, pages: Range<usize>, to: usize) -> BookwormResult<()> {
+        self.check_dense()?;
+        let page_size = self.pager.page_size;
+        let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(pages.len())];
+        let mut swap = self.swap.clear_on_drop();
+        for start in pages.clone().step_by(COPY_BATCH_PAGES) {
+            let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(pages.end - start)];
+            self.pager.read_pages_into(start, run)?;
+            swap.push_raw_pages(run)?;
+        }
+        self.metrics.peak_swap_pages = self.metrics.peak_swap_pages.max(swap.pages_count);
+        self.poisoned = true;
+        for start in (0..swap.pages_count).step_by(COPY_BATCH_PAGES) {
+            let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(swap.pages_count - start)];
+            swap.read_pages_into(start, run)?;
+            self.pager.write_raw_pages(to + start, run)?;
+        }
+        Ok(())
+    }
+    /// Exchanges the contents of two pages, leaving every other page where it is
+    pub fn swap_pages(&mut self, a: usize, b: usize) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_page(a)?;
+            bookworm.check_page(b)?;
+            if a == b {
+                return Ok(());
+            }
+            let page_a = bookworm.pager.get_raw_page(a)?;
+            let page_b = bookworm.pager.get_raw_page(b)?;
+            bookworm.invalidate_decoded(a..=a);
+            bookworm.invalidate_decoded(b..=b);
+            bookworm.poisoned = true;
+            bookworm.pager.write_raw_page(a, &page_b)?;
+            bookworm.pager.write_raw_page(b, &page_a)?;
+            bookworm.poisoned = false;
+            Ok(())
+        })
+    }
+    fn check_page(&self, page: usize) -> BookwormResult<()> {
+        if page >= self.pager.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                format!(
+                    "Page {} is out of range: only {} pages exist",
+                    page, self.pager.pages_count
+                ),
+            ));
+        }
+        self.pager.check_live(page)
+    }
+    /// Drops every page from `len` on, zeroing them. Does nothing when there are no more than
+    /// `len` pages.
+    pub fn truncate(&mut self, len: usize) -> BookwormResult<()> {
+        let len = self.record_page(len);
+        self.in_context(OpKind::Truncate, |bookworm| {
+            bookworm.check_dense()?;
+            let pages_count = bookworm.pager.pages_count;
+            if len >= pages_count {
+                return Ok(());
+            }
+            bookworm.invalidate_decoded(len..);
+            bookworm.pager.pages_count = len;
+            bookworm.pager.zero_pages(len..pages_count)
+        })
+    }
+    /// Inserts a record at `page`, moving that page and the following ones one page later
+    pub fn insert<T: Serialize>(&mut self, page: usize, data: &T) -> BookwormResult<()> {
+        self.in_context(OpKind::Insert, |bookworm| {
+            if page > bookworm.pager.pages_count {
+                return Err(BookwormError::new(
+                    ErrorKind::PageOutOfRange,
+                    format!(
+                        "Could not insert at page {}: only {} pages exist",
+                        page, bookworm.pager.pages_count
+                    ),
+                ));
+            }
+            let serialized = bookworm.pager.serialize(data)?;
+            bookworm.shift_in(page, &serialized)
+        })
+    }
+    /// Makes room for a page by staging the pages from `page` on in the swap and copying them
+    /// back one page later
+    fn shift_in(&mut self, page: usize, data: &[u8]) -> BookwormResult<()> {
+        self.check_dense()?;
+        self.invalidate_decoded(page..);
+        let page_size = self.pager.page_size;
+        let pages_count = self.pager.pages_count;
+        let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(pages_count - page)];
+        let mut swap = self.swap.clear_on_drop();
+        for start in (page..pages_count).step_by(COPY_BATCH_PAGES) {
+            let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(pages_count - start)];
+            self.pager.read_pages_into(start, run)?;
+            swap.push_raw_pages(run)?;
+        }
+        self.metrics.peak_swap_pages = self.metrics.peak_swap_pages.max(swap.pages_count);
+        self.poisoned = true;
+        self.pager.pages_count += 1;
+        for start in (0..swap.pages_count).step_by(COPY_BATCH_PAGES) {
+            let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(swap.pages_count - start)];
+            swap.read_pages_into(start, run)?;
+            self.pager.write_raw_pages(page + 1 + start, run)?;
+        }
+        self.pager.write_raw_page(page, data)?;
+        self.poisoned = false;
+        Ok(())
+    }
+    /// Replaces the pages in `range` with the records of `replacement`, shifting the following
+    /// pages once through the swap when the lengths differ. Every record is serialized before
+    /// anything is moved.
+    pub fn splice<T, I>(&mut self, range: Range<usize>, replacement: I) -> BookwormResult<()>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_dense()?;
+            let page_size = bookworm.pager.page_size;
+            let pages_count = bookworm.pager.pages_count;
+            if range.start > range.end || range.end > pages_count {
+                return Err(BookwormError::new(
+                    ErrorKind::PageOutOfRange,
+                    format!(
+                        "Pages {}..{} are out of range: only {} pages exist",
+                        range.start, range.end, pages_count
+                    ),
+                ));
+            }
+            let mut pages = Vec::new();
+            for (page, item) in (range.start..).zip(replacement) {
+                let serialized = bookworm.pager.serialize(&item).map_err(|err| {
+                    BookwormError::new(err.kind(), format!("Could not write page {}", page))
+                        .with_source(err)
+                        .at_page(page)
+                })?;
+                bookworm.pager.stage_page(&serialized, &mut pages)?;
+            }
+            let new_len = pages.len() / page_size;
+            let new_count = pages_count - range.len() + new_len;
+            bookworm.invalidate_decoded(range.start..);
+            if new_len != range.len() {
+                // the moved pages may land past the current count
+                bookworm.pager.pages_count = pages_count.max(new_count);
+                if let Err(err) = bookworm.move_run(range.end..pages_count, range.start + new_len) {
+                    if !bookworm.poisoned {
+                        bookworm.pager.pages_count = pages_count;
+                    }
+                    return Err(err);
+                }
+                bookworm.pager.pages_count = new_count;
+            }
+            bookworm.poisoned = true;
+            bookworm.pager.write_raw_pages(range.start, &pages)?;
+            bookworm.poisoned = false;
+            bookworm
+                .pager
+                .zero_pages(new_count..pages_count.max(new_count))
+        })
+    }
+    /// Number of pages currently staged in the swap
+    pub fn swap_len(&self) -> usize {
+        self.swap.pages_count
+    }
+    /// Zeroes the whole swap storage instead of only forgetting the staged pages
+    pub fn swap_clear(&mut self) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| bookworm.swap.erase())
+    }
+    /// Points the swap to a different storage, refused while pages are staged in the current one
+    pub fn replace_swap(&mut self, swap: H) -> BookwormResult<()> {
+        #[cfg(feature = "wal")]
+        if self.wal_entries() > 0 {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not replace swap: it holds a write ahead log, checkpoint first".to_string(),
+            ));
+        }
+        if self.swap.pages_count > 0 {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not replace swap: it still holds staged pages".to_string(),
+            ));
+        }
+        let mut swap = Pager::with_codec(self.swap.page_size, swap, self.swap.codec.clone());
+        swap.reserve_front(DELETE_MARKER_BYTES);
+        swap.clear();
+        self.swap = swap;
         Ok(())
     }
+    /// Gives back the handles to the data source and the swap
+    pub fn into_inner(self) -> (H, H) {
+        (
+            self.pager.data_source.clone(),
+            self.swap.data_source.clone(),
+        )
+    }
+    /// Takes back ownership of the data source and the swap, handing the bookworm back if
+    /// any of them is still shared
+    #[allow(clippy::result_large_err)]
+    pub fn try_unwrap_inner(self) -> Result<(S, S), Self> {
+        if self.pager.data_source.is_shared() || self.swap.data_source.is_shared() {
+            return Err(self);
+        }
+        let (data_source, swap) = self.into_inner();
+        match (data_source.try_unwrap(), swap.try_unwrap()) {
+            (Ok(data_source), Ok(swap)) => Ok((data_source, swap)),
+            _ => unreachable!("sharing was checked above"),
+        }
+    }
+    /// Whether an operation was interrupted after it started rewriting pages, leaving them in
+    /// an unknown state. Every operation fails while the bookworm is poisoned.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+    /// Accepts the pages as they are after an interrupted rewrite
+    pub fn clear_poison(&mut self) {
+        self.poisoned = false;
+    }
+    /// Flushes both storages and releases them. Dropping a bookworm flushes as well, but any
+    /// failure is lost there, so callers who care about errors must close it instead.
+    pub fn close(mut self) -> BookwormResult<()> {
+        self.closed = true;
+        self.in_context(OpKind::Flush, Self::flush_storages)
+    }
+    /// Writes back the dirty pages and flushes both storages
+    pub fn flush(&mut self) -> BookwormResult<()> {
+        self.in_context(OpKind::Flush, Self::flush_storages)
+    }
+    /// In write back mode overwriting a page only updates a copy in memory, marking it dirty
+    /// until `flush` writes it. Turning it off writes the dirty pages back.
+    pub fn set_write_back(&mut self, enabled: bool) -> BookwormResult<()> {
+        self.in_context(OpKind::Flush, |bookworm| {
+            if !enabled {
+                bookworm.pager.write_back()?;
+            }
+            bookworm.pager.write_back_mode = enabled;
+            Ok(())
+        })
+    }
+    /// Pages overwritten in write back mode that haven't been written to the data source
+    pub fn dirty_pages(&self) -> Vec<usize> {
+        self.pager.dirty_pages()
+    }
+    fn flush_storages(&mut self) -> BookwormResult<()> {
+        self.pager.write_back()?;
+        self.pager.flush()?;
+        self.swap.flush()
+    }
+    /// Runs `operation` under a fresh context that gets attached to any error it returns.
+    /// Operations other than flushes apply the write ahead log first, reads that look the log
+    /// up go through `in_logged_context` instead.
+    fn in_context<T>(
+        &mut self,
+        kind: OpKind,
+        operation: impl FnOnce(&mut Self) -> BookwormResult<T>,
+    ) -> BookwormResult<T> {
+        #[cfg(feature = "wal")]
+        if kind != OpKind::Flush {
+            return self.in_logged_context(kind, |bookworm| {
+                bookworm.checkpoint_log()?;
+                operation(bookworm)
+            });
+        }
+        self.in_logged_context(kind, operation)
+    }
+    /// Same as `in_context`, leaving the write ahead log as it is
+    fn in_logged_context<T>(
+        &mut self,
+        kind: OpKind,
+        operation: impl FnOnce(&mut Self) -> BookwormResult<T>,
+    ) -> BookwormResult<T> {
+        let context = OpContext::new(kind);
+        if self.poisoned {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Bookworm is poisoned: an operation was interrupted while rewriting pages"
+                    .to_string(),
+            )
+            .with_context(context));
+        }
+        if let Err(err) = self.open_bitmap(kind) {
+            return Err(err.with_context(context));
+        }
+        let result = operation(self);
+        self.index_records(kind);
+        let recorded = self.pager.record_count().and_then(|_| self.store_bitmap());
+        result
+            .and_then(|result| recorded.map(|_| result))
+            .and_then(|result| self.after_write(kind).map(|_| result))
+            .map_err(|err| err.with_context(context))
+    }
+    /// Moves the pages accepted by `keep` forward over the rejected ones in a single pass,
+    /// zeroing the freed tail. Free pages are dropped without asking `keep`, folding the free
+    /// list back. Returns how many pages were removed.
+    fn compact_pages<F>(&mut self, mut keep: F) -> BookwormResult<usize>
+    where
+        F: FnMut(&[u8]) -> BookwormResult<bool>,
+    {
+        self.check_unchained("move pages")?;
+        let pages_count = self.pager.pages_count;
+        let mut buf = vec![0; self.pager.page_size];
+        let mut write_pos = 0;
+        for read_pos in 0..pages_count {
+            if self.pager.free.contains(&read_pos) {
+                continue;
+            }
+            self.pager.read_page_into(read_pos, &mut buf)?;
+            if !keep(&buf)? {
+                continue;
+            }
+            if write_pos != read_pos {
+                self.poisoned = true;
+                self.pager.write_raw_pages(write_pos, &buf)?;
+            }
+            write_pos += 1;
+        }
+        self.pager.pages_count = write_pos;
+        self.pager.free.clear();
+        self.poisoned = false;
+        for page in write_pos..pages_count {
+            self.pager.zero_page(page)?;
+        }
+        self.invalidate_decoded(..);
+        Ok(pages_count - write_pos)
+    }
+    /// Refuses operations that take every page for a record of its own
+    pub(crate) fn check_unchained(&self, action: &str) -> BookwormResult<()> {
+        if self.pager.layout == PageLayout::Chained {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                format!("Could not {}: chained records span several pages", action),
+            ));
+        }
+        Ok(())
+    }
+    /// Reads and decodes a page through `buf`, naming the page in any error. Free pages are
+    /// refused.
+    fn decode_page<T: DeserializeOwned>(
+        &mut self,
+        page: usize,
+        buf: &mut [u8],
+    ) -> BookwormResult<T> {
+        self.pager.check_live(page)?;
+        self.pager
+            .read_page_into(page, buf)
+            .map_err(|err| err.on_read(page))?;
+        self.pager
+            .deserialize(buf)
+            .map_err(|err| err.on_parse(page))
+    }
+    /// Page an iterator over the data source starts at. The write ahead log gets applied first
+    /// so the iterator sees every record, a log that can't be applied leaves it empty.
+    fn iter_start(&mut self, start: usize) -> usize {
+        #[cfg(feature = "wal")]
+        if self.wal_mode() && self.checkpoint().is_err() {
+            return usize::MAX;
+        }
+        self.record_page(start)
+    }
+    /// Drops every decoded value cached for the given pages
+    fn invalidate_decoded(&mut self, pages: impl RangeBounds<usize>) {
+        self.decoded_cache.invalidate(pages);
+    }
+}
+
+/// Turns any range of pages into a half open one, leaving the upper bound open ended
+fn pages_in(range: impl RangeBounds<usize>) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => usize::MAX,
+    };
+    start..end.max(start)
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Drop for Bookworm<S, C, H> {
+    /// Best-effort flush, use `Bookworm::close` to find out whether it worked
+    fn drop(&mut self) {
+        if !self.closed && self.durability != Durability::Manual {
+            let _ = self.flush_storages();
+        }
+    }
+}
+
+impl<'a, S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> IntoIterator
+    for &'a mut Bookworm<S, C, H>
+{
+    type Item = Vec<u8>;
+    type IntoIter = RawPagerIter<'a, S, C, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.raw_iter(0)
+    }
 }
 
-pub struct RawPageIterator<S: Read + Write + Seek> {
-    pager_iterator: RawPagerIterator<S>,
+pub struct Typed<
+    'a,
+    S: Read + Write + Seek,
+    T: DeserializeOwned + Debug,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    _marker: PhantomData<T>,
 }
 
-impl<'a, S: Read + Write + Seek> Into<RawPageIterator<S>> for Bookworm<S> {
-    fn into(self) -> RawPageIterator<S> {
+impl<'a, S, T, C, H> IntoIterator for Typed<'a, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    T: DeserializeOwned + Debug,
+    C: Codec,
+{
+    type Item = T;
+    type IntoIter = PagerIter<'a, S, T, C, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.bookworm.iter(0)
+    }
+}
+
+pub struct RawPageIterator<S: Read + Write + Seek, H: SharedStorage<S> = Rc<RefCell<S>>> {
+    pager_iterator: RawPagerIterator<S, H>,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> From<Bookworm<S, C, H>>
+    for RawPageIterator<S, H>
+{
+    fn from(mut bookworm: Bookworm<S, C, H>) -> Self {
+        let start = bookworm.iter_start(0);
         RawPageIterator {
-            pager_iterator: self.pager.into_raw_iterator(0),
+            pager_iterator: bookworm.pager.raw_iterator(start),
         }
     }
 }
 
-impl<S: Read + Write + Seek> Iterator for RawPageIterator<S> {
+impl<S: Read + Write + Seek, H: SharedStorage<S>> RawPageIterator<S, H> {
+    /// Gives back the handle to the data source, the only one left when the iterator was
+    /// made from a bookworm created with `Bookworm::from_owned`
+    pub fn into_parts(self) -> H {
+        self.pager_iterator.into_data_source()
+    }
+}
+
+impl<S: Read + Write + Seek, H: SharedStorage<S>> Iterator for RawPageIterator<S, H> {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.pager_iterator.next()
     }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pager_iterator.size_hint()
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.pager_iterator.nth(n)
+    }
 }
 
-pub struct PageIterator<S: Read + Write + Seek, T: DeserializeOwned> {
-    pager_iterator: PagerIterator<S, T>,
+impl<S: Read + Write + Seek, H: SharedStorage<S>> ExactSizeIterator for RawPageIterator<S, H> {}
+
+impl<S: Read + Write + Seek, H: SharedStorage<S>> DoubleEndedIterator for RawPageIterator<S, H> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.pager_iterator.next_back()
+    }
+}
+
+pub struct PageIterator<
+    S: Read + Write + Seek,
+    T: DeserializeOwned,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    pager_iterator: PagerIterator<S, T, C, H>,
     _marker: std::marker::PhantomData<T>,
 }
 
-impl<S, T> Iterator for PageIterator<S, T>
+impl<S: Read + Write + Seek, T: DeserializeOwned, C: Codec, H: SharedStorage<S>>
+    PageIterator<S, T, C, H>
+{
+    /// Same as `RawPageIterator::into_parts`
+    pub fn into_parts(self) -> H {
+        self.pager_iterator.into_data_source()
+    }
+}
+
+impl<S, T, C, H> Iterator for PageIterator<S, T, C, H>
 where
     S: Read + Write + Seek,
+    H: SharedStorage<S>,
     T: DeserializeOwned,
+    C: Codec,
 {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.pager_iterator.next()
     }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pager_iterator.size_hint()
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.pager_iterator.nth(n)
+    }
 }
 
-impl<'a, S: Read + Write + Seek, T: DeserializeOwned> Into<PageIterator<S, T>> for Bookworm<S> {
-    fn into(self) -> PageIterator<S, T> {
-        let mut data_source = self.pager.data_source.borrow_mut();
-        let _ = data_source.rewind();
-        drop(data_source);
+impl<S, T, C, H> ExactSizeIterator for PageIterator<S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    T: DeserializeOwned,
+    C: Codec,
+{
+}
+
+impl<S, T, C, H> DoubleEndedIterator for PageIterator<S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    T: DeserializeOwned,
+    C: Codec,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.pager_iterator.next_back()
+    }
+}
+
+impl<S: Read + Write + Seek, T: DeserializeOwned, C: Codec, H: SharedStorage<S>>
+    From<Bookworm<S, C, H>> for PageIterator<S, T, C, H>
+{
+    fn from(mut bookworm: Bookworm<S, C, H>) -> Self {
+        let start = bookworm.iter_start(0);
         PageIterator {
-            pager_iterator: self.pager.into_iterator(0),
+            pager_iterator: bookworm.pager.iterator(start),
             _marker: Default::default(),
         }
     }
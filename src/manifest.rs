@@ -0,0 +1,163 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    checksum::crc32,
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+const MANIFEST_MAGIC: &[u8; 4] = b"BWMF";
+const MANIFEST_VERSION: u8 = 1;
+
+/// How the pages of a manifest are digested
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Crc32,
+    Fnv1a64,
+}
+
+impl DigestAlgorithm {
+    pub fn digest(self, bytes: &[u8]) -> u64 {
+        match self {
+            DigestAlgorithm::Crc32 => crc32(bytes) as u64,
+            DigestAlgorithm::Fnv1a64 => bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+            }),
+        }
+    }
+    fn to_tag(self) -> u8 {
+        match self {
+            DigestAlgorithm::Crc32 => 0,
+            DigestAlgorithm::Fnv1a64 => 1,
+        }
+    }
+    fn from_tag(tag: u8) -> BookwormResult<Self> {
+        match tag {
+            0 => Ok(DigestAlgorithm::Crc32),
+            1 => Ok(DigestAlgorithm::Fnv1a64),
+            _ => Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!("Could not read manifest: unknown digest algorithm {}", tag),
+            )),
+        }
+    }
+}
+
+/// Digests of every page plus one over the whole sequence, meant to be stored apart from the
+/// bookworm and checked against it later
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub algorithm: DigestAlgorithm,
+    pub pages: Vec<u64>,
+    /// Digest of the page digests in order
+    pub digest: u64,
+}
+
+/// Pages that differ between a manifest and the bookworm it's checked against
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ManifestDiff {
+    pub added: Vec<usize>,
+    pub removed: Vec<usize>,
+    pub modified: Vec<usize>,
+}
+
+impl ManifestDiff {
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl Manifest {
+    fn new(algorithm: DigestAlgorithm, pages: Vec<u64>) -> Self {
+        let sequence: Vec<u8> = pages.iter().flat_map(|page| page.to_le_bytes()).collect();
+        Self {
+            algorithm,
+            digest: algorithm.digest(&sequence),
+            pages,
+        }
+    }
+    /// Writes the manifest as the magic, a version, the algorithm, the page count, the
+    /// sequence digest and every page digest, integers in little endian
+    pub fn write_to<W: Write>(&self, mut writer: W) -> BookwormResult<()> {
+        let mut bytes = Vec::with_capacity(22 + self.pages.len() * 8);
+        bytes.extend_from_slice(MANIFEST_MAGIC);
+        bytes.push(MANIFEST_VERSION);
+        bytes.push(self.algorithm.to_tag());
+        bytes.extend_from_slice(&(self.pages.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.digest.to_le_bytes());
+        for page in &self.pages {
+            bytes.extend_from_slice(&page.to_le_bytes());
+        }
+        writer.write_all(&bytes).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, "Could not write manifest".to_string())
+                .with_source(err)
+        })
+    }
+    pub fn read_from<R: Read>(mut reader: R) -> BookwormResult<Self> {
+        let read_error = |err| {
+            BookwormError::new(ErrorKind::Io, "Could not read manifest".to_string())
+                .with_source(err)
+        };
+        let mut head = [0; 22];
+        reader.read_exact(&mut head).map_err(read_error)?;
+        if &head[..4] != MANIFEST_MAGIC || head[4] != MANIFEST_VERSION {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                "Could not read manifest: not a manifest or unsupported version".to_string(),
+            ));
+        }
+        let algorithm = DigestAlgorithm::from_tag(head[5])?;
+        let count = u64::from_le_bytes(head[6..14].try_into().unwrap());
+        let digest = u64::from_le_bytes(head[14..22].try_into().unwrap());
+        let mut pages = Vec::new();
+        let mut page = [0; 8];
+        for _ in 0..count {
+            reader.read_exact(&mut page).map_err(read_error)?;
+            pages.push(u64::from_le_bytes(page));
+        }
+        let manifest = Self::new(algorithm, pages);
+        if manifest.digest != digest {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                "Could not read manifest: sequence digest doesn't match its pages".to_string(),
+            ));
+        }
+        Ok(manifest)
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Digests every page, reading them one at a time into the same buffer
+    pub fn build_manifest(&mut self, algorithm: DigestAlgorithm) -> BookwormResult<Manifest> {
+        self.in_context(OpKind::Scan, |bookworm| {
+            let mut buf = vec![0; bookworm.pager.page_size];
+            let mut pages = Vec::with_capacity(bookworm.pager.pages_count);
+            for page in 0..bookworm.pager.pages_count {
+                bookworm
+                    .pager
+                    .read_page_into(page, &mut buf)
+                    .map_err(|err| err.on_read(page))?;
+                pages.push(algorithm.digest(&buf));
+            }
+            Ok(Manifest::new(algorithm, pages))
+        })
+    }
+    /// Compares the pages against a manifest built earlier
+    pub fn verify_manifest(&mut self, manifest: &Manifest) -> BookwormResult<ManifestDiff> {
+        let current = self.build_manifest(manifest.algorithm)?;
+        let mut diff = ManifestDiff::default();
+        if current.digest == manifest.digest && current.pages.len() == manifest.pages.len() {
+            return Ok(diff);
+        }
+        for (page, (expected, found)) in manifest.pages.iter().zip(&current.pages).enumerate() {
+            if expected != found {
+                diff.modified.push(page);
+            }
+        }
+        diff.added = (manifest.pages.len()..current.pages.len()).collect();
+        diff.removed = (current.pages.len()..manifest.pages.len()).collect();
+        Ok(diff)
+    }
+}
@@ -1,24 +1,162 @@
 use std::{
     cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
     fmt::Debug,
-    io::{BufReader, Read, Seek, SeekFrom, Write},
+    io::{IoSlice, Read, Seek, SeekFrom, Write},
+    ops::{Deref, DerefMut, Range},
     rc::Rc,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
 };
 
-use bincode::serialize;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::error::{BookwormError, BookwormResult};
+use crate::{
+    cache::PageCache,
+    checksum::{Checksum, ChecksumAlgorithm, Digest},
+    codec::{BincodeCodec, Codec},
+    count::{CountSource, OpenIntegrity},
+    error::{BookwormError, BookwormResult, ErrorKind},
+    spill::OversizePolicy,
+    storage::SharedStorage,
+    truncate::Truncate,
+    verify::{Check, PageVerification},
+};
 
-pub struct Pager<S: Read + Write + Seek> {
-    pub data_source: Rc<RefCell<S>>,
-    page_size: usize,
+/// Pages of zeroes written at once when clearing a run of pages
+const ZERO_BATCH_PAGES: usize = 64;
+/// Bytes taken by the length in front of length prefixed pages
+const LENGTH_PREFIX_BYTES: usize = 4;
+/// Bytes taken by the header of chained pages: the data length, the offset to the next page of
+/// the record and the flags
+const CHAIN_HEADER_BYTES: usize = 9;
+/// Flags the pages of a chained record after the first one
+const CONTINUATION_FLAG: u8 = 1;
+/// Bytes taken by the header of checksummed pages in front of the digest: the data length and
+/// the digest length
+const CHECKSUM_HEADER_BYTES: usize = 5;
+const FILE_MAGIC: &[u8; 4] = b"BKWM";
+/// Version 2 records the length of the digest in checksummed pages, version 1 files are still
+/// read when they aren't checksummed
+const FORMAT_VERSION: u8 = 2;
+/// Bytes reserved for the file header: the magic bytes, the format version, the feature
+/// flags, the page size, the checksum algorithm and the pages count
+const FILE_HEADER_BYTES: u64 = 16;
+/// Where the pages count sits in the file header, a little endian 40 bit integer
+const HEADER_COUNT_AT: usize = 11;
+const LENGTH_PREFIXED_FLAG: u8 = 1;
+const CHAINED_FLAG: u8 = 1 << 1;
+const CHECKSUMMED_FLAG: u8 = 1 << 2;
+/// Set along with the chained flag by stores spilling records bigger than a page over chains
+const SPILL_FLAG: u8 = 1 << 3;
+/// Set by stores keeping their pages count in the file header
+const COUNTED_FLAG: u8 = 1 << 4;
+/// Set by stores keeping a bitmap of their live pages between the file header and the pages
+const LIVE_BITMAP_FLAG: u8 = 1 << 5;
+
+/// How data is laid out within a page
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PageLayout {
+    /// The data followed by zero padding, where the data ends is left for the reader to find
+    #[default]
+    Padded,
+    /// A little endian u32 length before the data, so raw reads give back exactly what was
+    /// written
+    LengthPrefixed,
+    /// A header holding the data length, the offset to the page the record goes on at and
+    /// whether the page continues a record, so records bigger than a page span a chain of
+    /// pages. Records are addressed by their first page. Reads, pushes, iterators, `delete` and
+    /// `pop` follow chains, other operations work on single pages.
+    Chained,
+    /// A little endian u32 length, the length of the digest and the digest of the data before
+    /// it, checked on every read of the whole page. The algorithm is recorded in the file
+    /// header, opening a store with another one is refused.
+    Checksummed(ChecksumAlgorithm),
+}
+
+pub struct Pager<
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    pub data_source: H,
+    pub page_size: usize,
     pub pages_count: usize,
+    /// Pages physically present in the data source
+    pub capacity: usize,
+    /// Pages from this index up to `capacity` are known to be zeroed
+    clean_from: usize,
+    /// Skips seeking to the tail on push when the stream is known to be already there
+    pub append_mode: bool,
+    /// Where the stream was left by the last push in append mode, assumes nothing else moves
+    /// it behind the pager's back
+    position: Option<u64>,
+    pub corrective_seeks: usize,
+    pub vectored_batches: usize,
+    /// Most bytes a single decode may consume, so a corrupt length prefix can't ask for a
+    /// huge allocation. Defaults to the page size.
+    pub decode_limit: u64,
+    /// Must match the layout the pages were written with
+    pub layout: PageLayout,
+    /// Recorded in the file header along with the layout
+    pub oversize: OversizePolicy,
+    /// Recorded in the file header along with the layout
+    pub count_source: CountSource,
+    /// Pages count the file header holds, when it holds one
+    recorded_count: Option<usize>,
+    /// Pages reserved for the live bitmap right after the file header
+    bitmap_pages: usize,
+    pub codec: C,
+    /// Where the first page starts, past the file header when there is one
+    data_offset: u64,
+    /// Pages freed by free list deletes, which reads refuse and iterators skip
+    pub free: BTreeSet<usize>,
+    /// Recently read pages served without going to the data source, when enabled
+    cache: Option<PageCache>,
+    /// Keeps overwritten pages in memory as dirty until they're written back
+    pub write_back_mode: bool,
+    /// Whole raw pages written in write back mode that the data source doesn't hold yet
+    dirty: DirtyPages,
+    _storage: std::marker::PhantomData<S>,
+}
+
+/// Whole raw pages by index, shared with the background flusher when one runs. Whoever writes
+/// them back locks them before the data source.
+pub(crate) type DirtyPages = Arc<Mutex<BTreeMap<usize, Vec<u8>>>>;
+
+/// Page metadata that can be gathered without decoding the payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+    pub index: usize,
+    pub payload_len: usize,
+    pub checksum: Option<Digest>,
+    pub flags: Option<u8>,
+    pub written_at: Option<u64>,
+}
+
+/// How much of a page is taken by its payload
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageFill {
+    pub payload_bytes: usize,
+    pub capacity: usize,
+    pub fraction: f64,
+    /// Whether `payload_bytes` comes from stored metadata. Headerless pages are measured by
+    /// trimming trailing zeroes, which undercounts payloads that end in zero bytes.
+    pub exact: bool,
+}
+
+/// Fill levels aggregated over a whole store
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillSummary {
+    pub pages: usize,
+    pub min: usize,
+    pub avg: f64,
+    pub max: usize,
+    pub exact: bool,
 }
 
-impl<S: Read + Write + Seek> Pager<S> {
-    pub fn new(page_size: usize, data_source: Rc<RefCell<S>>) -> Self {
-        let mut data_source_ref = data_source.borrow_mut();
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Pager<S, C, H> {
+    pub fn with_codec(page_size: usize, data_source: H, codec: C) -> Self {
+        let mut data_source_ref = data_source.access();
         let data_source_len = data_source_ref.seek(SeekFrom::End(0)).unwrap_or(0) as usize;
         drop(data_source_ref);
         let last_page = data_source_len / page_size;
@@ -26,213 +164,2334 @@ impl<S: Read + Write + Seek> Pager<S> {
             page_size,
             data_source,
             pages_count: last_page,
+            capacity: last_page,
+            clean_from: last_page,
+            append_mode: false,
+            position: None,
+            corrective_seeks: 0,
+            vectored_batches: 0,
+            decode_limit: page_size as u64,
+            layout: PageLayout::default(),
+            oversize: OversizePolicy::default(),
+            count_source: CountSource::default(),
+            recorded_count: None,
+            bitmap_pages: 0,
+            codec,
+            data_offset: 0,
+            cache: None,
+            write_back_mode: false,
+            dirty: DirtyPages::default(),
+            free: BTreeSet::new(),
+            _storage: std::marker::PhantomData,
+        }
+    }
+    /// Writes the file header to an empty data source, or checks the one a data source already
+    /// starts with against the page size and layout, then places the pages after it. The
+    /// oversize policy and the count source are taken from the header, along with the pages
+    /// count when it records one.
+    pub fn open_header(&mut self) -> BookwormResult<OpenIntegrity> {
+        self.position = None;
+        let mut recorded_pages = None;
+        if self.stored_bytes()? == 0 {
+            self.write_header()?;
+        } else {
+            let header = FileHeader::read_from(&mut *self.data_source.access())?;
+            if header.layout != self.layout {
+                return Err(open_error(
+                    ErrorKind::InvalidInput,
+                    &format!(
+                        "it was written with the {:?} layout, not {:?}",
+                        header.layout, self.layout
+                    ),
+                ));
+            }
+            if header.page_size != self.page_size {
+                return Err(open_error(
+                    ErrorKind::InvalidInput,
+                    &format!(
+                        "its pages are {} bytes long, not {}",
+                        header.page_size, self.page_size
+                    ),
+                ));
+            }
+            self.oversize = header.oversize;
+            self.count_source = match header.pages_count {
+                Some(_) => CountSource::Header,
+                None => CountSource::FileLength,
+            };
+            self.recorded_count = header.pages_count;
+            recorded_pages = header.pages_count;
+            if header.live_bitmap {
+                let mut reserved = [0; 4];
+                self.read_reserved(0, &mut reserved)?;
+                let bitmap_pages = u32::from_le_bytes(reserved) as usize;
+                let bitmap_end = FILE_HEADER_BYTES + (bitmap_pages * self.page_size) as u64;
+                if bitmap_pages == 0 || bitmap_end > self.stored_bytes()? {
+                    return Err(open_error(
+                        ErrorKind::Corrupted,
+                        &format!("its live bitmap of {} pages doesn't fit", bitmap_pages),
+                    ));
+                }
+                self.bitmap_pages = bitmap_pages;
+            }
+        }
+        self.data_offset = FILE_HEADER_BYTES + (self.bitmap_pages * self.page_size) as u64;
+        let stored_pages = self.stored_pages()?;
+        let torn_bytes = self.stored_bytes()? - self.offset_of(stored_pages);
+        self.pages_count = recorded_pages.unwrap_or(stored_pages);
+        self.capacity = stored_pages;
+        self.clean_from = stored_pages;
+        Ok(OpenIntegrity::new(recorded_pages, stored_pages, torn_bytes))
+    }
+    /// Writes the file header recording the page size, the layout, the oversize policy and
+    /// the pages count when it's kept there
+    pub(crate) fn write_header(&mut self) -> BookwormResult<()> {
+        let mut header = [0; FILE_HEADER_BYTES as usize];
+        header[..4].copy_from_slice(FILE_MAGIC);
+        header[4] = FORMAT_VERSION;
+        header[5] = layout_flags(self.layout);
+        if self.oversize == OversizePolicy::Spill {
+            header[5] |= SPILL_FLAG;
+        }
+        header[6..10].copy_from_slice(&(self.page_size as u32).to_le_bytes());
+        if let PageLayout::Checksummed(algorithm) = self.layout {
+            header[10] = algorithm.to_tag();
+        }
+        let recorded_count = match self.count_source {
+            CountSource::Header => {
+                header[5] |= COUNTED_FLAG;
+                header[HEADER_COUNT_AT..].copy_from_slice(&count_bytes(self.pages_count));
+                Some(self.pages_count)
+            }
+            CountSource::FileLength => None,
+        };
+        if self.bitmap_pages > 0 {
+            header[5] |= LIVE_BITMAP_FLAG;
+        }
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .rewind()
+            .and_then(|_| data_source.write_all(&header))
+            .map_err(|err| {
+                open_error(ErrorKind::Io, "the header could not be written").with_source(err)
+            })?;
+        self.recorded_count = recorded_count;
+        Ok(())
+    }
+    /// Brings the pages count in the file header up to date when it's kept there, writing
+    /// nothing when it already is
+    pub(crate) fn record_count(&mut self) -> BookwormResult<()> {
+        if self.count_source != CountSource::Header
+            || !self.has_header()
+            || self.recorded_count == Some(self.pages_count)
+        {
+            return Ok(());
+        }
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .seek(SeekFrom::Start(HEADER_COUNT_AT as u64))
+            .and_then(|_| data_source.write_all(&count_bytes(self.pages_count)))
+            .map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Io,
+                    "Could not record the pages count in the file header".to_owned(),
+                )
+                .with_source(err)
+            })?;
+        self.recorded_count = Some(self.pages_count);
+        Ok(())
+    }
+    /// Reserves `pages` zeroed pages for the live bitmap between the file header and the pages
+    /// of a store that has none yet, recording them in the header
+    pub(crate) fn reserve_bitmap(&mut self, pages: usize) -> BookwormResult<()> {
+        self.discard(0..usize::MAX);
+        self.write_reserved(0, &vec![0; pages * self.page_size])?;
+        self.bitmap_pages = pages;
+        self.data_offset = FILE_HEADER_BYTES + (pages * self.page_size) as u64;
+        self.pages_count = 0;
+        self.capacity = 0;
+        self.clean_from = 0;
+        self.write_header()
+    }
+    /// Pages reserved for the live bitmap
+    pub fn bitmap_pages(&self) -> usize {
+        self.bitmap_pages
+    }
+    /// Writes `bytes` at `offset` past the file header, into the pages reserved for the live
+    /// bitmap
+    pub(crate) fn write_reserved(&mut self, offset: u64, bytes: &[u8]) -> BookwormResult<()> {
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .seek(SeekFrom::Start(FILE_HEADER_BYTES + offset))
+            .and_then(|_| data_source.write_all(bytes))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not write live bitmap".to_owned())
+                    .with_source(err)
+            })
+    }
+    /// Reads what `write_reserved` wrote
+    pub(crate) fn read_reserved(&mut self, offset: u64, buf: &mut [u8]) -> BookwormResult<()> {
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .seek(SeekFrom::Start(FILE_HEADER_BYTES + offset))
+            .and_then(|_| data_source.read_exact(buf))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not read live bitmap".to_owned())
+                    .with_source(err)
+            })
+    }
+    /// Pages count the file header records, read back from the data source
+    pub(crate) fn read_recorded_count(&mut self) -> BookwormResult<Option<usize>> {
+        self.position = None;
+        let header = FileHeader::read_from(&mut *self.data_source.access())?;
+        Ok(header.pages_count)
+    }
+    /// Keeps the first `bytes` bytes of the data source out of the pages, for bookkeeping
+    /// stored in front of them
+    pub fn reserve_front(&mut self, bytes: u64) {
+        self.data_offset = bytes;
+        let stored_pages = self.stored_pages().unwrap_or(0);
+        self.pages_count = stored_pages;
+        self.capacity = stored_pages;
+        self.clean_from = stored_pages;
+    }
+    /// Writes the bytes kept in front of the pages, see `reserve_front`
+    pub fn write_front(&mut self, bytes: &[u8]) -> BookwormResult<()> {
+        debug_assert!(bytes.len() as u64 <= self.data_offset);
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .rewind()
+            .and_then(|_| data_source.write_all(bytes))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not write data source".to_owned())
+                    .with_source(err)
+            })
+    }
+    pub fn read_front(&mut self, buf: &mut [u8]) -> BookwormResult<()> {
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .rewind()
+            .and_then(|_| data_source.read_exact(buf))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not read data source".to_owned())
+                    .with_source(err)
+            })
+    }
+    /// Number of bytes in front of the first page
+    pub fn data_offset(&self) -> u64 {
+        self.data_offset
+    }
+    /// Whether the pages come after a file header
+    pub fn has_header(&self) -> bool {
+        self.data_offset > 0
+    }
+    pub fn check_live(&self, page: usize) -> BookwormResult<()> {
+        if self.free.contains(&page) {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                format!("Page {} is free", page),
+            ));
+        }
+        Ok(())
+    }
+    /// Byte offset of a page within the data source
+    fn offset_of(&self, page: usize) -> u64 {
+        self.data_offset + (page * self.page_size) as u64
+    }
+    /// Number of whole pages the data source physically holds right now
+    pub fn stored_pages(&mut self) -> BookwormResult<usize> {
+        let len = self.stored_bytes()?.saturating_sub(self.data_offset);
+        Ok(len as usize / self.page_size)
+    }
+    /// Length of the data source, leaving the stream where it was
+    pub fn stored_bytes(&mut self) -> BookwormResult<u64> {
+        let length_error = |err| {
+            BookwormError::new(
+                ErrorKind::Io,
+                "Could not read data source length".to_owned(),
+            )
+            .with_source(err)
+        };
+        let mut data_source = self.data_source.access();
+        let current = data_source.stream_position().map_err(length_error)?;
+        let len = data_source.seek(SeekFrom::End(0)).map_err(length_error)?;
+        data_source
+            .seek(SeekFrom::Start(current))
+            .map_err(length_error)?;
+        Ok(len)
+    }
+    /// What the pages count becomes once the data source holds `stored_pages`. Pages appended
+    /// past the physical end all count, so do preallocated slots before them.
+    pub fn count_for(&self, stored_pages: usize) -> usize {
+        let physical = self.capacity.max(self.pages_count);
+        if stored_pages > physical {
+            stored_pages
+        } else if stored_pages < physical {
+            self.pages_count.min(stored_pages)
+        } else {
+            self.pages_count
+        }
+    }
+    /// Adopts a data source that was resized behind the pager's back to `stored_pages`, now
+    /// holding `pages_count` pages
+    pub fn resync(&mut self, stored_pages: usize, pages_count: usize) {
+        self.discard(pages_count.min(self.pages_count)..usize::MAX);
+        self.pages_count = pages_count;
+        self.capacity = stored_pages;
+        self.clean_from = self.clean_from.min(stored_pages);
+    }
+    /// Extends an empty data source with zeroed pages so later pushes don't grow it
+    pub fn preallocate(&mut self, pages: usize) -> BookwormResult<()> {
+        self.discard(0..usize::MAX);
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        let len = data_source.seek(SeekFrom::End(0)).map_err(|err| {
+            BookwormError::new(
+                ErrorKind::Io,
+                "Could not read data source length".to_owned(),
+            )
+            .with_source(err)
+        })?;
+        if len != self.data_offset {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not preallocate: data source is not empty".to_owned(),
+            ));
+        }
+        data_source
+            .write_all(&vec![0; pages * self.page_size])
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not preallocate pages".to_owned())
+                    .with_source(err)
+            })?;
+        self.pages_count = 0;
+        self.capacity = pages;
+        self.clean_from = 0;
+        Ok(())
+    }
+    pub fn get_page<T: DeserializeOwned>(&mut self, page: usize) -> BookwormResult<T> {
+        self.check_live(page)?;
+        if self.layout == PageLayout::Chained {
+            let (record, _) = self.record_at(page)?;
+            return self.decode_record(&record);
         }
+        let mut buf = vec![0; self.page_size];
+        self.read_page_into(page, &mut buf)?;
+        self.deserialize(&buf)
+    }
+    /// Reads a page into `buf` and decodes a record borrowing from it, so strings and byte
+    /// slices point into the buffer instead of being copied out
+    pub fn get_page_ref<'de, T: Deserialize<'de>>(
+        &mut self,
+        page: usize,
+        buf: &'de mut Vec<u8>,
+    ) -> BookwormResult<T> {
+        self.check_live(page)?;
+        let record: &'de [u8] = if self.layout == PageLayout::Chained {
+            let (record, _) = self.record_at(page)?;
+            *buf = record;
+            buf
+        } else {
+            buf.resize(self.page_size, 0);
+            self.read_page_into(page, buf)?;
+            payload_of(self.layout, buf)?
+        };
+        self.decode_borrowed(record)
+    }
+    /// Decodes a record borrowing its strings and byte slices from `record`
+    pub(crate) fn decode_borrowed<'de, T: Deserialize<'de>>(
+        &self,
+        record: &'de [u8],
+    ) -> BookwormResult<T> {
+        let limit = record_limit(self.layout, self.decode_limit, record);
+        self.codec
+            .deserialize_borrowed(record, limit)
+            .map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Deserialization,
+                    "Could not parse data".to_string(),
+                )
+                .with_source(err)
+            })
+    }
+    /// Decodes a record from the data of a page or chain, without any header
+    pub fn decode_record<T: DeserializeOwned>(&self, record: &[u8]) -> BookwormResult<T> {
+        let limit = record_limit(self.layout, self.decode_limit, record);
+        self.codec.deserialize(record, limit).map_err(|err| {
+            BookwormError::new(
+                ErrorKind::Deserialization,
+                "Could not parse data".to_string(),
+            )
+            .with_source(err)
+        })
     }
-    pub fn get_page<T: DeserializeOwned + Debug>(&mut self, page: usize) -> BookwormResult<T> {
-        let raw_page = self.get_raw_page(page)?;
-        let parsed: T = bincode::deserialize(&raw_page)
-            .map_err(|_| BookwormError::new("Could not parse data".to_string()))?;
-        Ok(parsed)
+    /// Decodes a record from the raw bytes of a whole page
+    pub fn deserialize<T: DeserializeOwned>(&self, raw_page: &[u8]) -> BookwormResult<T> {
+        self.codec
+            .deserialize(payload_of(self.layout, raw_page)?, self.decode_limit)
+            .map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Deserialization,
+                    "Could not parse data".to_string(),
+                )
+                .with_source(err)
+            })
     }
+    /// Reads the data of a page, which is the whole page unless pages are length prefixed
     pub fn get_raw_page(&mut self, page: usize) -> BookwormResult<Vec<u8>> {
-        if page >= self.pages_count {
-            return Err(BookwormError::new("Page doesn't exist".to_string()));
+        if self.layout == PageLayout::Chained {
+            return Ok(self.record_at(page)?.0);
         }
-        let mut data_source = self.data_source.borrow_mut();
-        let page_offset = self.page_size * page;
-        let mut r = BufReader::new(&mut *data_source);
-        r.seek(SeekFrom::Start(page_offset as u64))
-            .map_err(|_| BookwormError::new("Could not read page data".to_string()))?;
         let mut buf = vec![0; self.page_size];
-        data_source
-            .read_exact(&mut buf)
-            .map_err(|_| BookwormError::new("Could not read page".to_string()))?;
+        let len = self.get_raw_page_into(page, &mut buf)?;
+        buf.truncate(len);
         Ok(buf)
     }
-    pub fn write_raw_page(&mut self, page: usize, data: &[u8]) -> BookwormResult<()> {
+    /// Reads the data of a page into the start of `buf`, which must hold at least a page,
+    /// returning how many bytes were filled
+    pub fn get_raw_page_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<usize> {
+        self.check_page_buffer(buf)?;
+        self.check_live(page)?;
+        if self.layout == PageLayout::Chained {
+            let (record, _) = self.record_at(page)?;
+            let Some(slot) = buf.get_mut(..record.len()) else {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                    "Could not read page: buffer holds {} bytes but the record is {} bytes long",
+                    buf.len(),
+                    record.len()
+                ),
+                ));
+            };
+            slot.copy_from_slice(&record);
+            return Ok(record.len());
+        }
+        self.read_page_into(page, &mut buf[..self.page_size])?;
+        let len = payload_of(self.layout, &buf[..self.page_size])?.len();
+        let header = header_bytes(self.layout);
+        buf.copy_within(header..header + len, 0);
+        Ok(len)
+    }
+    /// Fails unless `buf` holds at least a page
+    pub(crate) fn check_page_buffer(&self, buf: &[u8]) -> BookwormResult<()> {
+        if buf.len() < self.page_size {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Could not read page: buffer holds {} bytes but pages are {} bytes long",
+                    buf.len(),
+                    self.page_size
+                ),
+            ));
+        }
+        Ok(())
+    }
+    /// Reads the data of the record starting at `page` along with how many pages it spans,
+    /// following the chain when pages are chained
+    pub fn record_at(&mut self, page: usize) -> BookwormResult<(Vec<u8>, usize)> {
+        self.check_live(page)?;
+        let mut buf = vec![0; self.page_size];
+        if self.layout != PageLayout::Chained {
+            let len = self.get_raw_page_into(page, &mut buf)?;
+            buf.truncate(len);
+            return Ok((buf, 1));
+        }
+        let mut record = Vec::new();
+        let mut current = page;
+        loop {
+            self.read_page_into(current, &mut buf)?;
+            let header = ChainHeader::of(&buf)?;
+            if header.continuation != (current != page) {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    match current == page {
+                        true => format!(
+                            "Could not read page {}: it continues a record started earlier",
+                            page
+                        ),
+                        false => format!("Could not read page {}: its chain is broken", page),
+                    },
+                ));
+            }
+            record.extend_from_slice(&buf[CHAIN_HEADER_BYTES..CHAIN_HEADER_BYTES + header.len]);
+            if header.next == 0 {
+                return Ok((record, current + 1 - page));
+            }
+            current += header.next;
+        }
+    }
+    /// First page of the record that ends right before `end`
+    pub fn record_start(&mut self, end: usize) -> BookwormResult<usize> {
+        if self.layout != PageLayout::Chained || end == 0 {
+            return Ok(end.saturating_sub(1));
+        }
+        let mut buf = vec![0; self.page_size];
+        let mut page = end - 1;
+        loop {
+            self.read_page_into(page, &mut buf)?;
+            if !ChainHeader::of(&buf)?.continuation || page == 0 {
+                return Ok(page);
+            }
+            page -= 1;
+        }
+    }
+    /// Reads a whole page into `buf`, which must be exactly one page long
+    pub fn read_page_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
         if page >= self.pages_count {
-            return Err(BookwormError::new("Page doesn't exist".to_string()));
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        self.read_slot_into(page, buf)
+    }
+    /// Same as `read_page_into`, but also reaches pages past the count that are still
+    /// physically present, like popped or preallocated ones
+    pub fn read_slot_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
+        if page >= self.capacity.max(self.pages_count) {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        let single = buf.len() == self.page_size;
+        if let Some(raw_page) = self.dirty().get(&page).filter(|_| single) {
+            buf.copy_from_slice(raw_page);
+            return Ok(());
+        }
+        if let Some(cached) = self
+            .cache
+            .as_mut()
+            .filter(|_| single)
+            .and_then(|cache| cache.get(page))
+        {
+            buf.copy_from_slice(cached);
+            return Ok(());
+        }
+        self.read_stored_into(page, buf)?;
+        if let Some(cache) = self.cache.as_mut().filter(|_| single) {
+            cache.insert(page, buf.to_vec());
+        }
+        let dirty = self.dirty();
+        for (page, raw_page) in (page..).zip(buf.chunks_mut(self.page_size)) {
+            if let Some(dirty) = dirty.get(&page) {
+                raw_page.copy_from_slice(&dirty[..raw_page.len()]);
+            }
         }
-        if data.len() > self.page_size {
+        Ok(())
+    }
+    /// Reads a whole page as it stands, from memory when it's dirty, otherwise from the data
+    /// source past the cache and without checking its checksum
+    pub fn read_unverified_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
+        if page >= self.pages_count {
             return Err(BookwormError::new(
-                "Could not write data to page: data is bigger than page".to_string(),
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
             ));
         }
-        let mut data_source = self.data_source.borrow_mut();
-        let page_offset = self.page_size * page;
+        self.check_page_buffer(buf)?;
+        if let Some(raw_page) = self.dirty().get(&page) {
+            buf.copy_from_slice(raw_page);
+            return Ok(());
+        }
+        self.read_bytes_into(page, buf)
+    }
+    fn read_stored_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
+        self.read_bytes_into(page, buf)?;
+        if let PageLayout::Checksummed(algorithm) = self.layout {
+            for (page, raw_page) in (page..).zip(buf.chunks(self.page_size)) {
+                verify_checksum(algorithm, page, raw_page)?;
+            }
+        }
+        Ok(())
+    }
+    fn read_bytes_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
+        self.position = None;
+        let mut data_source = self.data_source.access();
         data_source
-            .seek(SeekFrom::Start(page_offset as u64))
-            .map_err(|_| BookwormError::new("Could not write to page".to_string()))?;
+            .seek(SeekFrom::Start(self.offset_of(page)))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not seek to page {}", page))
+                    .with_source(err)
+                    .at_page(page)
+            })?;
+        data_source.read_exact(buf).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not read page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })
+    }
+    /// Checks the header and the checksum of a page already read into memory
+    pub(crate) fn verify_raw_page(&self, page: usize, raw_page: &[u8]) -> PageVerification {
+        let mut verification = PageVerification::new(page);
+        let header = match self.layout {
+            PageLayout::Padded => return verification,
+            PageLayout::LengthPrefixed => payload_of(self.layout, raw_page).map(drop),
+            PageLayout::Chained => ChainHeader::of(raw_page).and_then(|_| {
+                match raw_page[CHAIN_HEADER_BYTES - 1] & !CONTINUATION_FLAG {
+                    0 => Ok(()),
+                    flags => Err(BookwormError::new(
+                        ErrorKind::Corrupted,
+                        format!("Could not read page: unknown chain flags {:#04x}", flags),
+                    )),
+                }
+            }),
+            PageLayout::Checksummed(algorithm) => checksum_header(algorithm, raw_page)
+                .and_then(|_| payload_of(self.layout, raw_page))
+                .map(drop),
+        };
+        if let Err(err) = header {
+            verification.header = Check::Failed(err.to_string());
+            return verification;
+        }
+        verification.header = Check::Passed;
+        if let PageLayout::Checksummed(algorithm) = self.layout {
+            verification.checksum = match verify_checksum(algorithm, page, raw_page) {
+                Ok(()) => Check::Passed,
+                Err(err) => {
+                    verification.mismatch = err.mismatch();
+                    Check::Failed(err.to_string())
+                }
+            };
+        }
+        verification
+    }
+    /// Reads the file header back, failing when it isn't the one the pages were opened with
+    pub fn check_file_header(&mut self) -> BookwormResult<()> {
+        self.position = None;
+        let header = FileHeader::read_from(&mut *self.data_source.access())?;
+        if header.page_size != self.page_size || header.layout != self.layout {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "File header records {} byte {:?} pages, the store was opened with {} byte \
+                     {:?} pages",
+                    header.page_size, header.layout, self.page_size, self.layout
+                ),
+            ));
+        }
+        if header.oversize != self.oversize {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "File header records the {:?} oversize policy, the store uses {:?}",
+                    header.oversize, self.oversize
+                ),
+            ));
+        }
+        if header.live_bitmap != (self.bitmap_pages > 0) {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "File header records {} live bitmap, the store has {}",
+                    if header.live_bitmap { "a" } else { "no" },
+                    if self.bitmap_pages > 0 { "one" } else { "none" }
+                ),
+            ));
+        }
+        if header.pages_count != self.recorded_count {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "File header records a pages count of {:?}, the store recorded {:?}",
+                    header.pages_count, self.recorded_count
+                ),
+            ));
+        }
+        Ok(())
+    }
+    /// Reads a run of contiguous pages into `buf`, a whole number of pages long, with one read
+    pub fn read_pages_into(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
+        if page + buf.len() / self.page_size > self.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        self.read_slot_into(page, buf)
+    }
+    pub fn page_fill(&mut self, page: usize) -> BookwormResult<PageFill> {
+        let mut buf = vec![0; self.page_size];
+        self.read_page_into(page, &mut buf)?;
+        Ok(self.fill_of(&buf))
+    }
+    /// Whether a physical page holds no payload. Headerless pages count as empty when every
+    /// byte is zero, so a record that serializes to zeroes is indistinguishable from a free slot.
+    pub fn is_slot_empty(&mut self, page: usize) -> BookwormResult<bool> {
+        let mut buf = vec![0; self.page_size];
+        self.read_slot_into(page, &mut buf)?;
+        Ok(self.fill_of(&buf).payload_bytes == 0)
+    }
+    /// Measures a page that was already read into memory
+    pub fn fill_of(&self, raw_page: &[u8]) -> PageFill {
+        let payload_bytes = payload_len(self.layout, raw_page);
+        PageFill {
+            payload_bytes,
+            capacity: self.page_size,
+            fraction: payload_bytes as f64 / self.page_size as f64,
+            exact: matches!(
+                self.layout,
+                PageLayout::LengthPrefixed | PageLayout::Checksummed(_)
+            ),
+        }
+    }
+    pub fn write_raw_page(&mut self, page: usize, data: &[u8]) -> BookwormResult<()> {
+        if page >= self.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        let mut framed = Vec::new();
+        let data = self.frame(data, &mut framed)?;
+        self.discard(page..page + 1);
+        if self.write_back_mode {
+            let mut raw_page = data.to_vec();
+            raw_page.resize(self.page_size, 0);
+            self.dirty().insert(page, raw_page);
+            return Ok(());
+        }
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .seek(SeekFrom::Start(self.offset_of(page)))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not seek to page {}", page))
+                    .with_source(err)
+                    .at_page(page)
+            })?;
         let remaining_space = self.page_size - data.len();
+        data_source.write_all(data).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not write page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })?;
+        let is_clean = page >= self.clean_from && page < self.capacity;
+        if !is_clean {
+            data_source
+                .write_all(&vec![0; remaining_space])
+                .map_err(|err| {
+                    BookwormError::new(ErrorKind::Io, format!("Could not write page {}", page))
+                        .with_source(err)
+                        .at_page(page)
+                })?;
+        }
+        self.capacity = self.capacity.max(page + 1);
+        self.clean_from = self.clean_from.max(page + 1);
+        Ok(())
+    }
+    /// Writes a run of contiguous full pages starting at `page` with vectored writes, one slice
+    /// per page. Sources keeping the default `write_vectored` end up taking a write per page.
+    pub fn write_raw_pages(&mut self, page: usize, pages: &[u8]) -> BookwormResult<()> {
+        let count = pages.len() / self.page_size;
+        if page + count > self.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        self.discard(page..page + count);
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source
+            .seek(SeekFrom::Start(self.offset_of(page)))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not seek to page {}", page))
+                    .with_source(err)
+                    .at_page(page)
+            })?;
+        let mut slices: Vec<IoSlice> = pages.chunks(self.page_size).map(IoSlice::new).collect();
+        let mut slices = &mut slices[..];
+        while !slices.is_empty() {
+            let written = data_source.write_vectored(slices).map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not write page {}", page))
+                    .with_source(err)
+                    .at_page(page)
+            })?;
+            if written == 0 {
+                return Err(BookwormError::new(
+                    ErrorKind::Io,
+                    format!("Could not write page {}", page),
+                ));
+            }
+            self.vectored_batches += 1;
+            IoSlice::advance_slices(&mut slices, written);
+        }
+        self.capacity = self.capacity.max(page + count);
+        self.clean_from = self.clean_from.max(page + count);
+        Ok(())
+    }
+    /// Writes `bytes` at `offset` past the file header as they are, with no page layout, for
+    /// bookkeeping like the transaction journal
+    pub fn write_unframed(&mut self, offset: u64, bytes: &[u8]) -> BookwormResult<()> {
+        let first = offset as usize / self.page_size;
+        let end = (offset as usize + bytes.len()).div_ceil(self.page_size);
+        self.discard(first..end);
+        self.position = None;
+        let mut data_source = self.data_source.access();
         data_source
-            .write_all(&data)
-            .map_err(|_| BookwormError::new("Could not write page".to_string()))?;
+            .seek(SeekFrom::Start(self.data_offset + offset))
+            .and_then(|_| data_source.write_all(bytes))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not write data source".to_owned())
+                    .with_source(err)
+            })?;
+        self.capacity = self.capacity.max(end);
+        self.clean_from = self.clean_from.max(end);
+        Ok(())
+    }
+    /// Bytes the data source holds past the file header, as far as `read_unframed` reaches
+    pub fn unframed_len(&mut self) -> BookwormResult<u64> {
+        Ok(self.stored_bytes()?.saturating_sub(self.data_offset))
+    }
+    /// Reads what `write_unframed` wrote, failing when the data source ends first
+    pub fn read_unframed(&mut self, offset: u64, buf: &mut [u8]) -> BookwormResult<()> {
+        self.position = None;
+        let mut data_source = self.data_source.access();
         data_source
-            .write_all(&vec![0; remaining_space])
-            .map_err(|_| BookwormError::new("Could not write page".to_string()))?;
+            .seek(SeekFrom::Start(self.data_offset + offset))
+            .and_then(|_| data_source.read_exact(buf))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not read data source".to_owned())
+                    .with_source(err)
+            })
+    }
+    /// Reads `len` bytes starting at `offset` within the payload area of a page, past the
+    /// header the layout keeps in front of it
+    pub fn read_at(&mut self, page: usize, offset: usize, len: usize) -> BookwormResult<Vec<u8>> {
+        self.position_within(page, offset, len)?;
+        self.read_span(page, header_bytes(self.layout) + offset, len)
+    }
+    /// Reads `len` bytes starting `start` bytes into a page, header included
+    fn read_span(&mut self, page: usize, start: usize, len: usize) -> BookwormResult<Vec<u8>> {
+        if let Some(raw_page) = self.dirty().get(&page) {
+            return Ok(raw_page[start..start + len].to_vec());
+        }
+        self.position = None;
+        let position = self.offset_of(page) + start as u64;
+        let mut data_source = self.data_source.access();
+        data_source.seek(SeekFrom::Start(position)).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not seek to page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })?;
+        let mut buf = vec![0; len];
+        data_source.read_exact(&mut buf).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not read page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })?;
+        Ok(buf)
+    }
+    /// Overwrites only the bytes starting at `offset` within the payload area of a page,
+    /// refused for checksummed pages as it would leave their checksum stale
+    pub fn write_at(&mut self, page: usize, offset: usize, data: &[u8]) -> BookwormResult<()> {
+        if let PageLayout::Checksummed(_) = self.layout {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not write page: partial writes would leave its checksum stale".to_string(),
+            ));
+        }
+        let position = self.position_within(page, offset, data.len())?;
+        let start = header_bytes(self.layout) + offset;
+        self.uncache(page..page + 1);
+        if let Some(raw_page) = self.dirty().get_mut(&page) {
+            raw_page[start..start + data.len()].copy_from_slice(data);
+            return Ok(());
+        }
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        data_source.seek(SeekFrom::Start(position)).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not seek to page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })?;
+        data_source.write_all(data).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not write page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })?;
         Ok(())
     }
+    /// Byte position of `offset` within the payload area of a page, checking the range fits it
+    fn position_within(&self, page: usize, offset: usize, len: usize) -> BookwormResult<u64> {
+        if page >= self.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= self.payload_capacity() => {
+                Ok(self.offset_of(page) + (header_bytes(self.layout) + offset) as u64)
+            }
+            _ => Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not access page: range exceeds what a page holds".to_string(),
+            )),
+        }
+    }
     pub fn write_page<T: Serialize>(&mut self, page: usize, data: &T) -> BookwormResult<()> {
         if page >= self.pages_count {
-            return Err(BookwormError::new("Page doesn't exist".to_string()));
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        let serialized = self.serialize(data)?;
+        self.write_raw_page(page, &serialized).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, "Could not write page".to_string()).with_source(err)
+        })?;
+        Ok(())
+    }
+    /// Serializes a record, making sure it fits in a page
+    pub fn serialize<T: Serialize>(&self, data: &T) -> BookwormResult<Vec<u8>> {
+        let serialized = self.codec.serialize(data).map_err(|err| {
+            BookwormError::new(
+                ErrorKind::Serialization,
+                "Could not serialize data".to_string(),
+            )
+            .with_source(err)
+        })?;
+        if self.layout != PageLayout::Chained {
+            self.check_fits(&serialized)?;
         }
-        let serialized = bincode::serialize(data)
-            .map_err(|_| BookwormError::new("Could not serialize data".to_string()))?;
-        if serialized.len() > self.page_size {
+        Ok(serialized)
+    }
+    /// Bytes of data a page can hold
+    pub fn payload_capacity(&self) -> usize {
+        match self.layout {
+            PageLayout::Padded => self.page_size,
+            PageLayout::LengthPrefixed => self.page_size.saturating_sub(LENGTH_PREFIX_BYTES),
+            PageLayout::Chained => self.page_size.saturating_sub(CHAIN_HEADER_BYTES),
+            PageLayout::Checksummed(_) => self.page_size.saturating_sub(header_bytes(self.layout)),
+        }
+    }
+    fn check_fits(&self, data: &[u8]) -> BookwormResult<()> {
+        if data.len() > self.payload_capacity() {
             return Err(BookwormError::new(
+                ErrorKind::DataTooLarge,
                 "Could not write data to page: data is bigger than page".to_string(),
             ));
         }
-        self.write_raw_page(page, &serialized)
-            .map_err(|_| BookwormError::new("Could not write page".to_string()))?;
         Ok(())
     }
-    pub fn into_raw_iterator(self, starting_page: usize) -> RawPagerIterator<S> {
-        let mut data_source = self.data_source.borrow_mut();
-        let _ = data_source.seek(SeekFrom::Start((self.page_size * starting_page) as u64));
-        drop(data_source);
-        RawPagerIterator {
-            page_size: self.page_size,
-            data_source: self.data_source,
+    /// Lays `data` out as the start of a page, using `framed` when it needs a header
+    fn frame<'d>(&self, data: &'d [u8], framed: &'d mut Vec<u8>) -> BookwormResult<&'d [u8]> {
+        self.check_fits(data)?;
+        match self.layout {
+            PageLayout::Padded => Ok(data),
+            PageLayout::LengthPrefixed => {
+                framed.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                framed.extend_from_slice(data);
+                Ok(framed)
+            }
+            PageLayout::Chained => {
+                ChainHeader::single(data.len()).write_to(framed);
+                framed.extend_from_slice(data);
+                Ok(framed)
+            }
+            PageLayout::Checksummed(algorithm) => {
+                let digest = algorithm.digest(data);
+                framed.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                framed.push(digest.as_bytes().len() as u8);
+                framed.extend_from_slice(digest.as_bytes());
+                framed.extend_from_slice(data);
+                Ok(framed)
+            }
         }
     }
-    pub fn into_iterator<T: DeserializeOwned>(self, starting_page: usize) -> PagerIterator<S, T> {
-        let mut data_source = self.data_source.borrow_mut();
-        let _ = data_source.seek(SeekFrom::Start((self.page_size * starting_page) as u64));
-        drop(data_source);
-        PagerIterator {
+    /// Appends `data` to `pages` as a whole page, for runs written with `write_raw_pages`
+    /// Chained pages take as many pages as the data needs.
+    pub fn stage_page(&self, data: &[u8], pages: &mut Vec<u8>) -> BookwormResult<()> {
+        if self.layout == PageLayout::Chained && data.len() > self.payload_capacity() {
+            let capacity = self.payload_capacity();
+            if capacity == 0 {
+                return Err(BookwormError::new(
+                    ErrorKind::DataTooLarge,
+                    "Could not write data to page: pages can't hold a chain header".to_string(),
+                ));
+            }
+            let chunks = data.chunks(capacity).count();
+            for (index, chunk) in data.chunks(capacity).enumerate() {
+                ChainHeader {
+                    len: chunk.len(),
+                    next: usize::from(index + 1 < chunks),
+                    continuation: index > 0,
+                }
+                .write_to(pages);
+                pages.extend_from_slice(chunk);
+                pages.resize(pages.len() + capacity - chunk.len(), 0);
+            }
+            return Ok(());
+        }
+        let mut framed = Vec::new();
+        let data = self.frame(data, &mut framed)?;
+        pages.extend_from_slice(data);
+        pages.resize(pages.len() + self.page_size - data.len(), 0);
+        Ok(())
+    }
+    /// Describes a page reading only its header, headerless pages are reported as full. A
+    /// chained page reports the part of its record it holds itself.
+    pub fn page_info(&mut self, page: usize) -> BookwormResult<PageInfo> {
+        if page >= self.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                "Page doesn't exist".to_string(),
+            ));
+        }
+        let mut info = PageInfo {
+            index: page,
+            payload_len: self.page_size,
+            checksum: None,
+            flags: None,
+            written_at: None,
+        };
+        if self.layout == PageLayout::Padded {
+            return Ok(info);
+        }
+        // every header starts with the length of the payload as a u32
+        let header = self.read_span(page, 0, header_bytes(self.layout))?;
+        info.payload_len =
+            u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if info.payload_len > self.payload_capacity() {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Could not read page: stored length {} is over the {} bytes a page holds",
+                    info.payload_len,
+                    self.payload_capacity()
+                ),
+            )
+            .at_page(page));
+        }
+        match self.layout {
+            PageLayout::Checksummed(algorithm) => {
+                info.checksum = checksum_header(algorithm, &header)
+                    .map_err(|err| err.at_page(page))?
+                    .map(|(digest, _)| Digest::from_bytes(digest));
+            }
+            PageLayout::Chained => info.flags = Some(header[8]),
+            _ => {}
+        }
+        Ok(info)
+    }
+    /// Records starting among the live pages of `pages`. Chained pages are told apart by their
+    /// header, a header that can't be read counts as a record.
+    fn records_in(&mut self, pages: Range<usize>) -> usize {
+        if self.layout != PageLayout::Chained {
+            return live_count(&self.free, pages);
+        }
+        self.record_starts_in(pages).len()
+    }
+    /// First pages of the records starting among the live pages of `pages`, see `records_in`
+    pub(crate) fn record_starts_in(&mut self, pages: Range<usize>) -> Vec<usize> {
+        let mut starts = Vec::new();
+        for page in pages {
+            if self.free.contains(&page) {
+                continue;
+            }
+            let starts_record = self.layout != PageLayout::Chained
+                || self
+                    .read_span(page, 0, CHAIN_HEADER_BYTES)
+                    .map_or(true, |header| header[8] & CONTINUATION_FLAG == 0);
+            if starts_record {
+                starts.push(page);
+            }
+        }
+        starts
+    }
+    /// Creates an iterator that owns a handle to the data source
+    pub fn raw_iterator(&self, starting_page: usize) -> RawPagerIterator<S, H> {
+        self.raw_iterator_range(starting_page..self.pages_count)
+    }
+    /// Same as `raw_iterator`, stopping at the end of `pages` if it comes before the count
+    pub fn raw_iterator_range(&self, pages: Range<usize>) -> RawPagerIterator<S, H> {
+        let mut iterator = RawPagerIterator {
             page_size: self.page_size,
-            data_source: self.data_source,
+            data_offset: self.data_offset,
+            layout: self.layout,
+            front: pages.start,
+            back: pages.end.min(self.pages_count).max(pages.start),
+            records: 0,
+            stream_at: None,
+            data_source: self.data_source.clone(),
+            free: self.free.clone(),
+            dirty: self.dirty().clone(),
+            _storage: std::marker::PhantomData,
+        };
+        iterator.records = iterator.count_records();
+        iterator
+    }
+    /// Creates an iterator that owns a handle to the data source
+    pub fn iterator<T: DeserializeOwned>(&self, starting_page: usize) -> PagerIterator<S, T, C, H> {
+        self.iterator_range(starting_page..self.pages_count)
+    }
+    pub fn iterator_range<T: DeserializeOwned>(
+        &self,
+        pages: Range<usize>,
+    ) -> PagerIterator<S, T, C, H> {
+        PagerIterator {
+            raw: self.raw_iterator_range(pages),
+            decode_limit: self.decode_limit,
+            codec: self.codec.clone(),
             _marker: Default::default(),
         }
     }
     /// Creates a iterator without dropping the pager
-    pub fn iter<T: DeserializeOwned + Debug>(&mut self, starting_page: usize) -> PagerIter<S, T> {
+    pub fn iter<T: DeserializeOwned + Debug>(
+        &mut self,
+        starting_page: usize,
+    ) -> PagerIter<'_, S, T, C, H> {
         PagerIter {
             curr_pos: starting_page,
+            back: self.pages_count.max(starting_page),
+            records: self.records_in(starting_page..self.pages_count),
             pager: self,
-            _marker: std::marker::PhantomData::default(),
+            _marker: std::marker::PhantomData,
         }
     }
     /// Creates a raw iterator without dropping the pager
-    pub fn raw_iter(&mut self, starting_page: usize) -> RawPagerIter<S> {
+    pub fn raw_iter(&mut self, starting_page: usize) -> RawPagerIter<'_, S, C, H> {
         RawPagerIter {
             curr_pos: starting_page,
+            back: self.pages_count.max(starting_page),
+            records: self.records_in(starting_page..self.pages_count),
+            pager: self,
+        }
+    }
+    /// Creates an iterator yielding an error for each page that can't be read or decoded and
+    /// going on with the next page
+    pub fn iter_fallible<T: DeserializeOwned>(
+        &mut self,
+        starting_page: usize,
+    ) -> FalliblePagerIter<'_, S, T, C, H> {
+        FalliblePagerIter {
+            raw: self.raw_iter_fallible(starting_page),
+            _marker: std::marker::PhantomData,
+        }
+    }
+    /// Same as `iter_fallible`, yielding raw pages
+    pub fn raw_iter_fallible(&mut self, starting_page: usize) -> FallibleRawPagerIter<'_, S, C, H> {
+        FallibleRawPagerIter {
+            curr_pos: starting_page,
+            back: self.pages_count.max(starting_page),
             pager: self,
         }
     }
+    /// Serializes before growing, so a failing or panicking serializer leaves the count as it was
     pub fn push<T: Serialize>(&mut self, data: &T) -> BookwormResult<()> {
-        self.pages_count += 1;
-        self.write_page(self.pages_count - 1, data)?;
-        Ok(())
+        let serialized = self.serialize(data)?;
+        self.push_raw(&serialized)
     }
     pub fn push_raw(&mut self, data: &[u8]) -> BookwormResult<()> {
+        if self.append_mode {
+            return self.append_raw(data);
+        }
+        if self.layout == PageLayout::Chained && data.len() > self.payload_capacity() {
+            let mut pages = Vec::new();
+            self.stage_page(data, &mut pages)?;
+            return self.push_raw_pages(&pages);
+        }
         self.pages_count += 1;
-        self.write_raw_page(self.pages_count - 1, data)?;
+        if let Err(err) = self.write_raw_page(self.pages_count - 1, data) {
+            self.pages_count -= 1;
+            return Err(err);
+        }
+        Ok(())
+    }
+    /// Pushes a run of full pages, see `write_raw_pages`
+    pub fn push_raw_pages(&mut self, pages: &[u8]) -> BookwormResult<()> {
+        let start = self.pages_count;
+        self.pages_count += pages.len() / self.page_size;
+        if let Err(err) = self.write_raw_pages(start, pages) {
+            self.pages_count = start;
+            return Err(err);
+        }
+        Ok(())
+    }
+    /// Writes a page at the tail with a single write, seeking only when the stream was moved
+    /// since the last append
+    fn append_raw(&mut self, data: &[u8]) -> BookwormResult<()> {
+        let mut page = Vec::with_capacity(self.page_size);
+        self.stage_page(data, &mut page)?;
+        self.append_pages(&page)
+    }
+    /// Writes a run of full pages at the tail with a single write, seeking only when the stream
+    /// was moved since the last append. The count only grows once the write went through.
+    pub fn append_pages(&mut self, pages: &[u8]) -> BookwormResult<()> {
+        self.discard(self.pages_count..self.pages_count + pages.len() / self.page_size);
+        let tail = self.offset_of(self.pages_count);
+        let mut data_source = self.data_source.access();
+        if self.position.take() != Some(tail) {
+            data_source.seek(SeekFrom::Start(tail)).map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not write to page".to_string())
+                    .with_source(err)
+            })?;
+            self.corrective_seeks += 1;
+        }
+        data_source.write_all(pages).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, "Could not write page".to_string()).with_source(err)
+        })?;
+        self.position = Some(tail + pages.len() as u64);
+        self.pages_count += pages.len() / self.page_size;
+        self.capacity = self.capacity.max(self.pages_count);
+        self.clean_from = self.clean_from.max(self.pages_count);
         Ok(())
     }
+    /// Removes the last record, along with every page of its chain
     pub fn pop(&mut self) -> BookwormResult<()> {
+        if self.layout == PageLayout::Chained {
+            let pages_count = self.pages_count;
+            self.pages_count = self.record_start(pages_count)?;
+            return self.zero_pages(self.pages_count..pages_count);
+        }
         self.pages_count -= 1;
-        let page_offset = self.pages_count * self.page_size;
-        let mut data_source = self.data_source.borrow_mut();
+        self.zero_page(self.pages_count)
+    }
+    /// Overwrites a page with zeroes, regardless of it being past the pages count
+    pub fn zero_page(&mut self, page: usize) -> BookwormResult<()> {
+        self.discard(page..page + 1);
+        let page_offset = self.offset_of(page);
+        self.position = None;
+        let mut data_source = self.data_source.access();
         data_source
-            .seek(SeekFrom::Start(page_offset as u64))
-            .map_err(|_| BookwormError::new("Could not read page".to_owned()))?;
+            .seek(SeekFrom::Start(page_offset))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not read page {}", page))
+                    .with_source(err)
+                    .at_page(page)
+            })?;
         let data = vec![0; self.page_size];
+        data_source.write_all(&data).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, format!("Could not remove page {}", page))
+                .with_source(err)
+                .at_page(page)
+        })?;
+        Ok(())
+    }
+    /// Zeroes a run of pages after a single seek, writing a bounded buffer at a time
+    pub fn zero_pages(&mut self, pages: Range<usize>) -> BookwormResult<()> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+        self.discard(pages.clone());
+        self.position = None;
+        let mut data_source = self.data_source.access();
         data_source
-            .write_all(&data)
-            .map_err(|_| BookwormError::new("Could not remove page".to_owned()))?;
+            .seek(SeekFrom::Start(self.offset_of(pages.start)))
+            .map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Io,
+                    format!("Could not read page {}", pages.start),
+                )
+                .with_source(err)
+                .at_page(pages.start)
+            })?;
+        let zeroes = vec![0; self.page_size * pages.len().min(ZERO_BATCH_PAGES)];
+        let mut remaining = pages.len() * self.page_size;
+        while remaining > 0 {
+            let chunk = remaining.min(zeroes.len());
+            data_source.write_all(&zeroes[..chunk]).map_err(|err| {
+                BookwormError::new(
+                    ErrorKind::Io,
+                    format!("Could not remove page {}", pages.start),
+                )
+                .with_source(err)
+                .at_page(pages.start)
+            })?;
+            remaining -= chunk;
+        }
+        if pages.start < self.clean_from && pages.end >= self.clean_from {
+            self.clean_from = pages.start;
+        }
         Ok(())
     }
+    /// Runs `sync` on the data source, which pushes its writes to the disk
+    pub fn sync_with(&mut self, sync: fn(&mut S) -> std::io::Result<()>) -> BookwormResult<()> {
+        sync(&mut self.data_source.access()).map_err(|err| {
+            BookwormError::new(ErrorKind::Io, "Could not sync data source".to_owned())
+                .with_source(err)
+        })
+    }
+    pub fn flush(&mut self) -> BookwormResult<()> {
+        self.data_source.access().flush().map_err(|err| {
+            BookwormError::new(ErrorKind::Io, "Could not flush data source".to_owned())
+                .with_source(err)
+        })
+    }
+    /// Flushes the data source, then runs `sync` on it when given
+    pub fn persist(
+        &mut self,
+        sync: Option<fn(&mut S) -> std::io::Result<()>>,
+    ) -> BookwormResult<()> {
+        self.flush()?;
+        match sync {
+            Some(sync) => self.sync_with(sync),
+            None => Ok(()),
+        }
+    }
     pub fn clear(&mut self) {
         self.pages_count = 0;
+        self.free.clear();
+        self.discard(0..usize::MAX);
+    }
+    /// Keeps up to `pages` recently read pages in memory, dropping whatever was cached before
+    pub fn enable_cache(&mut self, pages: usize) {
+        self.cache = Some(PageCache::new(pages));
+    }
+    /// Reads served from the cache and reads that had to go to the data source
+    pub fn cache_counts(&self) -> (usize, usize) {
+        self.cache
+            .as_ref()
+            .map_or((0, 0), |cache| (cache.hits, cache.misses))
+    }
+    pub fn cached_pages(&self) -> usize {
+        self.cache.as_ref().map_or(0, PageCache::len)
+    }
+    /// Drops the cached copies of pages whose stored bytes are about to change
+    fn uncache(&mut self, pages: Range<usize>) {
+        if let Some(cache) = &mut self.cache {
+            cache.invalidate(pages);
+        }
+    }
+    /// Drops the dirty copies of pages about to be overwritten as a whole, along with the
+    /// cached ones
+    fn discard(&mut self, pages: Range<usize>) {
+        self.uncache(pages.clone());
+        self.dirty().retain(|page, _| !pages.contains(page));
+    }
+    fn dirty(&self) -> MutexGuard<'_, BTreeMap<usize, Vec<u8>>> {
+        self.dirty.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    /// The dirty pages along with where pages start in the data source, for writing them back
+    /// from another thread
+    #[cfg(feature = "background-flush")]
+    pub(crate) fn dirty_handle(&self) -> (DirtyPages, u64) {
+        (self.dirty.clone(), self.data_offset)
+    }
+    /// Pages overwritten in write back mode that the data source doesn't hold yet, ascending
+    pub fn dirty_pages(&self) -> Vec<usize> {
+        self.dirty().keys().copied().collect()
+    }
+    /// Writes the dirty pages to the data source in ascending order, runs of adjacent pages
+    /// with a single write
+    pub fn write_back(&mut self) -> BookwormResult<()> {
+        self.position = None;
+        let end = write_dirty(
+            &mut self.dirty(),
+            &mut *self.data_source.access(),
+            self.data_offset,
+            self.page_size,
+        )?;
+        self.capacity = self.capacity.max(end);
+        self.clean_from = self.clean_from.max(end);
+        Ok(())
+    }
+    /// Borrows the pager so that it gets cleared once the borrow ends, even while unwinding
+    pub fn clear_on_drop(&mut self) -> ClearOnDrop<'_, S, C, H> {
+        ClearOnDrop { pager: self }
+    }
+    /// Zeroes everything the data source holds past the file header and resets the pages count
+    pub fn erase(&mut self) -> BookwormResult<()> {
+        self.discard(0..usize::MAX);
+        self.position = None;
+        let mut data_source = self.data_source.access();
+        let len = data_source.seek(SeekFrom::End(0)).map_err(|err| {
+            BookwormError::new(
+                ErrorKind::Io,
+                "Could not read data source length".to_owned(),
+            )
+            .with_source(err)
+        })?;
+        data_source
+            .seek(SeekFrom::Start(self.data_offset))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not erase data source".to_owned())
+                    .with_source(err)
+            })?;
+        let zeroes = vec![0; self.page_size];
+        let mut remaining = len.saturating_sub(self.data_offset) as usize;
+        while remaining > 0 {
+            let chunk = remaining.min(self.page_size);
+            data_source.write_all(&zeroes[..chunk]).map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not erase data source".to_owned())
+                    .with_source(err)
+            })?;
+            remaining -= chunk;
+        }
+        self.pages_count = 0;
+        self.free.clear();
+        Ok(())
+    }
+}
+
+/// The data held by a whole page laid out as `layout`, checking a length prefix against the
+/// room there is for it
+pub(crate) fn payload_of(layout: PageLayout, raw_page: &[u8]) -> BookwormResult<&[u8]> {
+    match layout {
+        PageLayout::Padded => Ok(raw_page),
+        PageLayout::LengthPrefixed => {
+            let Some((prefix, data)) = raw_page.split_first_chunk::<LENGTH_PREFIX_BYTES>() else {
+                return Err(BookwormError::new(
+                    ErrorKind::Corrupted,
+                    "Could not read page: it can't hold a length prefix".to_string(),
+                ));
+            };
+            let len = u32::from_le_bytes(*prefix) as usize;
+            data.get(..len).ok_or_else(|| {
+                BookwormError::new(
+                    ErrorKind::Corrupted,
+                    format!(
+                        "Could not read page: stored length {} is over the {} bytes a page holds",
+                        len,
+                        data.len()
+                    ),
+                )
+            })
+        }
+        PageLayout::Chained => {
+            let header = ChainHeader::of(raw_page)?;
+            Ok(&raw_page[CHAIN_HEADER_BYTES..CHAIN_HEADER_BYTES + header.len])
+        }
+        PageLayout::Checksummed(algorithm) => {
+            let Some((_, data)) = checksum_header(algorithm, raw_page)? else {
+                return Ok(&[]);
+            };
+            let len =
+                u32::from_le_bytes([raw_page[0], raw_page[1], raw_page[2], raw_page[3]]) as usize;
+            data.get(..len).ok_or_else(|| {
+                BookwormError::new(
+                    ErrorKind::Corrupted,
+                    format!(
+                        "Could not read page: stored length {} is over the {} bytes a page holds",
+                        len,
+                        data.len()
+                    ),
+                )
+            })
+        }
+    }
+}
+
+fn open_error(kind: ErrorKind, reason: &str) -> BookwormError {
+    BookwormError::new(kind, format!("Could not open data source: {}", reason))
+}
+
+/// Writes dirty pages in ascending order, runs of adjacent pages with a single write, dropping
+/// each run once written. Returns the end of the last page written.
+pub(crate) fn write_dirty<S: Write + Seek>(
+    dirty: &mut BTreeMap<usize, Vec<u8>>,
+    data_source: &mut S,
+    data_offset: u64,
+    page_size: usize,
+) -> BookwormResult<usize> {
+    let mut written = 0;
+    while let Some((&first, _)) = dirty.first_key_value() {
+        let mut end = first;
+        let mut run = Vec::new();
+        while let Some(raw_page) = dirty.get(&end) {
+            run.extend_from_slice(raw_page);
+            end += 1;
+        }
+        data_source
+            .seek(SeekFrom::Start(data_offset + (first * page_size) as u64))
+            .and_then(|_| data_source.write_all(&run))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not write page {}", first))
+                    .with_source(err)
+                    .at_page(first)
+            })?;
+        for page in first..end {
+            dirty.remove(&page);
+        }
+        written = end;
+    }
+    Ok(written)
+}
+
+/// Splits a checksummed page into the digest its header holds and what follows it, checking the
+/// digest length the header records against the algorithm. Zeroed slots hold no digest.
+fn checksum_header(
+    algorithm: ChecksumAlgorithm,
+    raw_page: &[u8],
+) -> BookwormResult<Option<(&[u8], &[u8])>> {
+    let Some((header, rest)) = raw_page.split_first_chunk::<CHECKSUM_HEADER_BYTES>() else {
+        return Err(BookwormError::new(
+            ErrorKind::Corrupted,
+            "Could not read page: it can't hold a checksum header".to_string(),
+        ));
+    };
+    if header == &[0; CHECKSUM_HEADER_BYTES] {
+        return Ok(None);
+    }
+    let digest_len = header[4] as usize;
+    if digest_len != algorithm.digest_len() {
+        return Err(BookwormError::new(
+            ErrorKind::Corrupted,
+            format!(
+                "Could not read page: its header holds a {} byte digest, {:?} digests are {} bytes",
+                digest_len,
+                algorithm,
+                algorithm.digest_len()
+            ),
+        ));
+    }
+    match rest.split_at_checked(digest_len) {
+        Some(split) => Ok(Some(split)),
+        None => Err(BookwormError::new(
+            ErrorKind::Corrupted,
+            "Could not read page: it can't hold a checksum header".to_string(),
+        )),
+    }
+}
+
+/// What the file header at the start of a data source records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub page_size: usize,
+    pub layout: PageLayout,
+    /// Spilling stores flag it next to the chained layout, so readers know records may span
+    /// pages
+    pub oversize: OversizePolicy,
+    /// Pages count of stores keeping it in the header
+    pub pages_count: Option<usize>,
+    /// Whether pages reserved for a bitmap of the live pages follow the header
+    pub live_bitmap: bool,
+}
+
+impl FileHeader {
+    /// Reads and checks the header a data source starts with, leaving the stream past it
+    pub fn read_from<S: Read + Seek>(data_source: &mut S) -> BookwormResult<Self> {
+        let len = data_source.seek(SeekFrom::End(0)).map_err(|err| {
+            BookwormError::new(
+                ErrorKind::Io,
+                "Could not read data source length".to_owned(),
+            )
+            .with_source(err)
+        })?;
+        if len < FILE_HEADER_BYTES {
+            return Err(open_error(
+                ErrorKind::Corrupted,
+                "it is not a bookworm file",
+            ));
+        }
+        let mut header = [0; FILE_HEADER_BYTES as usize];
+        data_source
+            .rewind()
+            .and_then(|_| data_source.read_exact(&mut header))
+            .map_err(|err| {
+                open_error(ErrorKind::Io, "the header could not be read").with_source(err)
+            })?;
+        if &header[..4] != FILE_MAGIC {
+            return Err(open_error(
+                ErrorKind::Corrupted,
+                "it is not a bookworm file",
+            ));
+        }
+        let version = header[4];
+        if !(1..=FORMAT_VERSION).contains(&version) {
+            return Err(open_error(
+                ErrorKind::Corrupted,
+                &format!("format version {} is not supported", version),
+            ));
+        }
+        let algorithm = ChecksumAlgorithm::from_tag(header[10]).ok_or_else(|| {
+            open_error(
+                ErrorKind::Corrupted,
+                &format!("unknown checksum algorithm {}", header[10]),
+            )
+        })?;
+        let pages_count = match header[5] & COUNTED_FLAG {
+            0 => None,
+            _ => {
+                let mut count = [0; 8];
+                count[..FILE_HEADER_BYTES as usize - HEADER_COUNT_AT]
+                    .copy_from_slice(&header[HEADER_COUNT_AT..]);
+                Some(u64::from_le_bytes(count) as usize)
+            }
+        };
+        let live_bitmap = header[5] & LIVE_BITMAP_FLAG != 0;
+        let flags = header[5] & !COUNTED_FLAG & !LIVE_BITMAP_FLAG;
+        let (flags, oversize) = match flags & SPILL_FLAG {
+            0 => (flags, OversizePolicy::Reject),
+            _ => (flags & !SPILL_FLAG, OversizePolicy::Spill),
+        };
+        let layout = layout_of(flags, algorithm)
+            .filter(|layout| oversize == OversizePolicy::Reject || *layout == PageLayout::Chained)
+            .ok_or_else(|| {
+                open_error(
+                    ErrorKind::Corrupted,
+                    &format!("unknown feature flags {:#04x}", header[5]),
+                )
+            })?;
+        if version == 1 && matches!(layout, PageLayout::Checksummed(_)) {
+            return Err(open_error(
+                ErrorKind::Corrupted,
+                "format version 1 checksummed pages are not supported",
+            ));
+        }
+        let page_size = u32::from_le_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        if page_size == 0 {
+            return Err(open_error(
+                ErrorKind::Corrupted,
+                "its header records pages of 0 bytes",
+            ));
+        }
+        Ok(Self {
+            page_size,
+            layout,
+            oversize,
+            pages_count,
+            live_bitmap,
+        })
+    }
+}
+
+/// The pages count as it's laid out in the file header
+fn count_bytes(pages_count: usize) -> [u8; FILE_HEADER_BYTES as usize - HEADER_COUNT_AT] {
+    let mut bytes = [0; FILE_HEADER_BYTES as usize - HEADER_COUNT_AT];
+    let len = bytes.len();
+    bytes.copy_from_slice(&(pages_count as u64).to_le_bytes()[..len]);
+    bytes
+}
+
+/// Feature flags of the file header standing for `layout`
+fn layout_flags(layout: PageLayout) -> u8 {
+    match layout {
+        PageLayout::Padded => 0,
+        PageLayout::LengthPrefixed => LENGTH_PREFIXED_FLAG,
+        PageLayout::Chained => CHAINED_FLAG,
+        PageLayout::Checksummed(_) => CHECKSUMMED_FLAG,
+    }
+}
+
+/// The layout the feature flags of a file header stand for, if they are known, checksummed
+/// with the algorithm the header records
+fn layout_of(flags: u8, algorithm: ChecksumAlgorithm) -> Option<PageLayout> {
+    match flags {
+        0 => Some(PageLayout::Padded),
+        LENGTH_PREFIXED_FLAG => Some(PageLayout::LengthPrefixed),
+        CHAINED_FLAG => Some(PageLayout::Chained),
+        CHECKSUMMED_FLAG => Some(PageLayout::Checksummed(algorithm)),
+        _ => None,
+    }
+}
+
+/// Bytes in front of the data of a single page laid out as `layout`
+fn header_bytes(layout: PageLayout) -> usize {
+    match layout {
+        PageLayout::Padded => 0,
+        PageLayout::LengthPrefixed => LENGTH_PREFIX_BYTES,
+        PageLayout::Chained => CHAIN_HEADER_BYTES,
+        PageLayout::Checksummed(algorithm) => CHECKSUM_HEADER_BYTES + algorithm.digest_len(),
+    }
+}
+
+/// Checks the data of a checksummed page against the digest stored in its header. Zeroed
+/// slots pass, as they hold no data.
+fn verify_checksum(
+    algorithm: ChecksumAlgorithm,
+    page: usize,
+    raw_page: &[u8],
+) -> BookwormResult<()> {
+    let layout = PageLayout::Checksummed(algorithm);
+    let Some((stored, _)) =
+        checksum_header(algorithm, raw_page).map_err(|err| err.at_page(page))?
+    else {
+        return Ok(());
+    };
+    let data = payload_of(layout, raw_page).map_err(|err| err.at_page(page))?;
+    let computed = algorithm.digest(data);
+    if stored != computed.as_bytes() {
+        return Err(BookwormError::checksum_mismatch(
+            page,
+            Digest::from_bytes(stored),
+            computed,
+        ));
+    }
+    Ok(())
+}
+
+/// Header of a page in the chained layout
+struct ChainHeader {
+    len: usize,
+    /// Pages to skip to get to the next page of the record, zero on its last page
+    next: usize,
+    continuation: bool,
+}
+
+impl ChainHeader {
+    fn single(len: usize) -> Self {
+        Self {
+            len,
+            next: 0,
+            continuation: false,
+        }
+    }
+    fn of(raw_page: &[u8]) -> BookwormResult<Self> {
+        let Some((header, data)) = raw_page.split_first_chunk::<CHAIN_HEADER_BYTES>() else {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                "Could not read page: it can't hold a chain header".to_string(),
+            ));
+        };
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if len > data.len() {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Could not read page: stored length {} is over the {} bytes a page holds",
+                    len,
+                    data.len()
+                ),
+            ));
+        }
+        Ok(Self {
+            len,
+            next: u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize,
+            continuation: header[8] & CONTINUATION_FLAG != 0,
+        })
+    }
+    fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.len as u32).to_le_bytes());
+        out.extend_from_slice(&(self.next as u32).to_le_bytes());
+        out.push(if self.continuation {
+            CONTINUATION_FLAG
+        } else {
+            0
+        });
+    }
+}
+
+/// Bytes of data a page holds. Padded pages are measured by trimming trailing zeroes, a
+/// corrupt length prefix counts as a full page.
+pub(crate) fn payload_len(layout: PageLayout, raw_page: &[u8]) -> usize {
+    match layout {
+        PageLayout::Padded => {
+            let trailing_zeroes = raw_page.iter().rev().take_while(|byte| **byte == 0).count();
+            raw_page.len() - trailing_zeroes
+        }
+        PageLayout::LengthPrefixed | PageLayout::Chained | PageLayout::Checksummed(_) => {
+            payload_of(layout, raw_page)
+                .map(<[u8]>::len)
+                .unwrap_or(raw_page.len())
+        }
+    }
+}
+
+/// First page from `start` on that isn't free, or `end` when there is none before it
+pub(crate) fn first_live(free: &BTreeSet<usize>, mut start: usize, end: usize) -> usize {
+    while start < end && free.contains(&start) {
+        start += 1;
+    }
+    start
+}
+
+/// One past the last page before `end` that isn't free, or `start` when there is none
+pub(crate) fn last_live_end(free: &BTreeSet<usize>, start: usize, mut end: usize) -> usize {
+    while end > start && free.contains(&(end - 1)) {
+        end -= 1;
+    }
+    end
+}
+
+/// Pages in `pages` that aren't free
+fn live_count(free: &BTreeSet<usize>, pages: Range<usize>) -> usize {
+    if pages.is_empty() {
+        return 0;
+    }
+    pages.len() - free.range(pages).count()
+}
+
+/// The decode limit for a record that was already read whole, chained records may be longer
+/// than a page
+fn record_limit(layout: PageLayout, limit: u64, record: &[u8]) -> u64 {
+    match layout {
+        PageLayout::Chained => limit.max(record.len() as u64),
+        _ => limit,
+    }
+}
+
+impl<S: Read + Write + Seek + Truncate, C: Codec, H: SharedStorage<S>> Pager<S, C, H> {
+    /// Physically cuts the data source down to `pages` pages
+    pub fn shrink_to(&mut self, pages: usize) -> BookwormResult<()> {
+        self.discard(pages..usize::MAX);
+        self.position = None;
+        self.data_source
+            .access()
+            .truncate(self.offset_of(pages))
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, "Could not truncate data source".to_owned())
+                    .with_source(err)
+            })?;
+        self.pages_count = self.pages_count.min(pages);
+        self.free.split_off(&pages);
+        self.capacity = pages;
+        self.clean_from = self.clean_from.min(pages);
+        Ok(())
     }
 }
 
-pub struct RawPagerIterator<S: Read + Write + Seek> {
-    data_source: Rc<RefCell<S>>,
+pub struct ClearOnDrop<
+    'a,
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    pager: &'a mut Pager<S, C, H>,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Deref for ClearOnDrop<'_, S, C, H> {
+    type Target = Pager<S, C, H>;
+
+    fn deref(&self) -> &Self::Target {
+        self.pager
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> DerefMut for ClearOnDrop<'_, S, C, H> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.pager
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Drop for ClearOnDrop<'_, S, C, H> {
+    fn drop(&mut self) {
+        self.pager.clear();
+    }
+}
+
+pub struct RawPagerIterator<S: Read + Write + Seek, H: SharedStorage<S> = Rc<RefCell<S>>> {
+    data_source: H,
     page_size: usize,
+    data_offset: u64,
+    layout: PageLayout,
+    /// Next page from the front and one past the next page from the back, the zeroed slots
+    /// past the count aren't pages
+    front: usize,
+    back: usize,
+    /// Records left between the two ends
+    records: usize,
+    /// Page the stream is right at, so reading it needs no seek
+    stream_at: Option<usize>,
+    free: BTreeSet<usize>,
+    /// Pages the pager hadn't written back when the iterator was created
+    dirty: BTreeMap<usize, Vec<u8>>,
+    _storage: std::marker::PhantomData<S>,
 }
 
-impl<S: Read + Write + Seek> Iterator for RawPagerIterator<S> {
+impl<S: Read + Write + Seek, H: SharedStorage<S>> RawPagerIterator<S, H> {
+    /// Gives back the handle to the data source the iterator reads
+    pub fn into_data_source(self) -> H {
+        self.data_source
+    }
+    fn read_page(&mut self, page: usize, buf: &mut [u8]) -> Option<()> {
+        if let Some(raw_page) = self.dirty.get(&page) {
+            buf.copy_from_slice(raw_page);
+            return Some(());
+        }
+        let mut data_source = self.data_source.access();
+        if self.stream_at.take() != Some(page) {
+            data_source
+                .seek(SeekFrom::Start(
+                    self.data_offset + (page * self.page_size) as u64,
+                ))
+                .ok()?;
+        }
+        data_source.read_exact(buf).ok()?;
+        self.stream_at = Some(page + 1);
+        if let PageLayout::Checksummed(algorithm) = self.layout {
+            verify_checksum(algorithm, page, buf).ok()?;
+        }
+        Some(())
+    }
+    /// Leaves nothing between the two ends
+    fn finish(&mut self) {
+        self.front = self.back;
+        self.records = 0;
+    }
+    /// Records starting among the live pages between the two ends, chained pages are read to
+    /// tell the ones starting a record apart
+    fn count_records(&mut self) -> usize {
+        if self.layout != PageLayout::Chained {
+            return live_count(&self.free, self.front..self.back);
+        }
+        let mut buf = vec![0; self.page_size];
+        let mut records = 0;
+        for page in self.front..self.back {
+            if self.free.contains(&page) {
+                continue;
+            }
+            records += self
+                .read_page(page, &mut buf)
+                .is_none_or(|_| buf[8] & CONTINUATION_FLAG == 0) as usize;
+        }
+        records
+    }
+    /// Reads the record starting at `page` along with how many pages it spans, which must all
+    /// come before `end`
+    fn read(&mut self, page: usize, end: usize) -> Option<(Vec<u8>, usize)> {
+        let mut buf = vec![0; self.page_size];
+        self.read_page(page, &mut buf)?;
+        if self.layout != PageLayout::Chained {
+            let record = match self.layout {
+                PageLayout::Padded => buf,
+                _ => payload_of(self.layout, &buf).ok()?.to_vec(),
+            };
+            return Some((record, 1));
+        }
+        let mut header = ChainHeader::of(&buf).ok()?;
+        if header.continuation {
+            return None;
+        }
+        let mut record = Vec::new();
+        let mut last = page;
+        loop {
+            record.extend_from_slice(&buf[CHAIN_HEADER_BYTES..CHAIN_HEADER_BYTES + header.len]);
+            if header.next == 0 {
+                return Some((record, last + 1 - page));
+            }
+            last += header.next;
+            if last >= end {
+                return None;
+            }
+            self.read_page(last, &mut buf)?;
+            header = ChainHeader::of(&buf).ok()?;
+            if !header.continuation {
+                return None;
+            }
+        }
+    }
+    /// First page of the record that ends right before `end`, without going under the front
+    fn record_start(&mut self, end: usize) -> Option<usize> {
+        if self.layout != PageLayout::Chained {
+            return Some(end - 1);
+        }
+        let mut buf = vec![0; self.page_size];
+        for page in (self.front..end).rev() {
+            self.read_page(page, &mut buf)?;
+            if !ChainHeader::of(&buf).ok()?.continuation {
+                return Some(page);
+            }
+        }
+        None
+    }
+}
+
+impl<S: Read + Write + Seek, H: SharedStorage<S>> Iterator for RawPagerIterator<S, H> {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut buf = vec![0; self.page_size];
-        let mut data_source = self.data_source.borrow_mut();
-        match data_source.read_exact(&mut buf) {
-            Ok(_) => Some(buf),
-            Err(_) => None,
+        self.front = first_live(&self.free, self.front, self.back);
+        if self.front >= self.back {
+            return None;
+        }
+        self.records = self.records.saturating_sub(1);
+        let Some((record, span)) = self.read(self.front, self.back) else {
+            self.front += 1;
+            self.stream_at = None;
+            return None;
+        };
+        self.front += span;
+        Some(record)
+    }
+    /// Counts records, chained ones were counted when the iterator was created
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.records, Some(self.records))
+    }
+    /// Jumps over the skipped pages with a single seek instead of reading them, chained pages
+    /// and pages among free ones are walked record by record
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.layout == PageLayout::Chained || !self.free.is_empty() {
+            for _ in 0..n {
+                self.next()?;
+            }
+            return self.next();
+        }
+        if n >= self.back - self.front.min(self.back) {
+            self.front = self.back;
+            self.records = 0;
+            return None;
         }
+        self.front += n;
+        self.records -= n;
+        self.next()
     }
 }
 
-pub struct PagerIterator<S: Read + Write + Seek, T: DeserializeOwned> {
-    data_source: Rc<RefCell<S>>,
-    page_size: usize,
+impl<S: Read + Write + Seek, H: SharedStorage<S>> ExactSizeIterator for RawPagerIterator<S, H> {}
+
+impl<S: Read + Write + Seek, H: SharedStorage<S>> DoubleEndedIterator for RawPagerIterator<S, H> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back = last_live_end(&self.free, self.front, self.back);
+        if self.front >= self.back {
+            return None;
+        }
+        self.records = self.records.saturating_sub(1);
+        let Some(start) = self.record_start(self.back) else {
+            self.back -= 1;
+            return None;
+        };
+        let end = std::mem::replace(&mut self.back, start);
+        self.read(start, end).map(|(record, _)| record)
+    }
+}
+
+/// Decodes the records a `RawPagerIterator` reads. It's fused: the first record that can't
+/// be read or decoded ends it from both ends, rather than skipping to the next one.
+pub struct PagerIterator<
+    S: Read + Write + Seek,
+    T: DeserializeOwned,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    raw: RawPagerIterator<S, H>,
+    decode_limit: u64,
+    codec: C,
     _marker: std::marker::PhantomData<T>,
 }
 
-impl<S, T> Iterator for PagerIterator<S, T>
+impl<S: Read + Write + Seek, T: DeserializeOwned, C: Codec, H: SharedStorage<S>>
+    PagerIterator<S, T, C, H>
+{
+    pub fn into_data_source(self) -> H {
+        self.raw.into_data_source()
+    }
+    /// Decodes a record the raw iterator yielded, finishing it when there's none or it can't
+    /// be decoded
+    fn decode(&mut self, record: Option<Vec<u8>>) -> Option<T> {
+        let decoded = record.and_then(|record| {
+            let limit = record_limit(self.raw.layout, self.decode_limit, &record);
+            self.codec.deserialize(&record, limit).ok()
+        });
+        if decoded.is_none() {
+            self.raw.finish();
+        }
+        decoded
+    }
+}
+
+impl<S, T, C, H> Iterator for PagerIterator<S, T, C, H>
 where
     S: Read + Write + Seek,
+    H: SharedStorage<S>,
     T: DeserializeOwned,
+    C: Codec,
 {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut buf = vec![0; self.page_size];
-        let mut data_source = self.data_source.borrow_mut();
-        if let Ok(_) = data_source.read_exact(&mut buf) {
-            if let Ok(parsed) = bincode::deserialize(&buf) {
-                return Some(parsed);
-            }
-        }
-        None
+        let record = self.raw.next();
+        self.decode(record)
     }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let record = self.raw.nth(n);
+        self.decode(record)
+    }
+}
+
+impl<S, T, C, H> ExactSizeIterator for PagerIterator<S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    T: DeserializeOwned,
+    C: Codec,
+{
 }
 
-pub struct PagerIter<'a, S: Read + Write + Seek, T: DeserializeOwned + Debug> {
+impl<S, T, C, H> DoubleEndedIterator for PagerIterator<S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    T: DeserializeOwned,
+    C: Codec,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let record = self.raw.next_back();
+        self.decode(record)
+    }
+}
+
+pub struct PagerIter<
+    'a,
+    S: Read + Write + Seek,
+    T: DeserializeOwned + Debug,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
     curr_pos: usize,
-    pager: &'a mut Pager<S>,
+    /// One past the next page from the back
+    back: usize,
+    /// Records left between the two ends
+    records: usize,
+    pager: &'a mut Pager<S, C, H>,
     _marker: std::marker::PhantomData<T>,
 }
-impl<'a, S, T: DeserializeOwned + Debug> Iterator for PagerIter<'a, S, T>
+impl<S: Read + Write + Seek, T: DeserializeOwned + Debug, C: Codec, H: SharedStorage<S>>
+    PagerIter<'_, S, T, C, H>
+{
+    /// Leaves nothing between the two ends, once a record can't be read
+    fn finish(&mut self) {
+        self.curr_pos = self.back;
+        self.records = 0;
+    }
+}
+impl<'a, S, T: DeserializeOwned + Debug, C: Codec, H> Iterator for PagerIter<'a, S, T, C, H>
 where
     S: Read + Write + Seek,
+    H: SharedStorage<S>,
 {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Ok(page) = self.pager.get_page(self.curr_pos) {
-            self.curr_pos += 1;
-            Some(page)
-        } else {
-            None
+        self.curr_pos = first_live(&self.pager.free, self.curr_pos, self.back);
+        if self.curr_pos >= self.back {
+            return None;
+        }
+        let decoded = self
+            .pager
+            .record_at(self.curr_pos)
+            .and_then(|(record, span)| Ok((self.pager.decode_record(&record)?, span)));
+        let Ok((record, span)) = decoded else {
+            self.finish();
+            return None;
+        };
+        self.curr_pos += span;
+        self.records -= 1;
+        Some(record)
+    }
+    /// Counts records, chained ones were counted when the iterator was created
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.records, Some(self.records))
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.pager.layout == PageLayout::Chained || !self.pager.free.is_empty() {
+            for _ in 0..n {
+                self.next()?;
+            }
+            return self.next();
         }
+        self.curr_pos = self.curr_pos.saturating_add(n).min(self.back);
+        self.records = self.back - self.curr_pos;
+        self.next()
     }
 }
-pub struct RawPagerIter<'a, S: Read + Write + Seek> {
+impl<S, T: DeserializeOwned + Debug, C: Codec, H> ExactSizeIterator for PagerIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+{
+}
+impl<'a, S, T: DeserializeOwned + Debug, C: Codec, H> DoubleEndedIterator
+    for PagerIter<'a, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back = last_live_end(&self.pager.free, self.curr_pos, self.back);
+        if self.curr_pos >= self.back {
+            return None;
+        }
+        let decoded = self.pager.record_start(self.back).and_then(|start| {
+            let start = start.max(self.curr_pos);
+            let (record, _) = self.pager.record_at(start)?;
+            Ok((self.pager.decode_record(&record)?, start))
+        });
+        let Ok((record, start)) = decoded else {
+            self.finish();
+            return None;
+        };
+        self.back = start;
+        self.records -= 1;
+        Some(record)
+    }
+}
+pub struct RawPagerIter<
+    'a,
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
     curr_pos: usize,
-    pager: &'a mut Pager<S>,
+    /// One past the next page from the back
+    back: usize,
+    /// Records left between the two ends
+    records: usize,
+    pager: &'a mut Pager<S, C, H>,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> RawPagerIter<'_, S, C, H> {
+    /// Leaves nothing between the two ends, once a record can't be read
+    fn finish(&mut self) {
+        self.curr_pos = self.back;
+        self.records = 0;
+    }
 }
 
-impl<'a, S> Iterator for RawPagerIter<'a, S>
+impl<'a, S, C, H> Iterator for RawPagerIter<'a, S, C, H>
 where
     S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
 {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Ok(page) = self.pager.get_raw_page(self.curr_pos) {
-            self.curr_pos += 1;
-            Some(page)
-        } else {
-            None
+        self.curr_pos = first_live(&self.pager.free, self.curr_pos, self.back);
+        if self.curr_pos >= self.back {
+            return None;
+        }
+        let Ok((record, span)) = self.pager.record_at(self.curr_pos) else {
+            self.finish();
+            return None;
+        };
+        self.curr_pos += span;
+        self.records -= 1;
+        Some(record)
+    }
+    /// Counts records, chained ones were counted when the iterator was created
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.records, Some(self.records))
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.pager.layout == PageLayout::Chained || !self.pager.free.is_empty() {
+            for _ in 0..n {
+                self.next()?;
+            }
+            return self.next();
+        }
+        self.curr_pos = self.curr_pos.saturating_add(n).min(self.back);
+        self.records = self.back - self.curr_pos;
+        self.next()
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> ExactSizeIterator
+    for RawPagerIter<'_, S, C, H>
+{
+}
+
+impl<'a, S, C, H> DoubleEndedIterator for RawPagerIter<'a, S, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back = last_live_end(&self.pager.free, self.curr_pos, self.back);
+        if self.curr_pos >= self.back {
+            return None;
+        }
+        let read = self.pager.record_start(self.back).and_then(|start| {
+            let start = start.max(self.curr_pos);
+            Ok((self.pager.record_at(start)?.0, start))
+        });
+        let Ok((record, start)) = read else {
+            self.finish();
+            return None;
+        };
+        self.back = start;
+        self.records -= 1;
+        Some(record)
+    }
+}
+
+pub struct FallibleRawPagerIter<
+    'a,
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    curr_pos: usize,
+    back: usize,
+    pager: &'a mut Pager<S, C, H>,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> FallibleRawPagerIter<'_, S, C, H> {
+    /// Reads the next record along with the page it starts at, moving a single page past a
+    /// record that can't be read
+    fn next_record(&mut self) -> Option<(usize, BookwormResult<Vec<u8>>)> {
+        self.curr_pos = first_live(&self.pager.free, self.curr_pos, self.back);
+        if self.curr_pos >= self.back {
+            return None;
+        }
+        let page = self.curr_pos;
+        match self.pager.record_at(page) {
+            Ok((record, span)) => {
+                self.curr_pos += span;
+                Some((page, Ok(record)))
+            }
+            Err(err) => {
+                self.curr_pos += 1;
+                Some((page, Err(err.on_read(page))))
+            }
         }
     }
 }
 
+impl<S, C, H> Iterator for FallibleRawPagerIter<'_, S, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+{
+    type Item = BookwormResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().map(|(_, record)| record)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = live_count(&self.pager.free, self.curr_pos..self.back);
+        (0, Some(remaining))
+    }
+}
+
+pub struct FalliblePagerIter<
+    'a,
+    S: Read + Write + Seek,
+    T: DeserializeOwned,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    raw: FallibleRawPagerIter<'a, S, C, H>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<S, T, C, H> Iterator for FalliblePagerIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    T: DeserializeOwned,
+    C: Codec,
+{
+    type Item = BookwormResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (page, record) = self.raw.next_record()?;
+        Some(record.and_then(|record| {
+            self.raw
+                .pager
+                .decode_record(&record)
+                .map_err(|err| err.on_parse(page))
+        }))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -251,7 +2510,7 @@ mod tests {
     #[test]
     fn test_iter() {
         let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-        let mut pager = Pager::new(128, data_source);
+        let mut pager = Pager::with_codec(128, data_source, BincodeCodec);
         let test_data_1 = TestData::new(10, true);
         let test_data_2 = TestData::new(12, false);
         pager.push(&test_data_1).unwrap();
@@ -263,7 +2522,7 @@ mod tests {
     #[test]
     fn test_raw_iter() {
         let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-        let mut pager = Pager::new(128, data_source);
+        let mut pager = Pager::with_codec(128, data_source, BincodeCodec);
         pager.push_raw(b"apple").unwrap();
         pager.push_raw(b"grape").unwrap();
         let mut iter = pager.raw_iter(0);
@@ -274,4 +2533,44 @@ mod tests {
         assert!(parsed_first.contains("apple"));
         assert!(parsed_second.contains("grape"));
     }
+    #[test]
+    fn test_length_prefixed_raw_iter() {
+        let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+        let mut pager = Pager::with_codec(16, data_source, BincodeCodec);
+        pager.layout = PageLayout::LengthPrefixed;
+        pager.push_raw(b"apple").unwrap();
+        pager.push_raw(b"grape\0").unwrap();
+        pager.push_raw(&[7; 12]).unwrap();
+        pager.push_raw(&[7; 13]).unwrap_err();
+        let pages: Vec<Vec<u8>> = pager.raw_iter(0).collect();
+        assert_eq!(
+            pages,
+            vec![b"apple".to_vec(), b"grape\0".to_vec(), vec![7; 12]]
+        );
+        let pages: Vec<Vec<u8>> = pager.raw_iterator(0).collect();
+        assert_eq!(
+            pages,
+            vec![b"apple".to_vec(), b"grape\0".to_vec(), vec![7; 12]]
+        );
+        assert_eq!(pager.page_fill(1).unwrap().payload_bytes, 6);
+    }
+    #[test]
+    fn test_error_kinds() {
+        let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+        let mut pager = Pager::with_codec(16, data_source.clone(), BincodeCodec);
+        pager.push(&TestData::new(1, true)).unwrap();
+        pager.push(&TestData::new(2, false)).unwrap();
+        let err = pager.get_page::<TestData>(2).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PageOutOfRange);
+        assert_eq!(err.to_string(), "Page doesn't exist");
+        let err = pager.write_page(0, &[7u8; 17]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DataTooLarge);
+        data_source.borrow_mut().get_mut().truncate(24);
+        let err = pager.get_page::<TestData>(1).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert_eq!(
+            pager.get_page::<TestData>(0).unwrap(),
+            TestData::new(1, true)
+        );
+    }
 }
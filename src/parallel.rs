@@ -0,0 +1,153 @@
+use std::{
+    cell::RefCell,
+    fs::{File, OpenOptions},
+    os::unix::fs::FileExt,
+    path::Path,
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::{BookwormError, BookwormResult, ErrorKind},
+    file::swap_path_for,
+    Bookworm,
+};
+
+/// Writes `records` into a new file at `path` from `threads` workers, each one serializing a
+/// contiguous run of pages and writing it through its own handle at fixed offsets. Records
+/// are serialized with the default codec into padded pages, which is what the returned
+/// bookworm uses, so the file is byte-identical to pushing them one by one with that codec and
+/// layout only. The swap lives next to it, at `path` with a `.swap` extension appended.
+pub fn parallel_load<T: Serialize + Send>(
+    path: impl AsRef<Path>,
+    page_size: usize,
+    records: Vec<T>,
+    threads: usize,
+) -> BookwormResult<Bookworm<File>> {
+    let path = path.as_ref();
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|err| open_error(path, err))?;
+    file.set_len((records.len() * page_size) as u64)
+        .map_err(|err| {
+            BookwormError::new(ErrorKind::Io, "Could not preallocate pages".to_owned())
+                .with_source(err)
+        })?;
+
+    let per_thread = records.len().div_ceil(threads.max(1)).max(1);
+    let mut runs = Vec::new();
+    let mut records = records.into_iter();
+    let mut first_page = 0;
+    loop {
+        let run: Vec<T> = records.by_ref().take(per_thread).collect();
+        if run.is_empty() {
+            break;
+        }
+        let len = run.len();
+        runs.push((first_page, run));
+        first_page += len;
+    }
+    let failed = AtomicBool::new(false);
+    let results: Vec<BookwormResult<()>> = thread::scope(|scope| {
+        let workers: Vec<_> = runs
+            .into_iter()
+            .map(|(first_page, run)| {
+                let failed = &failed;
+                scope.spawn(move || {
+                    let result = write_run(path, page_size, first_page, &run, failed);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    result
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker.join().unwrap_or_else(|_| {
+                    failed.store(true, Ordering::Relaxed);
+                    Err(BookwormError::new(
+                        ErrorKind::Io,
+                        "A load worker panicked".to_owned(),
+                    ))
+                })
+            })
+            .collect()
+    });
+    if let Some(err) = results.into_iter().find_map(Result::err) {
+        return Err(BookwormError::new(
+            err.kind(),
+            format!("Parallel load left {} partially written", path.display()),
+        )
+        .with_source(err));
+    }
+
+    let swap_path = swap_path_for(path);
+    let swap = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&swap_path)
+        .map_err(|err| open_error(Path::new(&swap_path), err))?;
+    Ok(Bookworm::new(
+        page_size,
+        Rc::new(RefCell::new(file)),
+        Rc::new(RefCell::new(swap)),
+    ))
+}
+
+/// Serializes a run of records and writes them at their pages, stopping early once another
+/// worker failed
+fn write_run<T: Serialize>(
+    path: &Path,
+    page_size: usize,
+    first_page: usize,
+    run: &[T],
+    failed: &AtomicBool,
+) -> BookwormResult<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|err| open_error(path, err))?;
+    let mut buf = vec![0; page_size];
+    for (page, record) in (first_page..).zip(run) {
+        if failed.load(Ordering::Relaxed) {
+            // the failing worker reports the error
+            return Ok(());
+        }
+        let serialized = bincode::serialize(record).map_err(|err| {
+            BookwormError::new(
+                ErrorKind::Serialization,
+                format!("Could not serialize page {}", page),
+            )
+            .with_source(err)
+        })?;
+        if serialized.len() > page_size {
+            return Err(BookwormError::new(
+                ErrorKind::DataTooLarge,
+                format!("Could not write page {}: data is bigger than page", page),
+            ));
+        }
+        buf[..serialized.len()].copy_from_slice(&serialized);
+        buf[serialized.len()..].fill(0);
+        file.write_all_at(&buf, (page * page_size) as u64)
+            .map_err(|err| {
+                BookwormError::new(ErrorKind::Io, format!("Could not write page {}", page))
+                    .with_source(err)
+            })?;
+    }
+    Ok(())
+}
+
+fn open_error(path: &Path, err: std::io::Error) -> BookwormError {
+    BookwormError::new(ErrorKind::Io, format!("Could not open {}", path.display())).with_source(err)
+}
@@ -0,0 +1,142 @@
+use std::{
+    io::{Read, Seek, Write},
+    mem,
+};
+
+use crate::{
+    checksum::crc32,
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm, COPY_BATCH_PAGES,
+};
+
+const DELETE_MAGIC: &[u8; 4] = b"BKWD";
+/// Bytes kept in front of the swap pages for the marker of a delete in progress
+pub(crate) const DELETE_MARKER_BYTES: u64 = 40;
+
+/// What an operation does with a page it can't read while moving pages around
+pub enum RecoveryAction {
+    /// Leaves the page out, it is removed along with the operation's own target
+    SkipPage,
+    /// Fails the operation with the read error, before anything was rewritten
+    AbortOperation,
+    /// Uses these bytes as the page's content, they must fit in a page
+    ReplaceWith(Vec<u8>),
+}
+
+/// Pages an operation recovered from
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// Pages left out, along with the error they failed with
+    pub skipped: Vec<(usize, BookwormError)>,
+    /// Pages whose content was replaced
+    pub replaced: Vec<usize>,
+}
+
+/// Written in front of the swap pages while a delete copies them back: the deleted page, how
+/// many pages were staged and the pages count before the delete
+pub(crate) struct DeleteMarker {
+    page: usize,
+    staged: usize,
+    pages_count: usize,
+}
+
+impl DeleteMarker {
+    pub fn new(page: usize, staged: usize, pages_count: usize) -> Self {
+        Self {
+            page,
+            staged,
+            pages_count,
+        }
+    }
+    pub fn to_bytes(&self) -> [u8; DELETE_MARKER_BYTES as usize] {
+        let mut bytes = [0; DELETE_MARKER_BYTES as usize];
+        bytes[..4].copy_from_slice(DELETE_MAGIC);
+        bytes[8..16].copy_from_slice(&(self.page as u64).to_le_bytes());
+        bytes[16..24].copy_from_slice(&(self.staged as u64).to_le_bytes());
+        bytes[24..32].copy_from_slice(&(self.pages_count as u64).to_le_bytes());
+        let checksum = crc32(&bytes[..32]);
+        bytes[32..36].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+    /// Reads a marker back, `None` when there's none or it was only partly written
+    fn from_bytes(bytes: &[u8; DELETE_MARKER_BYTES as usize]) -> Option<Self> {
+        let checksum = u32::from_le_bytes(bytes[32..36].try_into().unwrap());
+        if &bytes[..4] != DELETE_MAGIC || crc32(&bytes[..32]) != checksum {
+            return None;
+        }
+        let field = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize;
+        Some(Self::new(field(8), field(16), field(24)))
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Finishes whatever an interrupted delete, transaction commit or checkpoint left in the
+    /// swap, the way creating a bookworm over the storages does. Returns whether there was
+    /// anything to finish, a bookworm poisoned by the interruption is usable again then.
+    pub fn recover(&mut self) -> BookwormResult<bool> {
+        let poisoned = mem::take(&mut self.poisoned);
+        let recovered = self.in_logged_context(OpKind::Write, Self::recover_swap);
+        if !matches!(recovered, Ok(true)) {
+            self.poisoned |= poisoned;
+        }
+        recovered
+    }
+    /// Copies the pages a delete staged in the swap back over the deleted one, when a marker
+    /// says it was interrupted doing so
+    pub(crate) fn finish_interrupted_delete(&mut self) -> BookwormResult<bool> {
+        if self.swap.stored_bytes()? < DELETE_MARKER_BYTES {
+            return Ok(false);
+        }
+        let mut bytes = [0; DELETE_MARKER_BYTES as usize];
+        self.swap.read_front(&mut bytes)?;
+        let Some(marker) = DeleteMarker::from_bytes(&bytes) else {
+            return Ok(false);
+        };
+        let new_count = marker.page + marker.staged;
+        if new_count >= marker.pages_count || self.pager.pages_count < marker.pages_count {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Could not finish deleting page {}: the data source doesn't match the swap",
+                    marker.page
+                ),
+            ));
+        }
+        self.poisoned = true;
+        let page_size = self.pager.page_size;
+        let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(marker.staged)];
+        for start in (0..marker.staged).step_by(COPY_BATCH_PAGES) {
+            let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(marker.staged - start)];
+            self.swap.read_unframed((start * page_size) as u64, run)?;
+            self.pager.write_raw_pages(marker.page + start, run)?;
+        }
+        let pages_count = self.pager.pages_count;
+        self.pager.pages_count = new_count;
+        self.pager.zero_pages(new_count..pages_count)?;
+        self.invalidate_decoded(marker.page..);
+        self.pager.persist(self.sync)?;
+        self.swap.write_front(&[0; DELETE_MARKER_BYTES as usize])?;
+        self.swap.persist(self.sync)?;
+        self.poisoned = false;
+        Ok(true)
+    }
+
+    /// Deletes a page like `delete`, asking `recover` what to do with each page that can't be
+    /// read while shifting the following ones
+    pub fn delete_with_recovery<F>(
+        &mut self,
+        page: usize,
+        mut recover: F,
+    ) -> BookwormResult<RecoveryReport>
+    where
+        F: FnMut(usize, &BookwormError) -> RecoveryAction,
+    {
+        self.in_context(OpKind::Delete, |bookworm| {
+            let mut report = RecoveryReport::default();
+            bookworm.shift_out(page, &mut recover, &mut report)?;
+            Ok(report)
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    codec::Codec,
+    count::CountSource,
+    error::{BookwormResult, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// How the pages count changed when it was derived again from the data source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshOutcome {
+    Grew { by: usize },
+    Shrank { by: usize },
+    Unchanged,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Derives the pages count again from the data source length, or from the file header when
+    /// it's kept there, picking up pages another handle appended or dropping the ones it
+    /// truncated away
+    pub fn refresh(&mut self) -> BookwormResult<RefreshOutcome> {
+        self.in_context(OpKind::Read, |bookworm| {
+            let stored_pages = bookworm.pager.stored_pages()?;
+            let current = bookworm.current_count(stored_pages)?;
+            let outcome = bookworm.outcome_for(current);
+            let previous = bookworm.pager.pages_count;
+            bookworm.pager.resync(stored_pages, current);
+            match outcome {
+                RefreshOutcome::Grew { .. } => bookworm.invalidate_decoded(previous..),
+                RefreshOutcome::Shrank { .. } => {
+                    bookworm.invalidate_decoded(bookworm.pager.pages_count..)
+                }
+                RefreshOutcome::Unchanged => {}
+            }
+            Ok(outcome)
+        })
+    }
+    /// Tells what `refresh` would do without changing anything
+    pub fn check_stale(&mut self) -> BookwormResult<RefreshOutcome> {
+        self.in_context(OpKind::Read, |bookworm| {
+            let stored_pages = bookworm.pager.stored_pages()?;
+            let current = bookworm.current_count(stored_pages)?;
+            Ok(bookworm.outcome_for(current))
+        })
+    }
+    fn current_count(&mut self, stored_pages: usize) -> BookwormResult<usize> {
+        if self.pager.count_source == CountSource::Header {
+            if let Some(count) = self.pager.read_recorded_count()? {
+                return Ok(count);
+            }
+        }
+        Ok(self.pager.count_for(stored_pages))
+    }
+    fn outcome_for(&self, current: usize) -> RefreshOutcome {
+        let previous = self.pager.pages_count;
+        if current > previous {
+            RefreshOutcome::Grew {
+                by: current - previous,
+            }
+        } else if current < previous {
+            RefreshOutcome::Shrank {
+                by: previous - current,
+            }
+        } else {
+            RefreshOutcome::Unchanged
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    rc::Rc,
+};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::{first_live, PageLayout},
+    storage::SharedStorage,
+    Bookworm, COPY_BATCH_PAGES,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Copies every record into a new bookworm over an empty data source whose pages are
+    /// `new_page_size` bytes long, leaving this one as it is. Padded pages are trimmed of
+    /// their trailing zeroes, which the new pages pad back. A record that doesn't fit in the
+    /// new pages fails the copy naming the page it starts at.
+    pub fn repage<S2: Read + Write + Seek>(
+        &mut self,
+        new_page_size: usize,
+        new_source: Rc<RefCell<S2>>,
+        new_swap: Rc<RefCell<S2>>,
+    ) -> BookwormResult<Bookworm<S2, C>> {
+        self.in_context(OpKind::Scan, |bookworm| {
+            let codec = bookworm.pager.codec.clone();
+            let mut repaged = Bookworm::with_codec(new_page_size, new_source, new_swap, codec);
+            if repaged.pager.stored_bytes()? != 0 {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not repage: new data source is not empty".to_owned(),
+                ));
+            }
+            let layout = bookworm.pager.layout;
+            repaged.set_layout(layout);
+            if bookworm.pager.has_header() {
+                repaged.pager.count_source = bookworm.pager.count_source;
+                repaged.pager.open_header()?;
+            }
+            let pages_count = bookworm.pager.pages_count;
+            let mut staged = Vec::with_capacity(new_page_size * COPY_BATCH_PAGES);
+            let mut page = first_live(&bookworm.pager.free, 0, pages_count);
+            while page < pages_count {
+                let (mut record, span) = bookworm.pager.record_at(page)?;
+                if layout == PageLayout::Padded {
+                    let len = record
+                        .iter()
+                        .rposition(|byte| *byte != 0)
+                        .map_or(0, |at| at + 1);
+                    record.truncate(len);
+                }
+                repaged
+                    .pager
+                    .stage_page(&record, &mut staged)
+                    .map_err(|err| {
+                        BookwormError::new(err.kind(), format!("Could not repage page {}", page))
+                            .with_source(err)
+                            .at_page(page)
+                    })?;
+                if staged.len() >= new_page_size * COPY_BATCH_PAGES {
+                    repaged.pager.append_pages(&staged)?;
+                    staged.clear();
+                }
+                page = first_live(&bookworm.pager.free, page + span, pages_count);
+            }
+            repaged.pager.append_pages(&staged)?;
+            repaged.pager.record_count()?;
+            Ok(repaged)
+        })
+    }
+}
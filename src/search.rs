@@ -0,0 +1,86 @@
+use std::{
+    cmp::Ordering,
+    fmt::Debug,
+    io::{Read, Seek, Write},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    codec::Codec,
+    error::{BookwormResult, OpKind},
+    pager::first_live,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Finds the first page whose record matches `predicate`, along with its index
+    pub fn find_page<T, F>(&mut self, predicate: F) -> BookwormResult<Option<(usize, T)>>
+    where
+        T: DeserializeOwned + Debug,
+        F: FnMut(&T) -> bool,
+    {
+        let pages = 0..self.pager.pages_count;
+        self.in_context(OpKind::Scan, |bookworm| bookworm.find_in(pages, predicate))
+    }
+    /// Same as `find_page`, scanning from the last page backwards
+    pub fn rfind_page<T, F>(&mut self, predicate: F) -> BookwormResult<Option<(usize, T)>>
+    where
+        T: DeserializeOwned + Debug,
+        F: FnMut(&T) -> bool,
+    {
+        let pages = (0..self.pager.pages_count).rev();
+        self.in_context(OpKind::Scan, |bookworm| bookworm.find_in(pages, predicate))
+    }
+    /// Binary searches pages kept sorted according to `compare`, like
+    /// `slice::binary_search_by`: `Ok` holds the index of a matching page, `Err` the index a
+    /// matching record could be inserted at while keeping the order. Free pages are stepped
+    /// over.
+    pub fn binary_search_by<T, F>(&mut self, mut compare: F) -> BookwormResult<Result<usize, usize>>
+    where
+        T: DeserializeOwned + Debug,
+        F: FnMut(&T) -> Ordering,
+    {
+        self.in_context(OpKind::Scan, |bookworm| {
+            bookworm.check_unchained("search page by page")?;
+            let mut buf = vec![0; bookworm.pager.page_size];
+            let (mut low, mut high) = (0, bookworm.pager.pages_count);
+            while low < high {
+                let mid = first_live(&bookworm.pager.free, low + (high - low) / 2, high);
+                if mid == high {
+                    high = low + (high - low) / 2;
+                    continue;
+                }
+                match compare(&bookworm.decode_page::<T>(mid, &mut buf)?) {
+                    Ordering::Less => low = mid + 1,
+                    Ordering::Greater => high = mid,
+                    Ordering::Equal => return Ok(Ok(mid)),
+                }
+            }
+            Ok(Err(low))
+        })
+    }
+    fn find_in<T, F>(
+        &mut self,
+        pages: impl Iterator<Item = usize>,
+        mut predicate: F,
+    ) -> BookwormResult<Option<(usize, T)>>
+    where
+        T: DeserializeOwned,
+        F: FnMut(&T) -> bool,
+    {
+        self.check_unchained("search page by page")?;
+        let mut buf = vec![0; self.pager.page_size];
+        for page in pages {
+            if self.pager.free.contains(&page) {
+                continue;
+            }
+            let record = self.decode_page(page, &mut buf)?;
+            if predicate(&record) {
+                return Ok(Some((page, record)));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    codec::{BincodeCodec, Codec},
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Log mode: every record is stored as `(sequence, data)`, so the sequence number is the
+/// leading field of the record and is always derived from the storage itself
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Pushes a record stamped with the next sequence number and returns that number
+    pub fn push_sequenced<T: Serialize>(&mut self, data: &T) -> BookwormResult<u64> {
+        self.in_context(OpKind::Push, |bookworm| {
+            let sequence = match bookworm.read_last_sequence()? {
+                Some(last) => last + 1,
+                None => 0,
+            };
+            bookworm.push(&(sequence, data))?;
+            Ok(sequence)
+        })
+    }
+    /// Sequence number of the last page, read back from the storage
+    pub fn last_sequence(&mut self) -> BookwormResult<Option<u64>> {
+        self.in_context(OpKind::Read, Self::read_last_sequence)
+    }
+    /// Iterates over the records starting at the first one whose sequence is at least
+    /// `sequence`, relying on sequences being monotone to binary search for it
+    pub fn iter_from_sequence<T: DeserializeOwned>(
+        &mut self,
+        sequence: u64,
+    ) -> BookwormResult<SequenceIter<'_, S, T, C, H>> {
+        let start = self.in_context(OpKind::Scan, |bookworm| {
+            let (mut low, mut high) = (0, bookworm.pager.pages_count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if bookworm.sequence_at(mid)? < sequence {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            Ok(low)
+        })?;
+        Ok(SequenceIter {
+            bookworm: self,
+            curr_pos: start,
+            previous: None,
+            finished: false,
+            _marker: PhantomData,
+        })
+    }
+    fn read_last_sequence(&mut self) -> BookwormResult<Option<u64>> {
+        match self.pager.pages_count {
+            0 => Ok(None),
+            pages_count => self.sequence_at(pages_count - 1).map(Some),
+        }
+    }
+    /// Decodes only the leading field of the record at `page`
+    fn sequence_at(&mut self, page: usize) -> BookwormResult<u64> {
+        let (record, _) = self
+            .pager
+            .record_at(page)
+            .map_err(|err| err.on_read(page))?;
+        self.pager
+            .decode_record(&record)
+            .map_err(|err| err.on_parse(page))
+    }
+}
+
+/// Yields `(sequence, record)` pairs, ending with an error when the sequence isn't contiguous
+pub struct SequenceIter<
+    'a,
+    S: Read + Write + Seek,
+    T: DeserializeOwned,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    curr_pos: usize,
+    previous: Option<u64>,
+    finished: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<S, T, C, H> Iterator for SequenceIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned,
+{
+    type Item = BookwormResult<(u64, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.curr_pos >= self.bookworm.pager.pages_count {
+            return None;
+        }
+        let (sequence, data) = match self.bookworm.pager.get_page::<(u64, T)>(self.curr_pos) {
+            Ok(record) => record,
+            Err(err) => {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        };
+        match self.previous {
+            Some(previous) if sequence != previous + 1 => {
+                self.finished = true;
+                return Some(Err(BookwormError::new(
+                    ErrorKind::Corrupted,
+                    format!(
+                        "Sequence corrupted at page {}: expected {} but found {}",
+                        self.curr_pos,
+                        previous + 1,
+                        sequence
+                    ),
+                )));
+            }
+            _ => {}
+        }
+        self.previous = Some(sequence);
+        self.curr_pos += 1;
+        Some(Ok((sequence, data)))
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{Cursor, Read, Seek, Write};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::FileHeader,
+    storage::SharedStorage,
+    Bookworm, COPY_BATCH_PAGES,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Writes the file header, if any, and every page to `dest` a batch of pages per write,
+    /// returning how many bytes were written. Dirty pages are written as they are in memory,
+    /// zeroed slots past the pages are left out.
+    pub fn snapshot_to<W: Write>(&mut self, dest: &mut W) -> BookwormResult<u64> {
+        self.in_context(OpKind::Scan, |bookworm| {
+            let write_error = |err| {
+                BookwormError::new(ErrorKind::Io, "Could not write snapshot".to_owned())
+                    .with_source(err)
+            };
+            let mut header = vec![0; bookworm.pager.data_offset() as usize];
+            bookworm.pager.read_front(&mut header)?;
+            dest.write_all(&header).map_err(write_error)?;
+            let page_size = bookworm.pager.page_size;
+            let pages_count = bookworm.pager.pages_count;
+            let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(pages_count)];
+            for start in (0..pages_count).step_by(COPY_BATCH_PAGES) {
+                let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(pages_count - start)];
+                bookworm.pager.read_pages_into(start, run)?;
+                dest.write_all(run).map_err(write_error)?;
+            }
+            Ok((header.len() + pages_count * page_size) as u64)
+        })
+    }
+    /// Replaces every page with the ones of a snapshot taken by `snapshot_to`, returning how
+    /// many there were. The snapshot is staged in the swap first, so one that doesn't match
+    /// the page size or layout, or ends partway through a page, leaves the pages untouched.
+    pub fn restore_from<R: Read>(&mut self, src: &mut R) -> BookwormResult<usize> {
+        self.in_context(OpKind::Write, |bookworm| {
+            let mut header = vec![0; bookworm.pager.data_offset() as usize];
+            if read_full(src, &mut header)? != header.len() {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not restore: snapshot ends before its file header".to_owned(),
+                ));
+            }
+            if !header.is_empty() {
+                let found = FileHeader::read_from(&mut Cursor::new(&header))?;
+                if found.page_size != bookworm.pager.page_size
+                    || found.layout != bookworm.pager.layout
+                {
+                    return Err(BookwormError::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Could not restore: snapshot has {} byte {:?} pages",
+                            found.page_size, found.layout
+                        ),
+                    ));
+                }
+            }
+            bookworm.check_dense()?;
+            bookworm.invalidate_decoded(..);
+            let page_size = bookworm.pager.page_size;
+            let mut buf = vec![0; page_size * COPY_BATCH_PAGES];
+            let mut swap = bookworm.swap.clear_on_drop();
+            loop {
+                let read = read_full(src, &mut buf)?;
+                if read % page_size != 0 {
+                    return Err(BookwormError::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Could not restore: snapshot ends {} bytes into page {}",
+                            read % page_size,
+                            swap.pages_count + read / page_size
+                        ),
+                    ));
+                }
+                swap.push_raw_pages(&buf[..read])?;
+                if read < buf.len() {
+                    break;
+                }
+            }
+            let restored = swap.pages_count;
+            let pages_count = bookworm.pager.pages_count;
+            bookworm.poisoned = true;
+            bookworm.pager.pages_count = pages_count.max(restored);
+            for start in (0..restored).step_by(COPY_BATCH_PAGES) {
+                let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(restored - start)];
+                swap.read_pages_into(start, run)?;
+                bookworm.pager.write_raw_pages(start, run)?;
+            }
+            bookworm.pager.pages_count = restored;
+            bookworm.pager.free.clear();
+            bookworm.poisoned = false;
+            bookworm
+                .pager
+                .zero_pages(restored..pages_count.max(restored))?;
+            Ok(restored)
+        })
+    }
+}
+
+/// Reads until `buf` is full or `src` ends, returning how many bytes were read
+fn read_full<R: Read>(src: &mut R, buf: &mut [u8]) -> BookwormResult<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match src.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                return Err(
+                    BookwormError::new(ErrorKind::Io, "Could not read snapshot".to_owned())
+                        .with_source(err),
+                )
+            }
+        }
+    }
+    Ok(filled)
+}
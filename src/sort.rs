@@ -0,0 +1,151 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::Debug,
+    io::{Read, Seek, Write},
+    ops::Range,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    codec::Codec,
+    error::{BookwormResult, OpKind},
+    pager::Pager,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Sorts the pages by the key of their records with an external merge sort. Runs of up to
+    /// the sort memory are sorted in memory and staged in the swap, then merged back into the
+    /// data source. Pages with equal keys keep their order.
+    pub fn sort_by_key<T, K, F>(&mut self, mut key: F) -> BookwormResult<()>
+    where
+        T: DeserializeOwned + Debug,
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.in_context(OpKind::Write, |bookworm| {
+            bookworm.check_dense()?;
+            bookworm.check_unchained("sort")?;
+            let page_size = bookworm.pager.page_size;
+            let pages_count = bookworm.pager.pages_count;
+            let run_len = bookworm.sort_memory;
+            let mut buf = vec![0; page_size * run_len.min(pages_count)];
+            bookworm.invalidate_decoded(..);
+            let mut swap = bookworm.swap.clear_on_drop();
+            let mut runs = Vec::new();
+            for start in (0..pages_count).step_by(run_len) {
+                let run = &mut buf[..page_size * run_len.min(pages_count - start)];
+                bookworm.pager.read_pages_into(start, run)?;
+                let mut keyed = Vec::with_capacity(run.len() / page_size);
+                for (page, raw_page) in (start..).zip(run.chunks(page_size)) {
+                    let record = bookworm
+                        .pager
+                        .deserialize(raw_page)
+                        .map_err(|err| err.on_parse(page))?;
+                    keyed.push((key(&record), page - start));
+                }
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                let order: Vec<usize> = keyed.into_iter().map(|(_, index)| index).collect();
+                permute_pages(run, page_size, &order);
+                swap.push_raw_pages(run)?;
+                runs.push(start..start + order.len());
+            }
+            bookworm.metrics.peak_swap_pages =
+                bookworm.metrics.peak_swap_pages.max(swap.pages_count);
+
+            // merged runs bounce between the two storages, starting from the swap
+            let mut in_swap = true;
+            while runs.len() > 1 {
+                bookworm.poisoned = true;
+                runs = if in_swap {
+                    merge_pass(&mut swap, &mut bookworm.pager, &runs, run_len, &mut key)?
+                } else {
+                    merge_pass(&mut bookworm.pager, &mut swap, &runs, run_len, &mut key)?
+                };
+                in_swap = !in_swap;
+            }
+            if in_swap {
+                bookworm.poisoned = true;
+                for start in (0..swap.pages_count).step_by(run_len) {
+                    let run = &mut buf[..page_size * run_len.min(swap.pages_count - start)];
+                    swap.read_pages_into(start, run)?;
+                    bookworm.pager.write_raw_pages(start, run)?;
+                }
+            }
+            bookworm.poisoned = false;
+            Ok(())
+        })
+    }
+}
+
+/// Reorders the pages of `run` in place so that page `i` ends up holding the page that was at
+/// `order[i]`, following each cycle of the permutation with a single spare page
+fn permute_pages(run: &mut [u8], page_size: usize, order: &[usize]) {
+    let mut placed = vec![false; order.len()];
+    let mut spare = vec![0; page_size];
+    for first in 0..order.len() {
+        if placed[first] || order[first] == first {
+            continue;
+        }
+        spare.copy_from_slice(&run[first * page_size..(first + 1) * page_size]);
+        let mut current = first;
+        loop {
+            placed[current] = true;
+            let source = order[current];
+            if source == first {
+                run[current * page_size..(current + 1) * page_size].copy_from_slice(&spare);
+                break;
+            }
+            run.copy_within(
+                source * page_size..(source + 1) * page_size,
+                current * page_size,
+            );
+            current = source;
+        }
+    }
+}
+
+/// Merges groups of up to `fan_in` neighbouring sorted runs of `from` into the same pages of
+/// `to`, keeping a single page of each run in memory. Returns the merged runs.
+fn merge_pass<S, C, H, T, K, F>(
+    from: &mut Pager<S, C, H>,
+    to: &mut Pager<S, C, H>,
+    runs: &[Range<usize>],
+    fan_in: usize,
+    key: &mut F,
+) -> BookwormResult<Vec<Range<usize>>>
+where
+    S: Read + Write + Seek,
+    C: Codec,
+    H: SharedStorage<S>,
+    T: DeserializeOwned,
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    let mut merged = Vec::with_capacity(runs.len().div_ceil(fan_in));
+    for group in runs.chunks(fan_in) {
+        let mut cursors = group.to_vec();
+        let mut heads = vec![vec![0; from.page_size]; group.len()];
+        // ties go to the earlier run, which keeps the sort stable
+        let mut heap = BinaryHeap::with_capacity(group.len());
+        for (run, head) in heads.iter_mut().enumerate() {
+            from.read_page_into(cursors[run].start, head)?;
+            heap.push(Reverse((key(&from.deserialize(head)?), run)));
+        }
+        let mut out = group[0].start;
+        while let Some(Reverse((_, run))) = heap.pop() {
+            to.write_raw_pages(out, &heads[run])?;
+            out += 1;
+            cursors[run].start += 1;
+            if !cursors[run].is_empty() {
+                from.read_page_into(cursors[run].start, &mut heads[run])?;
+                heap.push(Reverse((key(&from.deserialize(&heads[run])?), run)));
+            }
+        }
+        merged.push(group[0].start..out);
+    }
+    Ok(merged)
+}
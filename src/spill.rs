@@ -0,0 +1,126 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::PageLayout,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// What pushing a record bigger than a page does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Fails with `ErrorKind::DataTooLarge` and leaves the store as it was, pages laid out as
+    /// `PageLayout::Chained` still take such records over a chain addressed by its first page
+    #[default]
+    Reject,
+    /// Spreads the record over a chain of pages. Records are addressed by their position
+    /// instead of their first page, and `len` counts records.
+    Spill,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Spilling lays pages out as `PageLayout::Chained`, which only an empty store can switch
+    /// to. The policy is recorded in the file header, and picked back up from it on open.
+    /// `get_page`, `get_page_ref`, `get_raw_page`, `get_page_cached`, `set`, `delete`,
+    /// `truncate`, `to_vec` and the iterators then take record positions.
+    pub fn set_oversize_policy(&mut self, policy: OversizePolicy) -> BookwormResult<()> {
+        self.in_context(OpKind::Write, |bookworm| {
+            if policy == bookworm.pager.oversize {
+                return Ok(());
+            }
+            if policy == OversizePolicy::Spill && bookworm.pager.layout != PageLayout::Chained {
+                #[cfg(feature = "wal")]
+                let logging = bookworm.wal_mode();
+                #[cfg(not(feature = "wal"))]
+                let logging = false;
+                if bookworm.pager.pages_count > 0 || bookworm.free_list || logging {
+                    return Err(BookwormError::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Could not spill oversized records: pages are laid out as {:?} \
+                             already",
+                            bookworm.pager.layout
+                        ),
+                    ));
+                }
+                bookworm.set_layout(PageLayout::Chained);
+            }
+            bookworm.pager.oversize = policy;
+            bookworm.record_starts.clear();
+            bookworm.indexed_pages = 0;
+            if bookworm.pager.has_header() {
+                bookworm.pager.write_header()?;
+            }
+            Ok(())
+        })
+    }
+    pub fn oversize_policy(&self) -> OversizePolicy {
+        self.pager.oversize
+    }
+    pub(crate) fn spilling(&self) -> bool {
+        self.pager.oversize == OversizePolicy::Spill
+    }
+    /// First page of the record at `index` when spilling. Positions past the last record map
+    /// past the last page, so range checks fail the way they would for pages.
+    pub(crate) fn record_page(&self, index: usize) -> usize {
+        if !self.spilling() {
+            return index;
+        }
+        match self.record_starts.get(index) {
+            Some(page) => *page,
+            None => self
+                .pager
+                .pages_count
+                .saturating_add(index - self.record_starts.len()),
+        }
+    }
+    /// Fails when the record starting at `page` spans more than a page, which overwriting it
+    /// in place would leave half dangling
+    pub(crate) fn check_unspilled(&self, page: usize) -> BookwormResult<()> {
+        let Ok(index) = self.record_starts.binary_search(&page) else {
+            return Ok(());
+        };
+        let end = self
+            .record_starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.pager.pages_count);
+        if end - page > 1 {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Could not overwrite record {}: it spans {} pages",
+                    index,
+                    end - page
+                ),
+            ));
+        }
+        Ok(())
+    }
+    /// Brings the first pages of the records up to date after an operation of `kind`. Reads,
+    /// pushes and pops only change the tail, anything else may have moved pages around.
+    pub(crate) fn index_records(&mut self, kind: OpKind) {
+        if !self.spilling() {
+            return;
+        }
+        let pages_count = self.pager.pages_count;
+        match kind {
+            OpKind::Read | OpKind::Scan | OpKind::Flush | OpKind::Push | OpKind::Pop => {
+                let kept = self
+                    .record_starts
+                    .partition_point(|page| *page < pages_count);
+                self.record_starts.truncate(kept);
+                self.indexed_pages = self.indexed_pages.min(pages_count);
+            }
+            _ => {
+                self.record_starts.clear();
+                self.indexed_pages = 0;
+            }
+        }
+        let starts = self.pager.record_starts_in(self.indexed_pages..pages_count);
+        self.record_starts.extend(starts);
+        self.indexed_pages = pages_count;
+    }
+}
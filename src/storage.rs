@@ -0,0 +1,67 @@
+use std::{
+    any::Any,
+    cell::{RefCell, RefMut},
+    ops::DerefMut,
+    rc::Rc,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// Handles through which a bookworm reaches a storage it may share with others.
+/// `Rc<RefCell<S>>` keeps a bookworm on its thread, `Arc<Mutex<S>>` lets it move across
+/// threads.
+pub trait SharedStorage<S>: Clone {
+    type Guard<'a>: DerefMut<Target = S>
+    where
+        Self: 'a;
+    /// Pointer decoded values are cached behind, one that can cross threads along with the
+    /// bookworm when the storage can
+    type Decoded: Clone;
+
+    /// Borrows the storage until the guard is dropped
+    fn access(&self) -> Self::Guard<'_>;
+    /// Takes the storage back when no other handle shares it
+    fn try_unwrap(self) -> Result<S, Self>;
+    fn is_shared(&self) -> bool;
+    fn with<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.access())
+    }
+}
+
+impl<S> SharedStorage<S> for Rc<RefCell<S>> {
+    type Guard<'a>
+        = RefMut<'a, S>
+    where
+        S: 'a;
+    type Decoded = Rc<dyn Any>;
+
+    fn access(&self) -> Self::Guard<'_> {
+        self.borrow_mut()
+    }
+    fn try_unwrap(self) -> Result<S, Self> {
+        Rc::try_unwrap(self).map(RefCell::into_inner)
+    }
+    fn is_shared(&self) -> bool {
+        Rc::strong_count(self) != 1
+    }
+}
+
+/// A storage left poisoned by a thread that panicked holding it is still handed out, the
+/// bookworm's own poisoning covers interrupted rewrites
+impl<S> SharedStorage<S> for Arc<Mutex<S>> {
+    type Guard<'a>
+        = MutexGuard<'a, S>
+    where
+        S: 'a;
+    type Decoded = Arc<dyn Any + Send + Sync>;
+
+    fn access(&self) -> Self::Guard<'_> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    fn try_unwrap(self) -> Result<S, Self> {
+        Arc::try_unwrap(self)
+            .map(|mutex| mutex.into_inner().unwrap_or_else(PoisonError::into_inner))
+    }
+    fn is_shared(&self) -> bool {
+        Arc::strong_count(self) != 1
+    }
+}
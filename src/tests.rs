@@ -1,4 +1,9 @@
-use std::io::Cursor;
+use std::{
+    io::{Cursor, SeekFrom},
+    ops::{Bound, Deref},
+    panic::{catch_unwind, AssertUnwindSafe},
+    time::{Duration, SystemTime},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -15,11 +20,283 @@ impl TestData {
     }
 }
 
-#[test]
-fn test_read_write() {
-    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let mut bookworm = Bookworm::new(1024, data_source, swap);
+/// In-memory storage that records how it is being accessed
+#[derive(Default)]
+struct CountingStorage {
+    inner: Cursor<Vec<u8>>,
+    bytes_read: usize,
+    writes: usize,
+    /// Makes every write fail once this many writes went through
+    fail_writes_after: Option<usize>,
+    flushes: usize,
+    fail_flush: bool,
+    syncs: usize,
+    seeks: usize,
+    /// Takes vectored writes in one call instead of through the default one-buffer shim
+    vectored: bool,
+    vectored_writes: usize,
+    /// Makes reads touching these bytes fail
+    rotten: Option<std::ops::Range<u64>>,
+}
+
+impl Read for CountingStorage {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = self.inner.position();
+        if let Some(rotten) = &self.rotten {
+            if start < rotten.end && rotten.start < start + buf.len() as u64 {
+                return Err(std::io::Error::other("injected read failure"));
+            }
+        }
+        let read = self.inner.read(buf)?;
+        self.bytes_read += read;
+        Ok(read)
+    }
+}
+impl Write for CountingStorage {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self
+            .fail_writes_after
+            .is_some_and(|limit| self.writes >= limit)
+        {
+            return Err(std::io::Error::other("injected write failure"));
+        }
+        self.writes += 1;
+        self.inner.write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+        if !self.vectored {
+            let buf = bufs.iter().find(|buf| !buf.is_empty());
+            return self.write(buf.map_or(&[][..], |buf| buf));
+        }
+        self.vectored_writes += 1;
+        self.inner.write_vectored(bufs)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.fail_flush {
+            return Err(std::io::Error::other("injected flush failure"));
+        }
+        self.flushes += 1;
+        self.inner.flush()
+    }
+}
+impl SyncAll for CountingStorage {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        self.syncs += 1;
+        Ok(())
+    }
+}
+impl Seek for CountingStorage {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.seeks += 1;
+        self.inner.seek(pos)
+    }
+}
+
+/// Storage handles a test runs over, so it can go through both `Bookworm::new` and
+/// `Bookworm::new_shared`
+trait Handles {
+    type Of<S: Read + Write + Seek>: SharedStorage<S>;
+    type Shared<T>: Deref<Target = T>;
+
+    fn wrap<S: Read + Write + Seek>(storage: S) -> Self::Of<S>;
+    fn bookworm<S: Read + Write + Seek>(
+        page_size: usize,
+        data_source: Self::Of<S>,
+        swap: Self::Of<S>,
+    ) -> Bookworm<S, BincodeCodec, Self::Of<S>>;
+    fn open<S: Read + Write + Seek>(
+        page_size: usize,
+        layout: PageLayout,
+        data_source: Self::Of<S>,
+        swap: Self::Of<S>,
+    ) -> BookwormResult<Bookworm<S, BincodeCodec, Self::Of<S>>>;
+    /// `get_page_cached`, which each handle shares its values through differently
+    fn cached<S: Read + Write + Seek, T: DeserializeOwned + Send + Sync + 'static>(
+        bookworm: &mut Over<Self, S>,
+        page: usize,
+    ) -> BookwormResult<Self::Shared<T>>;
+}
+
+/// A bookworm over storages reached through the handles of `H`
+type Over<H, S = Cursor<Vec<u8>>> = Bookworm<S, BincodeCodec, <H as Handles>::Of<S>>;
+
+struct RcHandles;
+impl Handles for RcHandles {
+    type Of<S: Read + Write + Seek> = Rc<RefCell<S>>;
+    type Shared<T> = Rc<T>;
+
+    fn wrap<S: Read + Write + Seek>(storage: S) -> Self::Of<S> {
+        Rc::new(RefCell::new(storage))
+    }
+    fn bookworm<S: Read + Write + Seek>(
+        page_size: usize,
+        data_source: Self::Of<S>,
+        swap: Self::Of<S>,
+    ) -> Bookworm<S, BincodeCodec, Self::Of<S>> {
+        Bookworm::new(page_size, data_source, swap)
+    }
+    fn open<S: Read + Write + Seek>(
+        page_size: usize,
+        layout: PageLayout,
+        data_source: Self::Of<S>,
+        swap: Self::Of<S>,
+    ) -> BookwormResult<Bookworm<S, BincodeCodec, Self::Of<S>>> {
+        Bookworm::open(page_size, layout, data_source, swap)
+    }
+    fn cached<S: Read + Write + Seek, T: DeserializeOwned + Send + Sync + 'static>(
+        bookworm: &mut Over<Self, S>,
+        page: usize,
+    ) -> BookwormResult<Self::Shared<T>> {
+        bookworm.get_page_cached(page)
+    }
+}
+
+struct ArcHandles;
+impl Handles for ArcHandles {
+    type Of<S: Read + Write + Seek> = Arc<Mutex<S>>;
+    type Shared<T> = Arc<T>;
+
+    fn wrap<S: Read + Write + Seek>(storage: S) -> Self::Of<S> {
+        Arc::new(Mutex::new(storage))
+    }
+    fn bookworm<S: Read + Write + Seek>(
+        page_size: usize,
+        data_source: Self::Of<S>,
+        swap: Self::Of<S>,
+    ) -> Bookworm<S, BincodeCodec, Self::Of<S>> {
+        Bookworm::new_shared(page_size, data_source, swap)
+    }
+    fn open<S: Read + Write + Seek>(
+        page_size: usize,
+        layout: PageLayout,
+        data_source: Self::Of<S>,
+        swap: Self::Of<S>,
+    ) -> BookwormResult<Bookworm<S, BincodeCodec, Self::Of<S>>> {
+        Bookworm::open_shared(page_size, layout, data_source, swap)
+    }
+    fn cached<S: Read + Write + Seek, T: DeserializeOwned + Send + Sync + 'static>(
+        bookworm: &mut Over<Self, S>,
+        page: usize,
+    ) -> BookwormResult<Self::Shared<T>> {
+        bookworm.get_page_cached(page)
+    }
+}
+
+/// Runs each test once over `Rc<RefCell<_>>` storages and once over `Arc<Mutex<_>>` ones
+macro_rules! over_both_handles {
+    ($($(#[$attr:meta])* $test:ident),* $(,)?) => {
+        mod over_both_handles {
+            $(
+                $(#[$attr])*
+                #[test]
+                fn $test() {
+                    super::$test::<super::RcHandles>();
+                    super::$test::<super::ArcHandles>();
+                }
+            )*
+        }
+    };
+}
+
+over_both_handles!(
+    test_read_write,
+    test_iter_with_policy,
+    test_iter_pages,
+    test_push,
+    test_remove_page,
+    test_delete_page,
+    test_page_info_iter,
+    test_get_page_cached,
+    test_get_page_cached_distinct_types,
+    test_replace_swap,
+    test_swap_clear,
+    test_page_fill,
+    test_empty_pages,
+    test_read_write_at,
+    test_sequenced_log,
+    test_sequenced_log_regression,
+    test_sequence_and_ttl_codec,
+    test_error_context,
+    test_close,
+    test_close_failure,
+    test_push_panic_safety,
+    test_compaction_panic_safety,
+    test_delete_failure_poisons,
+    test_to_vec,
+    test_custom_codec_closures,
+    test_append_mode,
+    test_pages_view,
+    test_vectored_shift,
+    test_delete_with_recovery,
+    test_refresh,
+    test_decode_limit,
+    test_manifest,
+    test_insert,
+    test_set,
+    test_stats,
+    test_swap_pages,
+    test_truncate,
+    test_clear,
+    test_get_raw_page_into,
+    test_push_all,
+    test_move_page,
+    test_borrowing_iter,
+    test_borrowing_raw_iter,
+    test_double_ended_iterators,
+    test_iterator_nth_seeks,
+    test_into_iterator_for_borrow,
+    test_iter_range,
+    test_first_and_last,
+    test_find_page,
+    test_binary_search_by,
+    test_sort_by_key,
+    test_sort_by_key_corrupt_page,
+    test_retain,
+    test_retain_panic_is_restartable,
+    test_dedup,
+    test_drain,
+    test_splice,
+    test_copy_page,
+    test_swap_remove,
+    test_compact,
+    test_length_prefixed_layout,
+    test_chained_layout,
+    test_oversize_policy,
+    #[cfg(feature = "varint-codec")]
+    test_varint_codec,
+    test_bincode_codec,
+    test_codec_mismatch,
+    test_checksummed_layout,
+    test_file_header,
+    test_count_source,
+    test_free_list,
+    test_free_list_clear,
+    test_free_list_readers,
+    test_delete_in_place,
+    test_live_bitmap,
+    test_delete_shift_memory,
+    test_error_source,
+    test_iter_fallible,
+    test_get_page_ref,
+    test_page_cache,
+    test_write_back,
+    test_durability,
+    test_transaction,
+    #[cfg(feature = "wal")]
+    test_wal_torn_tail,
+    test_snapshot_restore,
+    test_snapshot_restore_header,
+    test_import_from,
+    test_compact_and_sort_framed_layouts,
+    test_read_write_at_layouts,
+    test_page_iterator_fused,
+    test_verify_page,
+);
+
+fn test_read_write<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(1024, data_source, swap);
     let test_data1 = TestData::new(10, true);
     let test_data2 = TestData::new(15, false);
     let test_data3 = TestData::new(20, true);
@@ -32,11 +309,10 @@ fn test_read_write() {
     assert_eq!(bookworm.get_page::<TestData>(2).unwrap(), test_data3);
 }
 
-#[test]
-fn test_iter_pages() {
-    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let mut bookworm = Bookworm::new(1024, data_source, swap);
+fn test_iter_pages<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(1024, data_source, swap);
     bookworm.push(&TestData::new(10, true)).unwrap();
     bookworm.push(&TestData::new(14, false)).unwrap();
     bookworm.push(&TestData::new(17, true)).unwrap();
@@ -49,12 +325,11 @@ fn test_iter_pages() {
     assert_eq!(iterator.next().unwrap(), TestData::new(6, false));
     assert_eq!(iterator.next(), None);
 }
-#[test]
-fn test_push() {
-    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+fn test_push<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
 
-    let mut bookworm = Bookworm::new(1024, data_source.clone(), swap.clone());
+    let mut bookworm = H::bookworm(1024, data_source.clone(), swap.clone());
 
     bookworm.push(&TestData::new(10, true)).unwrap();
     bookworm.push(&TestData::new(12, false)).unwrap();
@@ -66,7 +341,7 @@ fn test_push() {
     assert_eq!(iterator.next().unwrap(), TestData::new(6, true));
 
     drop(iterator);
-    let mut bookworm = Bookworm::new(1024, data_source.clone(), swap.clone());
+    let mut bookworm = H::bookworm(1024, data_source.clone(), swap.clone());
     bookworm.push(&TestData::new(18, false)).unwrap();
     let mut iterator = bookworm.into_iter::<TestData>();
     assert_eq!(iterator.next().unwrap(), TestData::new(10, true));
@@ -74,22 +349,20 @@ fn test_push() {
     assert_eq!(iterator.next().unwrap(), TestData::new(6, true));
     assert_eq!(iterator.next().unwrap(), TestData::new(18, false));
 }
-#[test]
-fn test_remove_page() {
-    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let mut pager = Bookworm::new(32, data_source, swap);
+fn test_remove_page<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut pager = H::bookworm(32, data_source, swap);
     let test_data = TestData::new(10, true);
     pager.push(&test_data).unwrap();
     pager.get_page::<TestData>(0).unwrap();
     pager.pop().unwrap();
     pager.get_page::<TestData>(0).unwrap_err();
 }
-#[test]
-fn test_delete_page() {
-    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
-    let mut bookworm = Bookworm::new(32, data_source, swap);
+fn test_delete_page<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
 
     bookworm.push(&TestData::new(10, true)).unwrap();
     bookworm.push(&TestData::new(12, false)).unwrap();
@@ -100,3 +373,4593 @@ fn test_delete_page() {
     assert_eq!(pages_iter.next().unwrap(), TestData::new(10, true));
     assert_eq!(pages_iter.next().unwrap(), TestData::new(6, true));
 }
+fn test_page_info_iter<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(64, data_source.clone(), swap);
+    bookworm.set_layout(PageLayout::Checksummed(ChecksumAlgorithm::Sha256));
+    for i in 0..4 {
+        bookworm.push(&vec![i as u8; i + 1]).unwrap();
+    }
+
+    data_source.access().bytes_read = 0;
+    let infos = bookworm
+        .page_info_iter()
+        .collect::<BookwormResult<Vec<_>>>()
+        .unwrap();
+    let info_bytes = data_source.access().bytes_read;
+    assert_eq!(info_bytes, 4 * (5 + 32));
+    assert_eq!(infos.len(), 4);
+    for (i, info) in infos.iter().enumerate() {
+        let payload = bookworm.get_raw_page(i).unwrap();
+        assert_eq!(info.index, i);
+        assert_eq!(info.payload_len, payload.len());
+        assert_eq!(info.payload_len, 8 + i + 1);
+        assert_eq!(info.checksum, Some(Sha256.digest(&payload)));
+        assert_eq!(info.flags, None);
+        assert_eq!(info.written_at, None);
+    }
+
+    data_source.access().bytes_read = 0;
+    for page in 0..4 {
+        bookworm.get_raw_page(page).unwrap();
+    }
+    let scan_bytes = data_source.access().bytes_read;
+    assert_eq!(scan_bytes, 4 * 64);
+    assert!(info_bytes < scan_bytes);
+
+    // a length prefix carries no checksum, padded pages are reported as full
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    bookworm.set_layout(PageLayout::LengthPrefixed);
+    bookworm.push_raw(b"abc").unwrap();
+    let info = bookworm.page_info_iter().next().unwrap().unwrap();
+    assert_eq!((info.payload_len, info.checksum), (3, None));
+    bookworm.set_layout(PageLayout::Padded);
+    let info = bookworm.page_info_iter().next().unwrap().unwrap();
+    assert_eq!((info.payload_len, info.checksum), (16, None));
+
+    // chained pages report the flags of their header, continuations have the low bit set
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    bookworm.set_layout(PageLayout::Chained);
+    bookworm.push_raw(b"abc").unwrap();
+    bookworm.push_raw(&[7; 10]).unwrap();
+    let infos = bookworm
+        .page_info_iter()
+        .collect::<BookwormResult<Vec<_>>>()
+        .unwrap();
+    let flags = infos.iter().map(|info| info.flags).collect::<Vec<_>>();
+    assert_eq!(flags, [Some(0), Some(0), Some(1)]);
+    assert_eq!(
+        infos
+            .iter()
+            .map(|info| info.payload_len)
+            .collect::<Vec<_>>(),
+        [3, 7, 3]
+    );
+    assert!(infos.iter().all(|info| info.written_at.is_none()));
+
+    // a poisoned bookworm refuses to describe its pages
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    for i in 0..3 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    let writes = data_source.access().writes;
+    data_source.access().fail_writes_after = Some(writes + 1);
+    bookworm.delete(0).unwrap_err();
+    assert!(bookworm.is_poisoned());
+    assert!(bookworm.page_info_iter().all(|info| info.is_err()));
+}
+fn test_get_page_cached<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+    bookworm.push(&TestData::new(12, false)).unwrap();
+
+    let first = H::cached::<_, TestData>(&mut bookworm, 1).unwrap();
+    let second = H::cached::<_, TestData>(&mut bookworm, 1).unwrap();
+    assert_eq!(*first, TestData::new(12, false));
+    assert!(std::ptr::eq(&*first, &*second));
+
+    bookworm.pop().unwrap();
+    bookworm.push(&TestData::new(30, true)).unwrap();
+    let updated = H::cached::<_, TestData>(&mut bookworm, 1).unwrap();
+    assert_eq!(*updated, TestData::new(30, true));
+    assert!(!std::ptr::eq(&*first, &*updated));
+
+    bookworm.delete(0).unwrap();
+    assert_eq!(
+        *H::cached::<_, TestData>(&mut bookworm, 0).unwrap(),
+        TestData::new(30, true)
+    );
+
+    // past its capacity the cache drops the least recently read values
+    bookworm.push(&TestData::new(40, false)).unwrap();
+    bookworm.set_decoded_cache_capacity(1);
+    let first = H::cached::<_, TestData>(&mut bookworm, 0).unwrap();
+    H::cached::<_, TestData>(&mut bookworm, 1).unwrap();
+    let again = H::cached::<_, TestData>(&mut bookworm, 0).unwrap();
+    assert_eq!(*again, TestData::new(30, true));
+    assert!(!std::ptr::eq(&*first, &*again));
+
+    // a poisoned bookworm refuses even values it has cached
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    for i in 0..3 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    H::cached::<_, TestData>(&mut bookworm, 0).unwrap();
+    let writes = data_source.access().writes;
+    data_source.access().fail_writes_after = Some(writes + 1);
+    bookworm.delete(1).unwrap_err();
+    assert!(bookworm.is_poisoned());
+    assert!(H::cached::<_, TestData>(&mut bookworm, 0).is_err());
+}
+fn test_get_page_cached_distinct_types<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+
+    let as_data = H::cached::<_, TestData>(&mut bookworm, 0).unwrap();
+    let as_count = H::cached::<_, u8>(&mut bookworm, 0).unwrap();
+    assert_eq!(*as_data, TestData::new(10, true));
+    assert_eq!(*as_count, 10);
+    assert_eq!(
+        *H::cached::<_, TestData>(&mut bookworm, 0).unwrap(),
+        TestData::new(10, true)
+    );
+    assert_eq!(*H::cached::<_, u8>(&mut bookworm, 0).unwrap(), 10);
+}
+#[test]
+fn test_get_page_cached_unsync() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(32, data_source, swap);
+    bookworm.push(&7u8).unwrap();
+
+    // values behind `Rc` don't need to be shareable across threads
+    let first = bookworm.get_page_cached::<std::cell::Cell<u8>>(0).unwrap();
+    first.set(8);
+    assert_eq!(
+        bookworm
+            .get_page_cached::<std::cell::Cell<u8>>(0)
+            .unwrap()
+            .get(),
+        8
+    );
+    assert_eq!(bookworm.get_page::<u8>(0).unwrap(), 7);
+}
+#[test]
+fn test_try_unwrap_inner() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(32, data_source, swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+    bookworm.push(&TestData::new(12, false)).unwrap();
+
+    let (data_source, swap) = bookworm.try_unwrap_inner().ok().unwrap();
+    let bytes = data_source.into_inner();
+    assert_eq!(bytes.len(), 64);
+    assert_eq!(&bytes[..2], &[10, 1]);
+    assert!(bytes[2..32].iter().all(|byte| *byte == 0));
+    assert_eq!(&bytes[32..34], &[12, 0]);
+    assert!(swap.into_inner().is_empty());
+}
+#[test]
+fn test_try_unwrap_inner_shared() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(32, data_source.clone(), swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+
+    let mut bookworm = match bookworm.try_unwrap_inner() {
+        Ok(_) => panic!("data source is still shared"),
+        Err(bookworm) => bookworm,
+    };
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(10, true)
+    );
+
+    let (returned, _) = bookworm.into_inner();
+    assert!(Rc::ptr_eq(&returned, &data_source));
+}
+fn test_replace_swap<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let first_swap = H::wrap(Cursor::new(Vec::new()));
+    let second_swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, first_swap.clone());
+    bookworm.set_shift_memory(0);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+
+    bookworm.delete(1).unwrap();
+    assert_eq!(bookworm.swap_len(), 0);
+    assert_eq!(bookworm.metrics().peak_swap_pages, 3);
+    let first_swap_len = first_swap.access().get_ref().len();
+    assert_eq!(first_swap_len, DELETE_MARKER_BYTES as usize + 3 * 32);
+
+    bookworm.replace_swap(second_swap.clone()).unwrap();
+    bookworm.delete(0).unwrap();
+    assert_eq!(first_swap.access().get_ref().len(), first_swap_len);
+    assert_eq!(
+        second_swap.access().get_ref().len(),
+        DELETE_MARKER_BYTES as usize + 3 * 32
+    );
+    assert_eq!(bookworm.metrics().peak_swap_pages, 3);
+
+    let mut iter = bookworm.into_iter::<TestData>();
+    assert_eq!(iter.next().unwrap(), TestData::new(2, true));
+    assert_eq!(iter.next().unwrap(), TestData::new(3, true));
+    assert_eq!(iter.next().unwrap(), TestData::new(4, true));
+}
+fn test_swap_clear<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap.clone());
+    bookworm.set_shift_memory(0);
+    for i in 1..4 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    bookworm.delete(0).unwrap();
+    assert!(swap.access().get_ref().iter().any(|byte| *byte != 0));
+
+    bookworm.swap_clear().unwrap();
+    assert_eq!(bookworm.swap_len(), 0);
+    assert_eq!(
+        swap.access().get_ref().len(),
+        DELETE_MARKER_BYTES as usize + 2 * 32
+    );
+    assert!(swap.access().get_ref().iter().all(|byte| *byte == 0));
+}
+#[test]
+fn test_with_capacity() {
+    let data_source = Rc::new(RefCell::new(CountingStorage::default()));
+    let swap = Rc::new(RefCell::new(CountingStorage::default()));
+    let mut bookworm = Bookworm::with_capacity(32, 4, data_source.clone(), swap).unwrap();
+    assert_eq!(data_source.borrow().inner.get_ref().len(), 4 * 32);
+    assert_eq!(bookworm.capacity(), 4);
+    assert_eq!(bookworm.remaining_capacity(), 4);
+    bookworm.get_page::<TestData>(0).unwrap_err();
+
+    data_source.borrow_mut().writes = 0;
+    for i in 0..4 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    assert_eq!(data_source.borrow().writes, 4);
+    assert_eq!(bookworm.remaining_capacity(), 0);
+
+    data_source.borrow_mut().writes = 0;
+    bookworm.push(&TestData::new(4, false)).unwrap();
+    assert_eq!(data_source.borrow().writes, 2);
+    assert_eq!(bookworm.capacity(), 5);
+    assert_eq!(data_source.borrow().inner.get_ref().len(), 5 * 32);
+    for i in 0..4 {
+        assert_eq!(
+            bookworm.get_page::<TestData>(i).unwrap(),
+            TestData::new(i as u8, true)
+        );
+    }
+}
+#[test]
+fn test_with_capacity_non_empty_source() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(vec![1; 64])));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    assert!(Bookworm::with_capacity(32, 4, data_source.clone(), swap).is_err());
+    assert_eq!(data_source.borrow().get_ref(), &vec![1; 64]);
+}
+fn test_page_fill<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+    bookworm.push(&(1u64, 2u64)).unwrap();
+    bookworm.push(&TestData::new(10, false)).unwrap();
+
+    let fill = bookworm.page_fill(0).unwrap();
+    assert_eq!(fill.payload_bytes, 2);
+    assert_eq!(fill.capacity, 32);
+    assert_eq!(fill.fraction, 2.0 / 32.0);
+    assert!(!fill.exact);
+    // The heuristic can't tell a trailing `false` from padding
+    assert_eq!(bookworm.page_fill(2).unwrap().payload_bytes, 1);
+    bookworm.page_fill(3).unwrap_err();
+
+    let summary = bookworm.fill_summary().unwrap();
+    assert_eq!(summary.pages, 3);
+    assert_eq!(summary.min, 1);
+    assert_eq!(summary.max, 9);
+    assert_eq!(summary.avg, 4.0);
+    assert!(!summary.exact);
+}
+fn test_empty_pages<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+    bookworm.push(&()).unwrap();
+    bookworm.push(&TestData::new(12, true)).unwrap();
+    assert!(!bookworm.is_page_empty(0).unwrap());
+    assert!(bookworm.is_page_empty(1).unwrap());
+    assert_eq!(bookworm.find_first_empty_page(0).unwrap(), Some(1));
+    assert_eq!(bookworm.find_first_empty_page(2).unwrap(), None);
+    bookworm.is_page_empty(3).unwrap_err();
+
+    bookworm.pop().unwrap();
+    assert!(bookworm.is_page_empty(2).unwrap());
+    assert_eq!(bookworm.find_first_empty_page(2).unwrap(), Some(2));
+
+    bookworm.push(&TestData::new(14, true)).unwrap();
+    assert!(!bookworm.is_page_empty(2).unwrap());
+    assert_eq!(bookworm.find_first_empty_page(2).unwrap(), None);
+
+    bookworm.delete(0).unwrap();
+    assert!(bookworm.is_page_empty(0).unwrap());
+    assert!(!bookworm.is_page_empty(1).unwrap());
+    assert!(bookworm.is_page_empty(2).unwrap());
+    assert_eq!(bookworm.find_first_empty_page(1).unwrap(), Some(2));
+}
+fn test_read_write_at<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    bookworm.push(&[1u8; 16]).unwrap();
+    bookworm.push(&[2u8; 16]).unwrap();
+    assert_eq!(
+        *H::cached::<_, [u8; 16]>(&mut bookworm, 0).unwrap(),
+        [1; 16]
+    );
+
+    bookworm.write_at(0, 6, &[9, 9, 9, 9]).unwrap();
+    assert_eq!(bookworm.read_at(0, 6, 4).unwrap(), vec![9; 4]);
+    let mut expected = vec![1; 16];
+    expected[6..10].copy_from_slice(&[9; 4]);
+    assert_eq!(bookworm.get_raw_page(0).unwrap(), expected);
+    assert_eq!(bookworm.get_raw_page(1).unwrap(), vec![2; 16]);
+    assert_eq!(
+        H::cached::<_, [u8; 16]>(&mut bookworm, 0).unwrap().to_vec(),
+        expected
+    );
+
+    bookworm.write_at(0, 14, &[0; 4]).unwrap_err();
+    bookworm.read_at(0, 12, 8).unwrap_err();
+    bookworm.read_at(2, 0, 1).unwrap_err();
+    assert_eq!(bookworm.get_raw_page(0).unwrap(), expected);
+}
+fn test_sequenced_log<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap.clone());
+    assert_eq!(bookworm.last_sequence().unwrap(), None);
+    for i in 0..3 {
+        assert_eq!(
+            bookworm.push_sequenced(&TestData::new(i, true)).unwrap(),
+            i as u64
+        );
+    }
+    drop(bookworm);
+
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    assert_eq!(bookworm.last_sequence().unwrap(), Some(2));
+    assert_eq!(
+        bookworm.push_sequenced(&TestData::new(3, false)).unwrap(),
+        3
+    );
+    assert_eq!(
+        bookworm.push_sequenced(&TestData::new(4, false)).unwrap(),
+        4
+    );
+
+    let records = bookworm
+        .iter_from_sequence::<TestData>(2)
+        .unwrap()
+        .collect::<BookwormResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        records,
+        vec![
+            (2, TestData::new(2, true)),
+            (3, TestData::new(3, false)),
+            (4, TestData::new(4, false)),
+        ]
+    );
+    assert_eq!(
+        bookworm.iter_from_sequence::<TestData>(5).unwrap().count(),
+        0
+    );
+}
+fn test_sequenced_log_regression<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..5 {
+        bookworm.push_sequenced(&TestData::new(i, true)).unwrap();
+    }
+    bookworm.write_at(3, 0, &1u64.to_le_bytes()).unwrap();
+
+    let mut iter = bookworm.iter_from_sequence::<TestData>(0).unwrap();
+    assert_eq!(iter.next().unwrap().unwrap().0, 0);
+    assert_eq!(iter.next().unwrap().unwrap().0, 1);
+    assert_eq!(iter.next().unwrap().unwrap().0, 2);
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+fn test_sequence_and_ttl_codec<H: Handles>() {
+    // the leading field is decoded with the codec from the payload, past any header
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = Bookworm::with_codec(32, data_source, swap, XorCodec(0x5a));
+    bookworm.set_layout(PageLayout::LengthPrefixed);
+    for i in 0..3 {
+        assert_eq!(bookworm.push_sequenced(&i).unwrap(), i as u64);
+    }
+    assert_eq!(bookworm.last_sequence().unwrap(), Some(2));
+    let sequences = bookworm
+        .iter_from_sequence::<i32>(1)
+        .unwrap()
+        .map(|record| record.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(sequences, vec![1, 2]);
+
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = Bookworm::with_codec(32, data_source, swap, XorCodec(0x5a));
+    bookworm.set_layout(PageLayout::Checksummed(ChecksumAlgorithm::XxHash64));
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    bookworm.set_clock(move || now);
+    bookworm
+        .push_with_ttl(&1u8, Duration::from_secs(10))
+        .unwrap();
+    bookworm
+        .push_with_ttl(&2u8, Duration::from_secs(100))
+        .unwrap();
+    let later = now + Duration::from_secs(50);
+    assert_eq!(bookworm.purge_expired(later).unwrap(), 1);
+    let unexpired = bookworm.iter_unexpired::<u8>().unwrap().collect::<Vec<_>>();
+    assert_eq!(unexpired, vec![2]);
+}
+#[test]
+fn test_ttl() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(32, data_source, swap);
+    let now = Arc::new(Mutex::new(
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_000),
+    ));
+    let clock = now.clone();
+    bookworm.set_clock(move || *clock.lock().unwrap());
+
+    bookworm
+        .push_with_ttl(&TestData::new(1, true), Duration::from_secs(10))
+        .unwrap();
+    bookworm
+        .push_with_ttl(&TestData::new(2, true), Duration::from_secs(100))
+        .unwrap();
+    bookworm
+        .push_with_ttl(&TestData::new(3, true), Duration::from_secs(10))
+        .unwrap();
+    bookworm
+        .push_with_ttl(&TestData::new(4, true), Duration::from_secs(1_000))
+        .unwrap();
+
+    let unexpired = bookworm.iter_unexpired::<TestData>().unwrap().count();
+    assert_eq!(unexpired, 4);
+
+    *now.lock().unwrap() += Duration::from_secs(50);
+    let unexpired = bookworm
+        .iter_unexpired::<TestData>()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unexpired,
+        vec![TestData::new(2, true), TestData::new(4, true)]
+    );
+    bookworm.get_page::<(u64, TestData)>(3).unwrap();
+
+    assert_eq!(bookworm.purge_expired(*now.lock().unwrap()).unwrap(), 2);
+    bookworm.get_page::<(u64, TestData)>(2).unwrap_err();
+    assert!(bookworm.is_page_empty(2).unwrap());
+    assert!(bookworm.is_page_empty(3).unwrap());
+    assert_eq!(
+        bookworm.get_page::<(u64, TestData)>(1).unwrap().1,
+        TestData::new(4, true)
+    );
+
+    *now.lock().unwrap() += Duration::from_secs(100);
+    assert_eq!(bookworm.purge_expired(*now.lock().unwrap()).unwrap(), 1);
+    assert_eq!(
+        bookworm
+            .iter_unexpired::<TestData>()
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![TestData::new(4, true)]
+    );
+}
+fn test_error_context<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    let read_err = bookworm.get_page::<TestData>(10).unwrap_err();
+    let read_context = read_err.context().unwrap();
+    assert_eq!(read_context.kind(), error::OpKind::Read);
+
+    // Staging into the swap works, the failure happens while copying pages back
+    let writes = data_source.access().writes;
+    data_source.access().fail_writes_after = Some(writes + 1);
+    let delete_err = bookworm.delete(1).unwrap_err();
+    let delete_context = delete_err.context().unwrap();
+    assert_eq!(delete_context.kind(), error::OpKind::Delete);
+    assert!(delete_context.id() > read_context.id());
+}
+fn test_close<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap.clone());
+    bookworm.push(&TestData::new(10, true)).unwrap();
+    bookworm.close().unwrap();
+    assert_eq!(data_source.access().flushes, 1);
+    assert_eq!(swap.access().flushes, 1);
+
+    let bookworm = H::bookworm(32, data_source.clone(), swap.clone());
+    drop(bookworm);
+    assert_eq!(data_source.access().flushes, 2);
+    assert_eq!(swap.access().flushes, 2);
+}
+fn test_close_failure<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    data_source.access().fail_flush = true;
+
+    let bookworm = H::bookworm(32, data_source.clone(), swap.clone());
+    let err = bookworm.close().unwrap_err();
+    assert_eq!(err.context().unwrap().kind(), error::OpKind::Flush);
+
+    let bookworm = H::bookworm(32, data_source, swap);
+    drop(bookworm);
+}
+struct PanicOnSerialize;
+
+impl Serialize for PanicOnSerialize {
+    fn serialize<Ser: serde::Serializer>(&self, _serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        panic!("serializer panicked")
+    }
+}
+
+fn test_push_panic_safety<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.push(&TestData::new(10, true)).unwrap();
+
+    let result = catch_unwind(AssertUnwindSafe(|| bookworm.push(&PanicOnSerialize)));
+    assert!(result.is_err());
+    assert!(!bookworm.is_poisoned());
+    bookworm.get_page::<TestData>(1).unwrap_err();
+
+    bookworm.push(&vec![0u8; 64]).unwrap_err();
+    bookworm.get_page::<TestData>(1).unwrap_err();
+
+    bookworm.push(&TestData::new(12, false)).unwrap();
+    assert_eq!(
+        bookworm.get_page::<TestData>(1).unwrap(),
+        TestData::new(12, false)
+    );
+}
+fn test_compaction_panic_safety<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+
+    // Nothing was moved yet when the predicate panics, so the store is untouched
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        bookworm.compact_pages(|raw_page| {
+            if raw_page[0] == 1 {
+                panic!("predicate panicked");
+            }
+            Ok(true)
+        })
+    }));
+    assert!(result.is_err());
+    assert!(!bookworm.is_poisoned());
+    assert_eq!(
+        bookworm.get_page::<TestData>(4).unwrap(),
+        TestData::new(4, true)
+    );
+
+    // Page 1 was already moved over page 0 when the predicate panics
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        bookworm.compact_pages(|raw_page| match raw_page[0] {
+            0 => Ok(false),
+            3 => panic!("predicate panicked"),
+            _ => Ok(true),
+        })
+    }));
+    assert!(result.is_err());
+    assert!(bookworm.is_poisoned());
+    bookworm.get_page::<TestData>(0).unwrap_err();
+    bookworm.push(&TestData::new(5, true)).unwrap_err();
+
+    bookworm.clear_poison();
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(1, true)
+    );
+}
+fn test_delete_failure_poisons<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    let writes = data_source.access().writes;
+    data_source.access().fail_writes_after = Some(writes + 1);
+    bookworm.delete(1).unwrap_err();
+    assert!(bookworm.is_poisoned());
+    assert_eq!(bookworm.swap_len(), 0);
+    bookworm.get_page::<TestData>(0).unwrap_err();
+}
+fn test_to_vec<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    assert!(bookworm.to_vec::<TestData>().unwrap().is_empty());
+    for i in 0..3 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    let records = bookworm.to_vec::<TestData>().unwrap();
+    assert_eq!(
+        records,
+        vec![
+            TestData::new(0, true),
+            TestData::new(1, true),
+            TestData::new(2, true)
+        ]
+    );
+    let raw = bookworm.to_raw_vec().unwrap();
+    assert_eq!(raw.len(), 3);
+    assert!(raw.iter().all(|page| page.len() == 32));
+    assert_eq!(&raw[2][..2], &[2, 1]);
+
+    // bincode rejects 2 as a bool
+    bookworm.write_at(1, 1, &[2]).unwrap();
+    let err = bookworm.to_vec::<TestData>().unwrap_err();
+    assert!(err.to_string().contains("page 1"));
+    assert_eq!(bookworm.to_raw_vec().unwrap().len(), 3);
+}
+fn test_custom_codec_closures<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm
+        .push_with(|| Ok::<_, String>(vec![0xAB, 3, b'f', b'o', b'o']))
+        .unwrap();
+    bookworm.push(&TestData::new(10, true)).unwrap();
+
+    let decode_header = |raw: &[u8]| match raw {
+        [0xAB, len, rest @ ..] => Ok(String::from_utf8_lossy(&rest[..*len as usize]).to_string()),
+        _ => Err("missing magic byte"),
+    };
+    assert_eq!(bookworm.get_page_with(0, decode_header).unwrap(), "foo");
+    assert_eq!(
+        bookworm.get_page::<TestData>(1).unwrap(),
+        TestData::new(10, true)
+    );
+
+    let err = bookworm.get_page_with(1, decode_header).unwrap_err();
+    assert!(err.to_string().contains("page 1"));
+    assert!(err.to_string().contains("missing magic byte"));
+
+    let err = bookworm
+        .push_with(|| Err::<Vec<u8>, _>("encoder failed"))
+        .unwrap_err();
+    assert!(err.to_string().contains("page 2"));
+    bookworm
+        .push_with(|| Ok::<_, String>(vec![1; 33]))
+        .unwrap_err();
+    bookworm.get_raw_page(2).unwrap_err();
+}
+fn test_append_mode<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    bookworm.append_mode(true);
+
+    data_source.access().seeks = 0;
+    data_source.access().writes = 0;
+    for i in 0..10_000u32 {
+        bookworm.push(&i).unwrap();
+    }
+    assert_eq!(data_source.access().seeks, 1);
+    assert_eq!(data_source.access().writes, 10_000);
+    assert_eq!(bookworm.metrics().corrective_seeks, 1);
+    assert_eq!(data_source.access().inner.get_ref().len(), 10_000 * 32);
+
+    assert_eq!(bookworm.get_page::<u32>(1234).unwrap(), 1234);
+    bookworm.push(&10_000u32).unwrap();
+    bookworm.push(&10_001u32).unwrap();
+    assert_eq!(bookworm.metrics().corrective_seeks, 2);
+    assert_eq!(bookworm.get_page::<u32>(10_000).unwrap(), 10_000);
+    assert_eq!(bookworm.get_page::<u32>(10_001).unwrap(), 10_001);
+    assert_eq!(bookworm.get_page::<u32>(9_999).unwrap(), 9_999);
+}
+fn store_with_corrupt_page<H: Handles>() -> Over<H> {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(64, data_source, swap);
+    for count in 0..3 {
+        bookworm
+            .push(&TestData {
+                count,
+                signed: false,
+            })
+            .unwrap();
+    }
+    // 2 isn't a valid bool
+    bookworm.write_at(1, 1, &[2]).unwrap();
+    bookworm
+}
+fn test_iter_with_policy<H: Handles>() {
+    let mut bookworm = store_with_corrupt_page::<H>();
+    let mut strict = bookworm.try_iter::<TestData>();
+    assert_eq!(strict.next().unwrap().unwrap().count, 0);
+    let err = strict.next().unwrap().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse page 1: invalid u8 while decoding bool, expected 0 or 1, found 2"
+    );
+    assert_eq!(err.context().unwrap().kind(), OpKind::Scan);
+    assert!(strict.next().is_none());
+    drop(strict);
+
+    let mut skip = bookworm.iter_with_policy::<TestData>(OnDecodeError::Skip);
+    let counts: Vec<u8> = skip.by_ref().map(|record| record.unwrap().count).collect();
+    assert_eq!(counts, vec![0, 2]);
+    assert_eq!(skip.skipped(), 1);
+    drop(skip);
+
+    let substitute = OnDecodeError::Substitute(Box::new(|page, raw_page: &[u8], _: &_| {
+        assert_eq!(raw_page[1], 2);
+        Some(TestData {
+            count: page as u8 + 100,
+            signed: true,
+        })
+    }));
+    let mut substitute = bookworm.iter_with_policy(substitute);
+    let counts: Vec<u8> = substitute
+        .by_ref()
+        .map(|record| record.unwrap().count)
+        .collect();
+    assert_eq!(counts, vec![0, 101, 2]);
+    assert_eq!(substitute.skipped(), 0);
+    drop(substitute);
+
+    let mut dropped =
+        bookworm.iter_with_policy(OnDecodeError::Substitute(Box::new(|_, _: &[u8], _: &_| {
+            None::<TestData>
+        })));
+    assert_eq!(dropped.by_ref().count(), 2);
+    assert_eq!(dropped.skipped(), 1);
+}
+fn test_pages_view<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..20u32 {
+        bookworm.push(&i).unwrap();
+    }
+    let mut pages = bookworm.pages();
+    assert_eq!(pages.len(), 20);
+    assert_eq!(pages.get::<u32>(19).unwrap(), 19);
+    assert!(pages.get::<u32>(20).is_err());
+
+    let mut middle = pages.range(5..15).unwrap();
+    assert_eq!(middle.len(), 10);
+    assert_eq!(middle.get::<u32>(0).unwrap(), 5);
+    assert!(middle.get::<u32>(10).is_err());
+    assert_eq!(middle.get_raw(1).unwrap()[0], 6);
+
+    let mut nested = middle.range(2..=4).unwrap();
+    let values: Vec<u32> = nested.iter().map(|value| value.unwrap()).collect();
+    assert_eq!(values, vec![7, 8, 9]);
+    assert_eq!(nested.iter_raw().count(), 3);
+    assert_eq!(nested.range(1..).unwrap().get::<u32>(0).unwrap(), 8);
+    assert!(nested.range(..4).is_err());
+    assert!(nested
+        .range((Bound::Excluded(2), Bound::Excluded(2)))
+        .is_err());
+
+    let mut empty = nested.range(3..).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty.iter::<u32>().count(), 0);
+    assert!(empty.get_raw(0).is_err());
+
+    assert!(middle.range(..11).is_err());
+    assert_eq!(middle.range(..).unwrap().len(), 10);
+}
+fn test_vectored_shift<H: Handles>() {
+    let vectored = || {
+        H::wrap(CountingStorage {
+            vectored: true,
+            ..Default::default()
+        })
+    };
+    let (data_source, swap) = (vectored(), vectored());
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap.clone());
+    bookworm.set_shift_memory(0);
+    for i in 0..100u32 {
+        bookworm.push(&i).unwrap();
+    }
+    let writes = data_source.access().writes;
+    bookworm.delete(0).unwrap();
+    // 99 pages staged and copied back in runs of 64
+    assert_eq!(swap.access().vectored_writes, 2);
+    assert_eq!(data_source.access().vectored_writes, 2);
+    assert_eq!(bookworm.metrics().vectored_batches, 4);
+    // only the vacated tail page is written page by page
+    assert_eq!(data_source.access().writes, writes + 1);
+    let values: Vec<u32> = bookworm.to_vec().unwrap();
+    assert_eq!(values, (1..100).collect::<Vec<u32>>());
+
+    let (data_source, swap) = (
+        H::wrap(CountingStorage::default()),
+        H::wrap(CountingStorage::default()),
+    );
+    let mut scalar = H::bookworm(16, data_source.clone(), swap.clone());
+    scalar.set_shift_memory(0);
+    for i in 0..100u32 {
+        scalar.push(&i).unwrap();
+    }
+    scalar.delete(0).unwrap();
+    // and the delete marker is set and cleared around copying them back
+    assert_eq!(swap.access().writes, 101);
+    assert_eq!(scalar.metrics().vectored_batches, 198);
+    assert_eq!(scalar.to_vec::<u32>().unwrap(), values);
+    assert_eq!(
+        data_source.access().inner.get_ref(),
+        bookworm.pager.data_source.access().inner.get_ref()
+    );
+}
+fn test_delete_with_recovery<H: Handles>() {
+    let rotten_store = || {
+        let data_source = H::wrap(CountingStorage::default());
+        let swap = H::wrap(CountingStorage::default());
+        let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+        for i in 0..6 {
+            bookworm.push(&TestData::new(i, true)).unwrap();
+        }
+        data_source.access().rotten = Some(3 * 32..3 * 32 + 1);
+        (bookworm, data_source)
+    };
+    let counts = |bookworm: &mut Bookworm<
+        CountingStorage,
+        BincodeCodec,
+        H::Of<CountingStorage>,
+    >|
+     -> Vec<u8> {
+        let records: Vec<TestData> = bookworm.to_vec().unwrap();
+        records.iter().map(|record| record.count).collect()
+    };
+
+    let (mut bookworm, data_source) = rotten_store();
+    let report = bookworm
+        .delete_with_recovery(1, |page, _| {
+            assert_eq!(page, 3);
+            RecoveryAction::SkipPage
+        })
+        .unwrap();
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].0, 3);
+    assert!(report.replaced.is_empty());
+    data_source.access().rotten = None;
+    assert_eq!(counts(&mut bookworm), vec![0, 2, 4, 5]);
+    assert!(bookworm.is_page_empty(4).unwrap());
+    assert!(bookworm.is_page_empty(5).unwrap());
+
+    let (mut bookworm, data_source) = rotten_store();
+    bookworm
+        .delete_with_recovery(1, |_, _| RecoveryAction::AbortOperation)
+        .unwrap_err();
+    assert!(!bookworm.is_poisoned());
+    data_source.access().rotten = None;
+    assert_eq!(counts(&mut bookworm), vec![0, 1, 2, 3, 4, 5]);
+
+    let (mut bookworm, data_source) = rotten_store();
+    bookworm
+        .delete_with_recovery(1, |_, _| RecoveryAction::ReplaceWith(vec![0; 33]))
+        .unwrap_err();
+    let replacement = bincode::serialize(&TestData::new(30, false)).unwrap();
+    let report = bookworm
+        .delete_with_recovery(1, |_, _| RecoveryAction::ReplaceWith(replacement.clone()))
+        .unwrap();
+    assert_eq!(report.replaced, vec![3]);
+    assert!(report.skipped.is_empty());
+    data_source.access().rotten = None;
+    assert_eq!(counts(&mut bookworm), vec![0, 2, 30, 4, 5]);
+}
+fn test_refresh<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source.clone(), H::wrap(Cursor::new(Vec::new())));
+    bookworm.push(&0u32).unwrap();
+    assert_eq!(*H::cached::<_, u32>(&mut bookworm, 0).unwrap(), 0);
+
+    // another handle appends over the same storage
+    let mut other = H::bookworm(32, data_source.clone(), H::wrap(Cursor::new(Vec::new())));
+    other.push(&1u32).unwrap();
+    other.push(&2u32).unwrap();
+    assert!(bookworm.get_page::<u32>(1).is_err());
+    assert_eq!(
+        bookworm.check_stale().unwrap(),
+        RefreshOutcome::Grew { by: 2 }
+    );
+    assert_eq!(bookworm.refresh().unwrap(), RefreshOutcome::Grew { by: 2 });
+    assert_eq!(bookworm.get_page::<u32>(2).unwrap(), 2);
+    assert_eq!(bookworm.refresh().unwrap(), RefreshOutcome::Unchanged);
+
+    data_source.access().get_mut().truncate(32);
+    assert_eq!(
+        bookworm.check_stale().unwrap(),
+        RefreshOutcome::Shrank { by: 2 }
+    );
+    assert_eq!(
+        bookworm.to_vec::<u32>().unwrap_err().to_string(),
+        "Could not read page 1: failed to fill whole buffer"
+    );
+    assert_eq!(
+        bookworm.refresh().unwrap(),
+        RefreshOutcome::Shrank { by: 2 }
+    );
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![0]);
+    bookworm.push(&3u32).unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![0, 3]);
+}
+fn test_decode_limit<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(64, data_source, swap);
+    bookworm.push(&"hello".to_string()).unwrap();
+    // a length prefix claiming a terabyte long string
+    bookworm
+        .write_at(0, 0, &(1u64 << 40).to_le_bytes())
+        .unwrap();
+    let started = std::time::Instant::now();
+    let err = bookworm.get_page::<String>(0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse data: the size limit has been reached"
+    );
+    assert!(started.elapsed() < Duration::from_secs(1));
+    bookworm
+        .write_at(0, 0, &(1u64 << 40).to_le_bytes())
+        .unwrap();
+    assert!(bookworm.get_page::<Vec<u64>>(0).is_err());
+    assert!(bookworm.to_vec::<String>().is_err());
+
+    bookworm.push(&vec![7u8; 40]).unwrap();
+    assert_eq!(bookworm.get_page::<Vec<u8>>(1).unwrap(), vec![7u8; 40]);
+    bookworm.set_decode_limit(16);
+    assert!(bookworm.get_page::<Vec<u8>>(1).is_err());
+    bookworm.set_decode_limit(1 << 20);
+    assert_eq!(bookworm.get_page::<Vec<u8>>(1).unwrap(), vec![7u8; 40]);
+}
+#[test]
+fn test_page_guard() {
+    let data_source = Rc::new(RefCell::new(CountingStorage::default()));
+    let swap = Rc::new(RefCell::new(CountingStorage::default()));
+    let mut bookworm = Bookworm::new(16, data_source.clone(), swap);
+    bookworm.push(&TestData::new(1, false)).unwrap();
+    bookworm.push(&vec![1u8, 2]).unwrap();
+
+    bookworm.get_page_cached::<TestData>(0).unwrap();
+    let mut guard = bookworm.get_mut::<TestData>(0).unwrap();
+    guard.count += 1;
+    guard.commit().unwrap();
+    assert_eq!(
+        *bookworm.get_page_cached::<TestData>(0).unwrap(),
+        TestData::new(2, false)
+    );
+
+    bookworm.get_mut::<TestData>(0).unwrap().signed = true;
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(2, true)
+    );
+
+    let writes = data_source.borrow().writes;
+    let guard = bookworm.get_mut::<TestData>(0).unwrap();
+    assert_eq!(guard.count, 2);
+    drop(guard);
+    bookworm.get_mut::<TestData>(0).unwrap().commit().unwrap();
+    assert_eq!(data_source.borrow().writes, writes);
+
+    let mut guard = bookworm.get_mut::<Vec<u8>>(1).unwrap();
+    guard.extend([0; 8]);
+    assert!(guard.commit().is_err());
+    assert!(!bookworm.is_poisoned());
+    assert_eq!(bookworm.get_page::<Vec<u8>>(1).unwrap(), vec![1, 2]);
+    bookworm.get_mut::<Vec<u8>>(1).unwrap().extend([0; 8]);
+    assert!(bookworm.is_poisoned());
+}
+#[cfg(unix)]
+#[test]
+fn test_parallel_load() {
+    let records: Vec<TestData> = (0..=250).map(|i| TestData::new(i, i % 3 == 0)).collect();
+    let mut sequential = Bookworm::new(
+        24,
+        Rc::new(RefCell::new(Cursor::new(Vec::new()))),
+        Rc::new(RefCell::new(Cursor::new(Vec::new()))),
+    );
+    for record in &records {
+        sequential.push(record).unwrap();
+    }
+    let expected = sequential.into_inner().0.borrow().get_ref().clone();
+
+    let dir = std::env::temp_dir();
+    for threads in [1, 2, 7] {
+        let path = dir.join(format!(
+            "bookworm-parallel-{}-{}",
+            std::process::id(),
+            threads
+        ));
+        let reloaded = records
+            .iter()
+            .map(|record| TestData::new(record.count, record.signed))
+            .collect();
+        let mut bookworm = parallel_load(&path, 24, reloaded, threads).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+        assert_eq!(bookworm.to_vec::<TestData>().unwrap(), records);
+        bookworm.push(&TestData::new(1, true)).unwrap();
+        drop(bookworm);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(format!("{}.swap", path.display())).unwrap();
+    }
+
+    let path = dir.join(format!(
+        "bookworm-parallel-{}-oversized",
+        std::process::id()
+    ));
+    let err = parallel_load(&path, 16, vec![vec![0u8; 2], vec![0u8; 9]], 2)
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("partially written"));
+    assert!(err.to_string().contains("page 1"));
+    std::fs::remove_file(&path).unwrap();
+
+    // the io error is kept as the source rather than formatted into the message
+    let path = dir.join(format!("bookworm-parallel-{}-missing", std::process::id()));
+    let err = parallel_load(path.join("data"), 16, vec![0u8], 1)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), error::ErrorKind::Io);
+    assert!(err
+        .to_string()
+        .starts_with(&format!("Could not open {}: ", path.join("data").display())));
+    let source = std::error::Error::source(&err)
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .unwrap();
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+}
+fn test_manifest<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    assert_eq!(DigestAlgorithm::Crc32.digest(b"123456789"), 0xCBF4_3926);
+    for algorithm in [DigestAlgorithm::Crc32, DigestAlgorithm::Fnv1a64] {
+        let manifest = bookworm.build_manifest(algorithm).unwrap();
+        assert_eq!(manifest.pages.len(), 5);
+        assert!(bookworm.verify_manifest(&manifest).unwrap().is_clean());
+
+        let mut file = Vec::new();
+        manifest.write_to(&mut file).unwrap();
+        assert_eq!(file.len(), 22 + 5 * 8);
+        let read = Manifest::read_from(file.as_slice()).unwrap();
+        assert_eq!(read, manifest);
+        let mut rewritten = Vec::new();
+        read.write_to(&mut rewritten).unwrap();
+        assert_eq!(rewritten, file);
+        file[30] ^= 1;
+        assert!(Manifest::read_from(file.as_slice()).is_err());
+    }
+
+    let manifest = bookworm.build_manifest(DigestAlgorithm::Crc32).unwrap();
+    bookworm.write_at(3, 20, &[1]).unwrap();
+    let diff = bookworm.verify_manifest(&manifest).unwrap();
+    assert_eq!(diff.modified, vec![3]);
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+
+    bookworm.push(&TestData::new(9, true)).unwrap();
+    assert_eq!(bookworm.verify_manifest(&manifest).unwrap().added, vec![5]);
+    bookworm.pop().unwrap();
+    bookworm.pop().unwrap();
+    let diff = bookworm.verify_manifest(&manifest).unwrap();
+    assert_eq!(diff.removed, vec![4]);
+    assert_eq!(diff.modified, vec![3]);
+}
+fn test_insert<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.insert(0, &TestData::new(2, false)).unwrap();
+    bookworm.push(&TestData::new(4, false)).unwrap();
+    bookworm.insert(0, &TestData::new(0, false)).unwrap();
+    bookworm.insert(1, &TestData::new(1, false)).unwrap();
+    bookworm.insert(3, &TestData::new(3, false)).unwrap();
+    assert_eq!(bookworm.swap_len(), 0);
+    bookworm.insert(5, &TestData::new(5, false)).unwrap();
+    let err = bookworm.insert(7, &TestData::new(7, false)).unwrap_err();
+    assert!(err.to_string().contains("page 7"));
+    bookworm.delete(2).unwrap();
+    bookworm.insert(2, &TestData::new(20, true)).unwrap();
+    bookworm.push(&TestData::new(6, false)).unwrap();
+    bookworm.delete(0).unwrap();
+    assert!(bookworm
+        .insert(1, &"too long for a single page of 32 bytes")
+        .is_err());
+
+    let counts: Vec<u8> = bookworm
+        .into_iter::<TestData>()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![1, 20, 3, 4, 5, 6]);
+}
+fn test_set<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..3 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    assert_eq!(H::cached::<_, TestData>(&mut bookworm, 1).unwrap().count, 1);
+    bookworm.set(1, &TestData::new(10, true)).unwrap();
+    assert_eq!(
+        bookworm.get_page::<TestData>(1).unwrap(),
+        TestData::new(10, true)
+    );
+    assert_eq!(
+        H::cached::<_, TestData>(&mut bookworm, 1).unwrap().count,
+        10
+    );
+
+    let err = bookworm.set(3, &TestData::new(3, false)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Page 3 is out of range: only 3 pages exist"
+    );
+    let err = bookworm.set(1, &vec![0u8; 32]).unwrap_err();
+    assert!(!err.to_string().contains("out of range"));
+    assert!(bookworm.set_raw(0, &[0; 33]).is_err());
+    assert!(bookworm
+        .set_raw(5, &[0])
+        .unwrap_err()
+        .to_string()
+        .contains("Page 5"));
+    bookworm.set_raw(2, &[2, 1]).unwrap();
+    assert_eq!(bookworm.get_raw_page(2).unwrap(), {
+        let mut page = vec![0; 32];
+        page[..2].copy_from_slice(&[2, 1]);
+        page
+    });
+
+    let records: Vec<TestData> = bookworm.into_iter().collect();
+    assert_eq!(
+        records,
+        vec![
+            TestData::new(0, false),
+            TestData::new(10, true),
+            TestData::new(2, true)
+        ]
+    );
+}
+fn test_stats<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    bookworm.set_shift_memory(0);
+    assert!(bookworm.is_empty());
+    assert_eq!(bookworm.page_size(), 32);
+    assert_eq!(bookworm.storage_bytes().unwrap(), 0);
+    for i in 0..4 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    assert_eq!(bookworm.len(), 4);
+    assert!(!bookworm.is_empty());
+    data_source.access().set_position(40);
+    assert_eq!(bookworm.storage_bytes().unwrap(), 128);
+    assert_eq!(data_source.access().position(), 40);
+
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.len(), 3);
+    assert_eq!(bookworm.storage_bytes().unwrap(), 128);
+    bookworm.delete(0).unwrap();
+    assert_eq!(
+        bookworm.stats().unwrap(),
+        BookwormStats {
+            pages: 2,
+            page_size: 32,
+            storage_bytes: 128,
+            swap_pages: 0,
+            swap_bytes: DELETE_MARKER_BYTES + 64,
+        }
+    );
+    assert_eq!(bookworm.get_page::<TestData>(1).unwrap().count, 2);
+}
+fn test_swap_pages<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    let counts = |bookworm: &mut Over<H>| -> Vec<u8> {
+        (0..bookworm.len())
+            .map(|page| bookworm.get_page::<TestData>(page).unwrap().count)
+            .collect()
+    };
+    bookworm.swap_pages(1, 2).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![0, 2, 1, 3, 4]);
+    bookworm.swap_pages(4, 0).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![4, 2, 1, 3, 0]);
+    bookworm.swap_pages(3, 3).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![4, 2, 1, 3, 0]);
+
+    let before = bookworm.to_raw_vec().unwrap();
+    assert!(bookworm
+        .swap_pages(0, 5)
+        .unwrap_err()
+        .to_string()
+        .contains("Page 5"));
+    assert!(bookworm.swap_pages(9, 1).is_err());
+    assert_eq!(bookworm.to_raw_vec().unwrap(), before);
+}
+fn test_truncate<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap.clone());
+    for i in 0..10 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    bookworm.truncate(10).unwrap();
+    bookworm.truncate(20).unwrap();
+    assert_eq!(bookworm.len(), 10);
+
+    let (seeks, writes) = data_source.with(|storage| (storage.seeks, storage.writes));
+    bookworm.truncate(4).unwrap();
+    assert_eq!(data_source.access().seeks, seeks + 1);
+    assert_eq!(data_source.access().writes, writes + 1);
+    assert_eq!(bookworm.len(), 4);
+    assert!(bookworm.is_page_empty(9).unwrap());
+    assert_eq!(swap.access().writes, 0);
+    bookworm.push(&TestData::new(40, true)).unwrap();
+    assert_eq!(
+        bookworm.get_page::<TestData>(4).unwrap(),
+        TestData::new(40, true)
+    );
+
+    bookworm.truncate(0).unwrap();
+    assert!(bookworm.is_empty());
+    assert!(bookworm.to_vec::<TestData>().unwrap().is_empty());
+    assert!(bookworm.is_page_empty(0).unwrap());
+    bookworm.push(&TestData::new(1, true)).unwrap();
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(1, true)
+    );
+    assert_eq!(data_source.access().inner.get_ref().len(), 320);
+}
+fn test_clear<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap.clone());
+    bookworm.set_shift_memory(0);
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    bookworm.delete(0).unwrap();
+    assert!(!swap.access().get_ref().is_empty());
+    bookworm.clear().unwrap();
+    assert!(bookworm.is_empty());
+    assert!(data_source.access().get_ref().is_empty());
+    assert!(swap.access().get_ref().is_empty());
+    bookworm.push(&TestData::new(7, true)).unwrap();
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(7, true)
+    );
+    bookworm.clear().unwrap();
+    drop(bookworm);
+
+    let mut reopened = H::bookworm(32, data_source, swap);
+    assert!(reopened.is_empty());
+    assert!(reopened.get_page::<TestData>(0).is_err());
+}
+fn test_get_raw_page_into<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..3 {
+        bookworm.push(&TestData::new(i, i % 2 == 0)).unwrap();
+    }
+    let mut buf = vec![0xFF; 40];
+    for page in 0..3 {
+        assert_eq!(bookworm.get_raw_page_into(page, &mut buf).unwrap(), 32);
+        assert_eq!(buf[..32], bookworm.get_raw_page(page).unwrap()[..]);
+    }
+    assert_eq!(buf[32..], [0xFF; 8]);
+    assert!(bookworm.get_raw_page_into(3, &mut buf).is_err());
+    let err = bookworm.get_raw_page_into(0, &mut [0; 31]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not read page: buffer holds 31 bytes but pages are 32 bytes long"
+    );
+}
+fn test_push_all<H: Handles>() {
+    let records: Vec<Vec<u8>> = (0..150u8).map(|i| vec![i; (i % 9) as usize]).collect();
+    let pushed = H::wrap(Cursor::new(Vec::new()));
+    let mut one_by_one = H::bookworm(24, pushed.clone(), H::wrap(Cursor::new(Vec::new())));
+    for record in &records {
+        one_by_one.push(record).unwrap();
+    }
+
+    let data_source = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(24, data_source.clone(), H::wrap(CountingStorage::default()));
+    bookworm.push(&records[0]).unwrap();
+    data_source.access().seeks = 0;
+    assert_eq!(bookworm.push_all(&records[1..]).unwrap(), 149);
+    assert_eq!(data_source.access().seeks, 1);
+    assert_eq!(bookworm.len(), 150);
+    assert_eq!(
+        data_source.access().inner.get_ref(),
+        pushed.access().get_ref()
+    );
+    assert_eq!(bookworm.push_all(Vec::<u8>::new()).unwrap(), 0);
+
+    let oversized = vec![vec![1u8], vec![2u8; 3], vec![3u8; 17], vec![4u8]];
+    let err = bookworm.push_all(oversized).unwrap_err();
+    assert!(err.to_string().contains("page 152"));
+    assert_eq!(bookworm.len(), 152);
+    assert_eq!(data_source.access().inner.get_ref().len(), 152 * 24);
+    assert_eq!(bookworm.get_page::<Vec<u8>>(151).unwrap(), vec![2u8; 3]);
+}
+fn test_move_page<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source, swap.clone());
+    for i in 0..8 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    let counts = |bookworm: &mut Bookworm<
+        CountingStorage,
+        BincodeCodec,
+        H::Of<CountingStorage>,
+    >|
+     -> Vec<u8> {
+        bookworm
+            .pages()
+            .iter::<TestData>()
+            .map(|record| record.unwrap().count)
+            .collect()
+    };
+    let staged = swap.access().writes;
+    bookworm.move_page(1, 4).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![0, 2, 3, 4, 1, 5, 6, 7]);
+    assert_eq!(swap.access().writes, staged + 3);
+    bookworm.move_page(6, 0).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![6, 0, 2, 3, 4, 1, 5, 7]);
+    bookworm.move_page(0, 7).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![0, 2, 3, 4, 1, 5, 7, 6]);
+    bookworm.move_page(3, 3).unwrap();
+    assert_eq!(bookworm.swap_len(), 0);
+
+    let before = bookworm.to_raw_vec().unwrap();
+    assert!(bookworm.move_page(8, 0).is_err());
+    assert!(bookworm.move_page(0, 8).is_err());
+    assert_eq!(bookworm.to_raw_vec().unwrap(), before);
+    assert!(!bookworm.is_poisoned());
+}
+fn test_borrowing_iter<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..4 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    bookworm.pop().unwrap();
+    let counts: Vec<u8> = bookworm
+        .iter::<TestData>(0)
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![0, 1, 2]);
+
+    bookworm.push(&TestData::new(9, true)).unwrap();
+    let mut iter = bookworm.iter::<TestData>(2);
+    assert_eq!(iter.next(), Some(TestData::new(2, false)));
+    assert_eq!(iter.next(), Some(TestData::new(9, true)));
+    assert_eq!(iter.next(), None);
+    assert_eq!(bookworm.iter::<TestData>(4).count(), 0);
+}
+fn test_borrowing_raw_iter<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    bookworm.push_raw(&[1]).unwrap();
+    bookworm.push_raw(&[2, 2]).unwrap();
+    bookworm.push_raw(&[3, 3, 3]).unwrap();
+    bookworm.pop().unwrap();
+    let pages: Vec<Vec<u8>> = bookworm.raw_iter(0).collect();
+    assert_eq!(pages, vec![vec![1, 0, 0, 0], vec![2, 2, 0, 0]]);
+    assert!(bookworm.push_raw(&[0; 5]).is_err());
+
+    bookworm.push_raw(&[4, 4, 4, 4]).unwrap();
+    let pages: Vec<Vec<u8>> = bookworm.raw_iter(1).collect();
+    assert_eq!(pages, vec![vec![2, 2, 0, 0], vec![4, 4, 4, 4]]);
+    assert_eq!(bookworm.raw_iter(3).count(), 0);
+    assert_eq!(bookworm.raw_iter(10).count(), 0);
+}
+fn test_double_ended_iterators<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    for i in 0..5u8 {
+        bookworm.push(&i).unwrap();
+    }
+    bookworm.pop().unwrap();
+    let reversed: Vec<u8> = bookworm.iter(0).rev().collect();
+    assert_eq!(reversed, vec![3, 2, 1, 0]);
+    let reversed: Vec<u8> = bookworm.raw_iter(1).rev().map(|page| page[0]).collect();
+    assert_eq!(reversed, vec![3, 2, 1]);
+
+    let mut iter = bookworm.iter::<u8>(0);
+    assert_eq!(iter.next_back(), Some(3));
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next_back(), Some(2));
+    assert_eq!(iter.next(), Some(1));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+
+    let mut iter = bookworm.into_iter::<u8>();
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next_back(), Some(3));
+    assert_eq!(iter.next(), Some(1));
+    assert_eq!(iter.next(), Some(2));
+    assert_eq!(iter.next_back(), None);
+    assert_eq!(iter.next(), None);
+    drop(iter);
+
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap.clone());
+    assert_eq!(bookworm.iter::<u8>(0).next_back(), None);
+    assert_eq!(bookworm.raw_iter(0).next_back(), None);
+    assert_eq!(bookworm.into_raw_iter().next_back(), None);
+
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    for i in 0..3u8 {
+        bookworm.push(&i).unwrap();
+    }
+    let pages: Vec<u8> = bookworm.into_raw_iter().rev().map(|page| page[0]).collect();
+    assert_eq!(pages, vec![2, 1, 0]);
+}
+#[test]
+fn test_iterator_len() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(4, data_source, swap);
+    for i in 0..6u8 {
+        bookworm.push(&i).unwrap();
+    }
+    let mut iter = bookworm.iter::<u8>(2);
+    assert_eq!(iter.len(), 4);
+    iter.next();
+    iter.next_back();
+    assert_eq!(iter.size_hint(), (2, Some(2)));
+    assert_eq!(bookworm.iter::<u8>(9).len(), 0);
+    let mut raw = bookworm.raw_iter(0);
+    raw.nth(2);
+    assert_eq!(raw.len(), 3);
+
+    let expected: Vec<u8> = bookworm.iter(1).collect();
+    let mut preallocated = Vec::with_capacity(5);
+    preallocated.extend(bookworm.iter::<u8>(1));
+    assert_eq!(preallocated, expected);
+
+    let mut iter = bookworm.into_iter::<u8>();
+    assert_eq!(iter.len(), 6);
+    iter.next();
+    iter.next_back();
+    assert_eq!(iter.len(), 4);
+    let rest: Vec<u8> = iter.collect();
+    assert_eq!(rest, vec![1, 2, 3, 4]);
+}
+fn test_iterator_nth_seeks<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(8, data_source.clone(), swap);
+    bookworm.push_all(0..50_000u32).unwrap();
+    assert_eq!(bookworm.iter::<u32>(0).nth(31_337), Some(31_337));
+    assert_eq!(
+        bookworm.raw_iter(10).nth(5).unwrap()[..4],
+        15u32.to_le_bytes()
+    );
+    let mut naive = bookworm.iter::<u32>(0);
+    for _ in 0..1234 {
+        naive.next();
+    }
+    assert_eq!(naive.next(), Some(1234));
+
+    let mut iter = bookworm.into_iter::<u32>();
+    let (seeks, bytes_read) = data_source.with(|storage| (storage.seeks, storage.bytes_read));
+    assert_eq!(iter.nth(40_000), Some(40_000));
+    assert_eq!(data_source.access().seeks, seeks + 1);
+    assert_eq!(data_source.access().bytes_read, bytes_read + 8);
+    assert_eq!(iter.next(), Some(40_001));
+    assert_eq!(data_source.access().seeks, seeks + 1);
+    assert_eq!(iter.nth(1), Some(40_003));
+    assert_eq!(data_source.access().seeks, seeks + 2);
+    assert_eq!(iter.nth(9_996), None);
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+}
+fn test_into_iterator_for_borrow<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    for i in 0..3u8 {
+        bookworm.push(&i).unwrap();
+    }
+    bookworm.pop().unwrap();
+    let mut firsts = Vec::new();
+    for page in &mut bookworm {
+        firsts.push(page[0]);
+    }
+    assert_eq!(firsts, vec![0, 1]);
+    bookworm.push(&7u8).unwrap();
+
+    let mut records = Vec::new();
+    for record in bookworm.typed::<u8>() {
+        records.push(record);
+    }
+    assert_eq!(records, vec![0, 1, 7]);
+    bookworm.push(&8u8).unwrap();
+    assert_eq!(bookworm.typed::<u8>().into_iter().last(), Some(8));
+}
+#[test]
+fn test_from_iter() {
+    let records: Vec<TestData> = (0..200).map(|i| TestData::new(i, i % 2 == 1)).collect();
+    let pushed = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut one_by_one = Bookworm::new(
+        16,
+        pushed.clone(),
+        Rc::new(RefCell::new(Cursor::new(Vec::new()))),
+    );
+    for record in &records {
+        one_by_one.push(record).unwrap();
+    }
+
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm =
+        Bookworm::from_iter(16, data_source.clone(), swap.clone(), &records).unwrap();
+    assert_eq!(bookworm.len(), 200);
+    assert_eq!(data_source.borrow().get_ref(), pushed.borrow().get_ref());
+    assert_eq!(bookworm.to_vec::<TestData>().unwrap(), records);
+    drop(bookworm);
+    assert!(Bookworm::from_iter(16, data_source, swap, &records).is_err());
+
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let items = (0..100).map(|i| vec![0u8; if i == 70 { 9 } else { 8 }]);
+    let err = Bookworm::from_iter(16, data_source.clone(), swap.clone(), items)
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("page 70"));
+    assert!(data_source.borrow().get_ref().is_empty());
+    assert!(Bookworm::new(16, data_source, swap).is_empty());
+}
+fn test_iter_range<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap);
+    bookworm.push_all(0..6u8).unwrap();
+    bookworm.pop().unwrap();
+    let collect = |bookworm: &mut Over<H, CountingStorage>, range: (Bound<usize>, Bound<usize>)| {
+        bookworm.iter_range::<u8>(range).collect::<Vec<u8>>()
+    };
+    assert_eq!(
+        bookworm.iter_range::<u8>(..).collect::<Vec<_>>(),
+        vec![0, 1, 2, 3, 4]
+    );
+    assert_eq!(
+        bookworm.iter_range::<u8>(2..).collect::<Vec<_>>(),
+        vec![2, 3, 4]
+    );
+    assert_eq!(
+        bookworm.iter_range::<u8>(..3).collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+    assert_eq!(
+        bookworm.iter_range::<u8>(1..=3).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        bookworm.iter_range::<u8>(3..100).rev().collect::<Vec<_>>(),
+        vec![4, 3]
+    );
+    assert_eq!(bookworm.iter_range::<u8>(7..9).count(), 0);
+    assert_eq!(
+        collect(&mut bookworm, (Bound::Excluded(3), Bound::Excluded(3))),
+        Vec::<u8>::new()
+    );
+    assert_eq!(bookworm.raw_iter_range(1..3).len(), 2);
+
+    let seeks = data_source.access().seeks;
+    let pages: Vec<Vec<u8>> = bookworm.raw_iter_range(1..).collect();
+    assert_eq!(pages.len(), 4);
+    assert_eq!(data_source.access().seeks, seeks + 1);
+    bookworm.push(&9u8).unwrap();
+    assert_eq!(
+        bookworm.iter_range::<u8>(4..).collect::<Vec<_>>(),
+        vec![4, 9]
+    );
+}
+fn test_first_and_last<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    assert_eq!(bookworm.first::<u8>().unwrap(), None);
+    assert_eq!(bookworm.last::<u8>().unwrap(), None);
+    assert_eq!(bookworm.last_raw().unwrap(), None);
+
+    bookworm.push(&1u8).unwrap();
+    assert_eq!(bookworm.first::<u8>().unwrap(), Some(1));
+    assert_eq!(bookworm.last::<u8>().unwrap(), Some(1));
+    bookworm.push(&2u8).unwrap();
+    bookworm.push(&3u8).unwrap();
+    assert_eq!(bookworm.last::<u8>().unwrap(), Some(3));
+    assert_eq!(bookworm.last_raw().unwrap(), Some(vec![3, 0, 0, 0]));
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.last::<u8>().unwrap(), Some(2));
+    bookworm.delete(0).unwrap();
+    assert_eq!(bookworm.first::<u8>().unwrap(), Some(2));
+    assert_eq!(bookworm.first_raw().unwrap(), Some(vec![2, 0, 0, 0]));
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.first::<u8>().unwrap(), None);
+}
+fn test_find_page<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    for i in 0..6 {
+        bookworm.push(&TestData::new(i, i % 2 == 0)).unwrap();
+    }
+    assert_eq!(
+        bookworm
+            .find_page(|record: &TestData| record.signed)
+            .unwrap(),
+        Some((0, TestData::new(0, true)))
+    );
+    assert_eq!(
+        bookworm
+            .rfind_page(|record: &TestData| record.signed)
+            .unwrap(),
+        Some((4, TestData::new(4, true)))
+    );
+    assert_eq!(
+        bookworm
+            .find_page(|record: &TestData| record.count == 5)
+            .unwrap(),
+        Some((5, TestData::new(5, false)))
+    );
+    assert_eq!(
+        bookworm
+            .find_page(|record: &TestData| record.count > 10)
+            .unwrap(),
+        None
+    );
+
+    bookworm.write_at(2, 1, &[2]).unwrap();
+    let err = bookworm
+        .find_page(|record: &TestData| record.count == 5)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse page 2: invalid u8 while decoding bool, expected 0 or 1, found 2"
+    );
+    assert_eq!(
+        bookworm
+            .rfind_page(|record: &TestData| record.count == 3)
+            .unwrap(),
+        Some((3, TestData::new(3, false)))
+    );
+}
+fn test_binary_search_by<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(8, data_source, swap);
+    let keys: Vec<u32> = (0..3000).map(|i| i * 3 + 1).collect();
+    let records: Vec<(u32, bool)> = keys.iter().map(|&key| (key, false)).collect();
+    bookworm.push_all(&records).unwrap();
+
+    assert_eq!(
+        bookworm
+            .binary_search_by(|(key, _): &(u32, bool)| key.cmp(&1))
+            .unwrap(),
+        Ok(0)
+    );
+    assert_eq!(
+        bookworm
+            .binary_search_by(|(key, _): &(u32, bool)| key.cmp(&8998))
+            .unwrap(),
+        Ok(2999)
+    );
+    assert_eq!(
+        bookworm
+            .binary_search_by(|(key, _): &(u32, bool)| key.cmp(&0))
+            .unwrap(),
+        Err(0)
+    );
+    assert_eq!(
+        bookworm
+            .binary_search_by(|(key, _): &(u32, bool)| key.cmp(&9000))
+            .unwrap(),
+        Err(3000)
+    );
+    for target in (0..9010).step_by(7) {
+        let found = bookworm
+            .binary_search_by(|(key, _): &(u32, bool)| key.cmp(&target))
+            .unwrap();
+        assert_eq!(found, keys.binary_search(&target));
+    }
+
+    // the second probe when looking for a low key
+    bookworm.write_at(750, 4, &[2]).unwrap();
+    let err = bookworm
+        .binary_search_by(|(key, _): &(u32, bool)| key.cmp(&5))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse page 750: invalid u8 while decoding bool, expected 0 or 1, found 2"
+    );
+}
+fn test_sort_by_key<H: Handles>() {
+    // 25 runs take three merge passes, 8 runs take two and get copied back from the swap
+    for records_count in [100u32, 30, 3] {
+        let data_source = H::wrap(Cursor::new(Vec::new()));
+        let swap = H::wrap(Cursor::new(Vec::new()));
+        let mut bookworm = H::bookworm(16, data_source, swap);
+        bookworm.set_sort_memory(4);
+        let records: Vec<(u32, u32)> = (0..records_count).map(|i| ((i * 37) % 11, i)).collect();
+        bookworm.push_all(&records).unwrap();
+
+        bookworm.sort_by_key(|(key, _): &(u32, u32)| *key).unwrap();
+        let mut expected = records.clone();
+        expected.sort_by_key(|(key, _)| *key);
+        let sorted: Vec<(u32, u32)> = bookworm.typed::<(u32, u32)>().into_iter().collect();
+        assert_eq!(sorted, expected);
+        assert_eq!(bookworm.len(), records_count as usize);
+        assert_eq!(bookworm.swap_len(), 0);
+        assert!(!bookworm.is_poisoned());
+    }
+}
+
+fn test_sort_by_key_corrupt_page<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    bookworm.set_sort_memory(4);
+    for i in 0..10u8 {
+        bookworm.push(&TestData::new(10 - i, false)).unwrap();
+    }
+    bookworm.write_at(6, 1, &[2]).unwrap();
+
+    let err = bookworm
+        .sort_by_key(|record: &TestData| record.count)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse page 6: invalid u8 while decoding bool, expected 0 or 1, found 2"
+    );
+    assert!(!bookworm.is_poisoned());
+    assert_eq!(bookworm.swap_len(), 0);
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(10, false)
+    );
+}
+fn test_retain<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap);
+    for i in 0..10 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+
+    assert_eq!(bookworm.retain(|_: &TestData| true).unwrap(), 0);
+    assert_eq!(bookworm.len(), 10);
+
+    let removed = bookworm
+        .retain(|record: &TestData| record.count.is_multiple_of(2))
+        .unwrap();
+    assert_eq!(removed, 5);
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![0, 2, 4, 6, 8]);
+    assert!(data_source.access().get_ref()[5 * 4..]
+        .iter()
+        .all(|byte| *byte == 0));
+
+    assert_eq!(bookworm.retain(|_: &TestData| false).unwrap(), 5);
+    assert!(bookworm.is_empty());
+    assert_eq!(bookworm.typed::<TestData>().into_iter().count(), 0);
+    assert!(data_source.access().get_ref().iter().all(|byte| *byte == 0));
+}
+
+fn test_retain_panic_is_restartable<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    for i in 0..10 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+
+    // pages 1 and 3 were already removed when the predicate panics on page 5
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        bookworm.retain(|record: &TestData| {
+            if record.count == 5 {
+                panic!("predicate panicked");
+            }
+            record.count.is_multiple_of(2)
+        })
+    }));
+    assert!(result.is_err());
+    assert!(!bookworm.is_poisoned());
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![0, 2, 4, 5, 6, 7, 8, 9]);
+
+    let removed = bookworm
+        .retain(|record: &TestData| record.count.is_multiple_of(2))
+        .unwrap();
+    assert_eq!(removed, 3);
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![0, 2, 4, 6, 8]);
+
+    bookworm.write_at(3, 1, &[2]).unwrap();
+    let err = bookworm
+        .retain(|record: &TestData| record.count != 2)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse page 3: invalid u8 while decoding bool, expected 0 or 1, found 2"
+    );
+    assert!(!bookworm.is_poisoned());
+    assert_eq!(bookworm.len(), 4);
+    assert_eq!(
+        bookworm.get_page::<TestData>(1).unwrap(),
+        TestData::new(4, false)
+    );
+}
+fn test_dedup<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap);
+    let counts = [1u8, 1, 1, 2, 3, 3, 3, 3, 4, 1, 5, 5];
+    for count in counts {
+        bookworm.push(&TestData::new(count, false)).unwrap();
+    }
+
+    assert_eq!(bookworm.dedup_by_raw().unwrap(), 6);
+    let deduped: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(deduped, vec![1, 2, 3, 4, 1, 5]);
+    assert!(data_source.access().get_ref()[6 * 4..]
+        .iter()
+        .all(|byte| *byte == 0));
+    assert_eq!(bookworm.dedup_by_raw().unwrap(), 0);
+
+    // equal counts but different flags are not byte identical
+    bookworm.push(&TestData::new(5, true)).unwrap();
+    assert_eq!(bookworm.dedup_by_raw().unwrap(), 0);
+    let removed = bookworm
+        .dedup_by(|record: &TestData, previous: &TestData| {
+            record.count.abs_diff(previous.count) <= 1
+        })
+        .unwrap();
+    assert_eq!(removed, 3);
+    let deduped: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(deduped, vec![1, 3, 1, 5]);
+    assert_eq!(bookworm.len(), 4);
+}
+fn test_drain<H: Handles>() {
+    let new_bookworm = || {
+        let data_source = H::wrap(Cursor::new(Vec::new()));
+        let swap = H::wrap(Cursor::new(Vec::new()));
+        let mut bookworm = H::bookworm(4, data_source.clone(), swap);
+        for i in 0..8 {
+            bookworm.push(&TestData::new(i, false)).unwrap();
+        }
+        (bookworm, data_source)
+    };
+    let counts = |bookworm: &mut Over<H>| -> Vec<u8> {
+        bookworm
+            .typed::<TestData>()
+            .into_iter()
+            .map(|record| record.count)
+            .collect()
+    };
+
+    let (mut bookworm, data_source) = new_bookworm();
+    let drained: Vec<u8> = bookworm
+        .drain::<TestData>(2..5)
+        .unwrap()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(drained, vec![2, 3, 4]);
+    assert_eq!(counts(&mut bookworm), vec![0, 1, 5, 6, 7]);
+    assert!(data_source.access().get_ref()[5 * 4..]
+        .iter()
+        .all(|byte| *byte == 0));
+    assert_eq!(bookworm.swap_len(), 0);
+
+    let (mut bookworm, _) = new_bookworm();
+    let mut drain = bookworm.drain::<TestData>(1..4).unwrap();
+    assert_eq!(drain.len(), 3);
+    assert_eq!(drain.next(), Some(TestData::new(1, false)));
+    drop(drain);
+    assert_eq!(counts(&mut bookworm), vec![0, 4, 5, 6, 7]);
+
+    let (mut bookworm, _) = new_bookworm();
+    assert_eq!(bookworm.drain::<TestData>(3..3).unwrap().count(), 0);
+    assert_eq!(bookworm.len(), 8);
+
+    let (mut bookworm, _) = new_bookworm();
+    let drained: Vec<u8> = bookworm
+        .drain::<TestData>(6..20)
+        .unwrap()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(drained, vec![6, 7]);
+    assert_eq!(counts(&mut bookworm), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(bookworm.drain::<TestData>(6..8).unwrap().len(), 0);
+    assert!(bookworm.drain::<TestData>(7..8).is_err());
+}
+fn test_splice<H: Handles>() {
+    let new_bookworm = || {
+        let data_source = H::wrap(Cursor::new(Vec::new()));
+        let swap = H::wrap(Cursor::new(Vec::new()));
+        let mut bookworm = H::bookworm(4, data_source.clone(), swap);
+        for i in 0..6 {
+            bookworm.push(&TestData::new(i, false)).unwrap();
+        }
+        (bookworm, data_source)
+    };
+    let counts = |bookworm: Over<H>| -> Vec<u8> {
+        bookworm
+            .into_iter::<TestData>()
+            .map(|record| record.count)
+            .collect()
+    };
+    let replacement = |counts: &[u8]| -> Vec<TestData> {
+        counts
+            .iter()
+            .map(|count| TestData::new(*count, true))
+            .collect()
+    };
+
+    let (mut bookworm, _) = new_bookworm();
+    bookworm.splice(2..3, replacement(&[20, 21, 22])).unwrap();
+    assert_eq!(bookworm.len(), 8);
+    assert_eq!(counts(bookworm), vec![0, 1, 20, 21, 22, 3, 4, 5]);
+
+    let (mut bookworm, data_source) = new_bookworm();
+    bookworm.splice(1..5, replacement(&[10])).unwrap();
+    assert_eq!(bookworm.len(), 3);
+    assert!(data_source.access().get_ref()[3 * 4..]
+        .iter()
+        .all(|byte| *byte == 0));
+    assert_eq!(counts(bookworm), vec![0, 10, 5]);
+
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(4, data_source, swap.clone());
+    for i in 0..6 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    bookworm.splice(3..5, replacement(&[30, 31])).unwrap();
+    assert_eq!(swap.access().writes, 0);
+    assert_eq!(
+        bookworm.to_vec::<TestData>().unwrap()[3..5],
+        replacement(&[30, 31])
+    );
+
+    let (mut bookworm, _) = new_bookworm();
+    bookworm.splice(6..6, replacement(&[60])).unwrap();
+    bookworm.splice(0..2, Vec::<TestData>::new()).unwrap();
+    let err = bookworm
+        .splice(1..2, vec![vec![0u8; 8], vec![0u8; 1]])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not write page 1: Could not write data to page: data is bigger than page"
+    );
+    bookworm.splice(3..9, replacement(&[1])).unwrap_err();
+    assert_eq!(counts(bookworm), vec![2, 3, 4, 5, 60]);
+}
+#[test]
+fn test_append() {
+    let new_bookworm = |counts: &[u8]| {
+        let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+        let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+        let mut bookworm = Bookworm::new(4, data_source, swap);
+        for count in counts {
+            bookworm.push(&TestData::new(*count, false)).unwrap();
+        }
+        bookworm
+    };
+    let mut bookworm = new_bookworm(&[1, 2]);
+    let mut other = new_bookworm(&[3, 4, 5]);
+
+    assert_eq!(bookworm.append(&mut other).unwrap(), 3);
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![1, 2, 3, 4, 5]);
+    assert_eq!(other.len(), 3);
+    assert_eq!(
+        other.get_page::<TestData>(0).unwrap(),
+        TestData::new(3, false)
+    );
+    assert_eq!(bookworm.append(&mut new_bookworm(&[])).unwrap(), 0);
+    assert_eq!(bookworm.len(), 5);
+
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut wider = Bookworm::new(8, data_source, swap);
+    wider.push(&TestData::new(6, false)).unwrap();
+    let err = bookworm.append(&mut wider).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not append: pages are 4 bytes long but the appended ones are 8"
+    );
+    assert_eq!(bookworm.len(), 5);
+}
+#[test]
+fn test_split_off() {
+    let new_bookworm = |data_source: Rc<RefCell<Cursor<Vec<u8>>>>| {
+        let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+        let mut bookworm = Bookworm::new(4, data_source, swap);
+        for i in 0..5 {
+            bookworm.push(&TestData::new(i, false)).unwrap();
+        }
+        bookworm
+    };
+    let empty = || Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let counts = |bookworm: &mut Bookworm<Cursor<Vec<u8>>>| -> Vec<u8> {
+        bookworm
+            .typed::<TestData>()
+            .into_iter()
+            .map(|record| record.count)
+            .collect()
+    };
+
+    let data_source = empty();
+    let mut bookworm = new_bookworm(data_source.clone());
+    let mut tail = bookworm.split_off(3, empty(), empty()).unwrap();
+    assert_eq!(counts(&mut bookworm), vec![0, 1, 2]);
+    assert_eq!(counts(&mut tail), vec![3, 4]);
+    drop(bookworm);
+    let mut reopened = Bookworm::new(4, data_source, empty());
+    assert_eq!(reopened.len(), 3);
+    assert_eq!(counts(&mut reopened), vec![0, 1, 2]);
+
+    let mut bookworm = new_bookworm(empty());
+    let mut tail = bookworm.split_off(0, empty(), empty()).unwrap();
+    assert!(bookworm.is_empty());
+    assert_eq!(counts(&mut tail), vec![0, 1, 2, 3, 4]);
+
+    let mut bookworm = new_bookworm(empty());
+    assert!(bookworm.split_off(5, empty(), empty()).unwrap().is_empty());
+    assert_eq!(bookworm.len(), 5);
+    let Err(err) = bookworm.split_off(6, empty(), empty()) else {
+        panic!("split past the last page");
+    };
+    assert_eq!(
+        err.to_string(),
+        "Page 6 is out of range: only 5 pages exist"
+    );
+    let occupied = Rc::new(RefCell::new(Cursor::new(vec![0; 4])));
+    assert!(bookworm.split_off(2, occupied, empty()).is_err());
+    assert_eq!(bookworm.len(), 5);
+}
+fn test_copy_page<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source, swap);
+    for i in 0..4 {
+        bookworm.push(&TestData::new(i, i % 2 == 1)).unwrap();
+    }
+
+    bookworm.copy_page(3, 0).unwrap();
+    assert_eq!(
+        bookworm.get_raw_page(0).unwrap(),
+        bookworm.get_raw_page(3).unwrap()
+    );
+    bookworm.copy_page(2, 2).unwrap();
+    assert_eq!(bookworm.copy_page_to_end(1).unwrap(), 4);
+    assert_eq!(
+        bookworm.get_raw_page(4).unwrap(),
+        bookworm.get_raw_page(1).unwrap()
+    );
+    assert_eq!(
+        bookworm.to_vec::<TestData>().unwrap(),
+        vec![
+            TestData::new(3, true),
+            TestData::new(1, true),
+            TestData::new(2, false),
+            TestData::new(3, true),
+            TestData::new(1, true),
+        ]
+    );
+
+    let err = bookworm.copy_page(0, 5).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Page 5 is out of range: only 5 pages exist"
+    );
+    bookworm.copy_page_to_end(5).unwrap_err();
+    assert_eq!(bookworm.len(), 5);
+}
+fn test_swap_remove<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap.clone());
+    for i in 0..5 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+
+    bookworm.swap_remove(1).unwrap();
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![0, 4, 2, 3]);
+    assert!(data_source.access().inner.get_ref()[4 * 4..]
+        .iter()
+        .all(|byte| *byte == 0));
+
+    bookworm.swap_remove(3).unwrap();
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![0, 4, 2]);
+    assert_eq!(swap.access().writes, 0);
+    bookworm.swap_remove(3).unwrap_err();
+    assert_eq!(bookworm.len(), 3);
+}
+fn test_compact<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap);
+    for i in 1..=8 {
+        bookworm.push(&TestData::new(i, false)).unwrap();
+    }
+    for page in [0, 3, 4, 7] {
+        bookworm.set_raw(page, &[]).unwrap();
+    }
+
+    let report = bookworm.compact().unwrap();
+    assert_eq!(
+        report,
+        CompactReport {
+            pages_reclaimed: 4,
+            bytes_reclaimed: 16
+        }
+    );
+    let counts: Vec<u8> = bookworm
+        .typed::<TestData>()
+        .into_iter()
+        .map(|record| record.count)
+        .collect();
+    assert_eq!(counts, vec![2, 3, 6, 7]);
+    assert!(data_source.access().get_ref()[4 * 4..]
+        .iter()
+        .all(|byte| *byte == 0));
+    assert_eq!(bookworm.compact().unwrap().pages_reclaimed, 0);
+}
+fn test_length_prefixed_layout<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(8, data_source.clone(), swap);
+    bookworm.set_layout(PageLayout::LengthPrefixed);
+    bookworm.push_raw(b"ab").unwrap();
+    bookworm.push(&TestData::new(3, true)).unwrap();
+    bookworm.push_all([TestData::new(4, false)]).unwrap();
+    bookworm.insert(0, &TestData::new(1, false)).unwrap();
+    assert_eq!(&data_source.access().get_ref()[8..16], b"\x02\0\0\0ab\0\0");
+
+    assert_eq!(bookworm.get_raw_page(1).unwrap(), b"ab");
+    assert_eq!(
+        bookworm.get_page::<TestData>(2).unwrap(),
+        TestData::new(3, true)
+    );
+    bookworm.delete(1).unwrap();
+    assert_eq!(
+        bookworm.to_vec::<TestData>().unwrap(),
+        vec![
+            TestData::new(1, false),
+            TestData::new(3, true),
+            TestData::new(4, false)
+        ]
+    );
+    bookworm.push_raw(&[0; 5]).unwrap_err();
+    let fill = bookworm.page_fill(0).unwrap();
+    assert_eq!(fill.payload_bytes, 2);
+    assert!(fill.exact);
+
+    // write_at stays within the payload, so the prefix is corrupted on the data source
+    data_source.access().get_mut()[8..12].copy_from_slice(&[9, 0, 0, 0]);
+    let err = bookworm.get_raw_page(1).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not read page: stored length 9 is over the 4 bytes a page holds"
+    );
+    bookworm.get_page::<TestData>(1).unwrap_err();
+
+    // the same bytes read with the padded layout show the prefix
+    let reopened = H::wrap(Cursor::new(data_source.access().get_ref().clone()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut padded = H::bookworm(8, reopened, swap);
+    assert_eq!(padded.get_raw_page(0).unwrap(), b"\x02\0\0\0\x01\0\0\0");
+}
+fn test_chained_layout<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source.clone(), swap);
+    bookworm.set_layout(PageLayout::Chained);
+    // 23 bytes of data fit in a page, the 50 bytes long vector takes 3 pages with its length
+    let big: Vec<u8> = (0..50).collect();
+    bookworm.push(&vec![1u8]).unwrap();
+    bookworm.push(&big).unwrap();
+    bookworm.push_all([vec![2u8], big.clone()]).unwrap();
+    bookworm.push(&vec![3u8]).unwrap();
+    assert_eq!(bookworm.len(), 9);
+
+    assert_eq!(bookworm.get_page::<Vec<u8>>(1).unwrap(), big);
+    assert_eq!(bookworm.get_page::<Vec<u8>>(4).unwrap(), vec![2]);
+    let err = bookworm.get_page::<Vec<u8>>(2).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not read page 2: it continues a record started earlier"
+    );
+    let records: Vec<Vec<u8>> = bookworm.typed::<Vec<u8>>().into_iter().collect();
+    assert_eq!(
+        records,
+        vec![vec![1], big.clone(), vec![2], big.clone(), vec![3]]
+    );
+    let records: Vec<Vec<u8>> = bookworm.iter::<Vec<u8>>(0).rev().collect();
+    assert_eq!(
+        records,
+        vec![vec![3], big.clone(), vec![2], big.clone(), vec![1]]
+    );
+    assert_eq!(bookworm.raw_iter(0).nth(3).unwrap().len(), 58);
+    assert_eq!(bookworm.last::<Vec<u8>>().unwrap(), Some(vec![3]));
+
+    // lengths count records rather than pages
+    let mut records = bookworm.iter::<Vec<u8>>(0);
+    assert_eq!(records.len(), 5);
+    records.next().unwrap();
+    records.next_back().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(bookworm.iter::<Vec<u8>>(4).len(), 3);
+    assert_eq!(bookworm.iter::<Vec<u8>>(4).count(), 3);
+    assert_eq!(bookworm.raw_iter(0).len(), 5);
+    let mut records = bookworm.raw_iter_range(1..);
+    assert_eq!(records.len(), 4);
+    records.next().unwrap();
+    assert_eq!(records.len(), 3);
+    drop(records);
+
+    // scans that take a record per page refuse chained pages
+    let kinds = [
+        bookworm.to_vec::<Vec<u8>>().map(drop),
+        bookworm.find_page(|_: &Vec<u8>| true).map(drop),
+        bookworm
+            .binary_search_by(|_: &Vec<u8>| std::cmp::Ordering::Less)
+            .map(drop),
+        bookworm.try_iter::<Vec<u8>>().next().unwrap().map(drop),
+        bookworm.drain::<Vec<u8>>(0..1).map(drop),
+        bookworm.retain(|_: &Vec<u8>| true).map(drop),
+        bookworm.compact().map(drop),
+        bookworm.sort_by_key(|record: &Vec<u8>| record.len()),
+    ]
+    .map(|result| result.unwrap_err().kind());
+    assert!(kinds
+        .iter()
+        .all(|kind| *kind == error::ErrorKind::InvalidInput));
+    assert_eq!(bookworm.len(), 9);
+
+    bookworm.delete(1).unwrap();
+    assert_eq!(bookworm.len(), 6);
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.last::<Vec<u8>>().unwrap(), Some(big.clone()));
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.len(), 2);
+    assert!(data_source.access().get_ref()[2 * 32..]
+        .iter()
+        .all(|byte| *byte == 0));
+
+    bookworm.push(&big).unwrap();
+    let records: Vec<Vec<u8>> = bookworm.into_iter::<Vec<u8>>().rev().collect();
+    assert_eq!(records, vec![big, vec![2], vec![1]]);
+}
+fn test_oversize_policy<H: Handles>() {
+    let big: Vec<u8> = (0..50).collect();
+    let records = vec![vec![1u8], big.clone(), vec![2], big.clone(), vec![3]];
+
+    // rejecting leaves the store byte for byte as it was, refusing the oversized records
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let untouched = H::wrap(Cursor::new(Vec::new()));
+    let open = |data_source| {
+        H::open(
+            32,
+            PageLayout::LengthPrefixed,
+            data_source,
+            H::wrap(Cursor::new(Vec::new())),
+        )
+        .unwrap()
+    };
+    let mut bookworm = open(data_source.clone());
+    let mut baseline = open(untouched.clone());
+    bookworm
+        .set_oversize_policy(OversizePolicy::Reject)
+        .unwrap();
+    for record in &records {
+        match bookworm.push(record) {
+            Ok(()) => baseline.push(record).unwrap(),
+            Err(err) => assert_eq!(err.kind(), ErrorKind::DataTooLarge),
+        }
+    }
+    assert_eq!(bookworm.len(), 3);
+    assert_eq!(
+        bookworm.to_vec::<Vec<u8>>().unwrap(),
+        [vec![1], vec![2], vec![3]]
+    );
+    assert_eq!(data_source.access().get_ref(), untouched.access().get_ref());
+    let header = FileHeader::read_from(&mut *data_source.access()).unwrap();
+    assert_eq!(header.oversize, OversizePolicy::Reject);
+    // pages already laid out without chains can't start spilling
+    let err = bookworm
+        .set_oversize_policy(OversizePolicy::Spill)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    // spilling chains the oversized records, which are addressed by position
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::open(32, PageLayout::Padded, data_source.clone(), swap.clone()).unwrap();
+    bookworm.set_oversize_policy(OversizePolicy::Spill).unwrap();
+    bookworm.push(&records[0]).unwrap();
+    bookworm.push(&records[1]).unwrap();
+    bookworm.push_all(&records[2..4]).unwrap();
+    bookworm.push(&records[4]).unwrap();
+    assert_eq!(bookworm.len(), 5);
+    assert_eq!(bookworm.pager.pages_count, 9);
+    for (index, record) in records.iter().enumerate() {
+        assert_eq!(&bookworm.get_page::<Vec<u8>>(index).unwrap(), record);
+    }
+    let Err(err) = bookworm.get_page::<Vec<u8>>(5) else {
+        panic!("read a record past the last one");
+    };
+    assert_eq!(err.kind(), ErrorKind::PageOutOfRange);
+    assert_eq!(*H::cached::<_, Vec<u8>>(&mut bookworm, 3).unwrap(), big);
+    assert_eq!(bookworm.get_raw_page(2).unwrap().len(), 9);
+    assert_eq!(bookworm.to_vec::<Vec<u8>>().unwrap(), records);
+    assert_eq!(
+        bookworm.iter::<Vec<u8>>(1).collect::<Vec<_>>(),
+        records[1..]
+    );
+    assert_eq!(
+        bookworm.iter_range::<Vec<u8>>(1..3).collect::<Vec<_>>(),
+        records[1..3]
+    );
+    let mut reversed = records.clone();
+    reversed.reverse();
+    assert_eq!(
+        bookworm.iter::<Vec<u8>>(0).rev().collect::<Vec<_>>(),
+        reversed
+    );
+
+    let stored = data_source.access().get_ref().clone();
+
+    // records taking a single page are overwritten in place, spilled ones can't be
+    bookworm.set(2, &vec![9u8]).unwrap();
+    let err = bookworm.set(1, &vec![9u8]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    bookworm.delete(1).unwrap();
+    assert_eq!(bookworm.len(), 4);
+    assert_eq!(
+        bookworm.to_vec::<Vec<u8>>().unwrap(),
+        [vec![1], vec![9], big.clone(), vec![3]]
+    );
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.last::<Vec<u8>>().unwrap(), Some(big.clone()));
+    bookworm.truncate(2).unwrap();
+    assert_eq!(bookworm.len(), 2);
+    bookworm.push(&big).unwrap();
+    assert_eq!(bookworm.len(), 3);
+    assert_eq!(bookworm.get_page::<Vec<u8>>(2).unwrap(), big);
+
+    // the header flags spilling stores, which reopen spilling
+    let data_source = H::wrap(Cursor::new(stored));
+    let header = FileHeader::read_from(&mut *data_source.access()).unwrap();
+    assert_eq!(
+        (header.layout, header.oversize),
+        (PageLayout::Chained, OversizePolicy::Spill)
+    );
+    let mut reopened = H::open(32, PageLayout::Chained, data_source.clone(), swap).unwrap();
+    assert_eq!(reopened.oversize_policy(), OversizePolicy::Spill);
+    assert_eq!(reopened.len(), 5);
+    assert_eq!(reopened.get_page::<Vec<u8>>(3).unwrap(), big);
+
+    // going back to rejecting leaves the chains addressed by their first page
+    reopened
+        .set_oversize_policy(OversizePolicy::Reject)
+        .unwrap();
+    assert_eq!(reopened.len(), 9);
+    assert_eq!(reopened.get_page::<Vec<u8>>(5).unwrap(), big);
+    let header = FileHeader::read_from(&mut *data_source.access()).unwrap();
+    assert_eq!(header.oversize, OversizePolicy::Reject);
+}
+/// Bincode with every byte flipped, a codec pages of the default one can't be read with
+#[derive(Clone)]
+struct XorCodec(u8);
+
+impl Codec for XorCodec {
+    type Error = bincode::Error;
+
+    fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, Self::Error> {
+        let mut bytes = BincodeCodec.serialize(value)?;
+        bytes.iter_mut().for_each(|byte| *byte ^= self.0);
+        Ok(bytes)
+    }
+    fn deserialize<T: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+        limit: u64,
+    ) -> Result<T, Self::Error> {
+        let bytes: Vec<u8> = bytes.iter().map(|byte| byte ^ self.0).collect();
+        BincodeCodec.deserialize(&bytes, limit)
+    }
+    /// Flipped bytes have to be copied out, so there's nothing to borrow from
+    fn deserialize_borrowed<'de, T: Deserialize<'de>>(
+        &self,
+        _bytes: &'de [u8],
+        _limit: u64,
+    ) -> Result<T, Self::Error> {
+        Err(Box::new(bincode::ErrorKind::Custom(
+            "xor'd pages can't be borrowed from".to_owned(),
+        )))
+    }
+}
+fn assert_codec_round_trip<H: Handles, C: Codec>(codec: C) {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = Bookworm::with_codec(32, data_source.clone(), swap.clone(), codec.clone());
+    let records: Vec<(u32, String)> = (0..10).map(|i| (i * 1000, format!("page {}", i))).collect();
+    bookworm.push_all(&records).unwrap();
+    bookworm.set(3, &(7u32, "set".to_owned())).unwrap();
+    drop(bookworm);
+
+    let mut bookworm = Bookworm::with_codec(32, data_source, swap, codec);
+    assert_eq!(bookworm.len(), 10);
+    assert_eq!(
+        bookworm.get_page::<(u32, String)>(3).unwrap(),
+        (7, "set".to_owned())
+    );
+    let read: Vec<(u32, String)> = bookworm.iter(0).collect();
+    assert_eq!(read.len(), 10);
+    assert_eq!(read[..3], records[..3]);
+    assert_eq!(read[4..], records[4..]);
+}
+fn test_bincode_codec<H: Handles>() {
+    assert_codec_round_trip::<H, _>(BincodeCodec);
+    assert_codec_round_trip::<H, _>(XorCodec(0x5A));
+}
+#[cfg(feature = "varint-codec")]
+fn test_varint_codec<H: Handles>() {
+    assert_codec_round_trip::<H, _>(VarintCodec);
+
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = Bookworm::with_codec(32, data_source.clone(), swap, VarintCodec);
+    bookworm.push(&(1000u32, "abc")).unwrap();
+    assert_eq!(
+        bookworm.get_raw_page(0).unwrap()[..8],
+        [251, 232, 3, 3, b'a', b'b', b'c', 0]
+    );
+}
+fn test_codec_mismatch<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = Bookworm::with_codec(32, data_source.clone(), swap.clone(), XorCodec(0x5A));
+    bookworm.push(&(1000u32, "abc".to_owned())).unwrap();
+    drop(bookworm);
+
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    let Err(err) = bookworm.get_page::<(u32, String)>(0) else {
+        panic!("a page of another codec was decoded");
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not parse data: the size limit has been reached"
+    );
+}
+fn test_checksummed_layout<H: Handles>() {
+    for algorithm in ChecksumAlgorithm::ALL {
+        check_checksummed_layout::<H>(algorithm);
+    }
+}
+
+/// Pages round trip and corruption is caught the same way whatever digests them
+fn check_checksummed_layout<H: Handles>(algorithm: ChecksumAlgorithm) {
+    let header = 5 + algorithm.digest_len();
+    let page_size = header + 8;
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(page_size, data_source.clone(), swap);
+    bookworm.set_layout(PageLayout::Checksummed(algorithm));
+    bookworm.push_raw(b"ab").unwrap();
+    bookworm
+        .push_all((0..4).map(|count| TestData::new(count, true)))
+        .unwrap();
+    let first_page = data_source.access().get_ref()[..page_size].to_vec();
+    assert_eq!(first_page[..5], [2, 0, 0, 0, algorithm.digest_len() as u8]);
+    assert_eq!(&first_page[5..header], algorithm.digest(b"ab").as_bytes());
+    assert_eq!(&first_page[header..], b"ab\0\0\0\0\0\0");
+    bookworm.delete(0).unwrap();
+    bookworm.push_raw(&[0; 9]).unwrap_err();
+    assert_eq!(bookworm.get_raw_page(3).unwrap(), [3, 1]);
+    assert!(bookworm.page_fill(0).unwrap().exact);
+
+    let flip = |offset: usize| data_source.access().get_mut()[2 * page_size + offset] ^= 0xFF;
+    flip(header);
+    let err = bookworm.get_page::<TestData>(2).unwrap_err();
+    let mismatch = error::ChecksumMismatch {
+        page: 2,
+        stored: algorithm.digest(&[2, 1]),
+        computed: algorithm.digest(&[!2, 1]),
+    };
+    assert_eq!(err.mismatch(), Some(mismatch));
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Checksum mismatch on page 2: stored {} but computed {}",
+            mismatch.stored, mismatch.computed
+        )
+    );
+    if algorithm == ChecksumAlgorithm::Crc32 {
+        assert_eq!(
+            err.to_string(),
+            "Checksum mismatch on page 2: stored 04e840eb but computed 97ccbd99"
+        );
+    }
+    assert_eq!(
+        bookworm.get_page::<TestData>(3).unwrap(),
+        TestData::new(3, true)
+    );
+    let mut records = bookworm.try_iter::<TestData>();
+    assert_eq!(records.next().unwrap().unwrap(), TestData::new(0, true));
+    assert_eq!(records.next().unwrap().unwrap(), TestData::new(1, true));
+    let err = records.next().unwrap().unwrap_err();
+    assert_eq!(err.mismatch().map(|mismatch| mismatch.page), Some(2));
+    assert!(records.next().is_none());
+    assert_eq!(bookworm.iter::<TestData>(0).count(), 2);
+    flip(header);
+    assert_eq!(
+        bookworm.get_page::<TestData>(2).unwrap(),
+        TestData::new(2, true)
+    );
+
+    // a flipped digest is caught as well
+    flip(header - 1);
+    let err = bookworm.get_page::<TestData>(2).unwrap_err();
+    assert_eq!(err.mismatch().map(|mismatch| mismatch.page), Some(2));
+    flip(header - 1);
+
+    // so is a digest length the algorithm doesn't produce
+    flip(4);
+    let err = bookworm.get_page::<TestData>(2).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Corrupted);
+    assert!(err.mismatch().is_none());
+    flip(4);
+
+    bookworm.write_at(0, 8, &[1]).unwrap_err();
+}
+#[test]
+fn test_checksum_algorithms() {
+    let hex = |algorithm: ChecksumAlgorithm, data: &[u8]| algorithm.digest(data).to_string();
+    assert_eq!(hex(ChecksumAlgorithm::Crc32, b""), "00000000");
+    assert_eq!(hex(ChecksumAlgorithm::Crc32, b"abc"), "352441c2");
+    assert_eq!(hex(ChecksumAlgorithm::XxHash64, b""), "ef46db3751d8e999");
+    assert_eq!(hex(ChecksumAlgorithm::XxHash64, b"abc"), "44bc2cf5ad770999");
+    assert_eq!(
+        hex(
+            ChecksumAlgorithm::XxHash64,
+            b"Nobody inspects the spammish repetition"
+        ),
+        "fbcea83c8a378bf1"
+    );
+    assert_eq!(
+        hex(ChecksumAlgorithm::Sha256, b""),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex(ChecksumAlgorithm::Sha256, b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        hex(
+            ChecksumAlgorithm::Sha256,
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+        ),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    for algorithm in ChecksumAlgorithm::ALL {
+        let digest = algorithm.digest(b"abc");
+        assert_eq!(digest.as_bytes().len(), algorithm.digest_len());
+        assert_eq!(digest, algorithm.checksum().digest(b"abc"));
+    }
+}
+fn test_file_header<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::open(
+        16,
+        PageLayout::LengthPrefixed,
+        data_source.clone(),
+        swap.clone(),
+    )
+    .unwrap();
+    assert!(bookworm.is_empty());
+    bookworm.push(&TestData::new(1, true)).unwrap();
+    bookworm.push(&TestData::new(2, false)).unwrap();
+    bookworm.insert(0, &TestData::new(0, true)).unwrap();
+    bookworm.set(1, &TestData::new(3, false)).unwrap();
+    assert_eq!(
+        &data_source.access().get_ref()[..16],
+        b"BKWM\x02\x01\x10\0\0\0\0\0\0\0\0\0"
+    );
+    assert_eq!(&data_source.access().get_ref()[16..22], b"\x02\0\0\0\0\x01");
+    drop(bookworm);
+
+    let mut bookworm = H::open(
+        16,
+        PageLayout::LengthPrefixed,
+        data_source.clone(),
+        swap.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        bookworm.to_vec::<TestData>().unwrap(),
+        vec![
+            TestData::new(0, true),
+            TestData::new(3, false),
+            TestData::new(2, false)
+        ]
+    );
+    bookworm.pop().unwrap();
+    bookworm.refresh().unwrap();
+    assert_eq!(bookworm.len(), 2);
+    drop(bookworm);
+
+    let open_error = |page_size, layout, bytes: &[u8]| {
+        let data_source = H::wrap(Cursor::new(bytes.to_vec()));
+        let swap = H::wrap(Cursor::new(Vec::new()));
+        match H::open(page_size, layout, data_source, swap) {
+            Ok(_) => panic!("the data source was opened"),
+            Err(err) => err.to_string(),
+        }
+    };
+    let stored = data_source.access().get_ref().clone();
+    assert_eq!(
+        open_error(16, PageLayout::Padded, &stored),
+        "Could not open data source: it was written with the LengthPrefixed layout, not Padded"
+    );
+    assert_eq!(
+        open_error(32, PageLayout::LengthPrefixed, &stored),
+        "Could not open data source: its pages are 16 bytes long, not 32"
+    );
+    let mut newer = stored.clone();
+    newer[4] = 9;
+    assert_eq!(
+        open_error(16, PageLayout::LengthPrefixed, &newer),
+        "Could not open data source: format version 9 is not supported"
+    );
+    // version 1 only differs in how checksummed pages are laid out
+    let mut older = stored.clone();
+    older[4] = 1;
+    let data_source = H::wrap(Cursor::new(older));
+    let mut bookworm = H::open(
+        16,
+        PageLayout::LengthPrefixed,
+        data_source,
+        H::wrap(Cursor::new(Vec::new())),
+    )
+    .unwrap();
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(0, true)
+    );
+    drop(bookworm);
+    older = stored.clone();
+    older[4] = 1;
+    older[5] = 1 << 2;
+    assert_eq!(
+        open_error(
+            16,
+            PageLayout::Checksummed(ChecksumAlgorithm::Crc32),
+            &older
+        ),
+        "Could not open data source: format version 1 checksummed pages are not supported"
+    );
+    assert_eq!(
+        open_error(16, PageLayout::Padded, &[7; 40]),
+        "Could not open data source: it is not a bookworm file"
+    );
+    assert_eq!(
+        open_error(16, PageLayout::Padded, b"BKWM"),
+        "Could not open data source: it is not a bookworm file"
+    );
+
+    // pre-header stores keep opening through `new`
+    let legacy = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, legacy.clone(), swap.clone());
+    bookworm.push(&TestData::new(5, true)).unwrap();
+    assert_eq!(&legacy.access().get_ref()[..2], [5, 1]);
+}
+#[test]
+fn test_open_existing() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::open(
+        128,
+        PageLayout::Checksummed(ChecksumAlgorithm::Sha256),
+        data_source.clone(),
+        swap.clone(),
+    )
+    .unwrap();
+    bookworm
+        .push_all((0..3).map(|count| TestData::new(count, false)))
+        .unwrap();
+    drop(bookworm);
+
+    assert_eq!(data_source.borrow().get_ref()[10], 2);
+    let mut bookworm = Bookworm::open_existing(data_source.clone(), swap.clone()).unwrap();
+    assert_eq!(bookworm.page_size(), 128);
+    let payload = bookworm.get_raw_page(0).unwrap();
+    let info = bookworm.page_info_iter().next().unwrap().unwrap();
+    assert_eq!(info.checksum, Some(Sha256.digest(&payload)));
+    assert_eq!(bookworm.len(), 3);
+    assert_eq!(
+        bookworm.get_page::<TestData>(2).unwrap(),
+        TestData::new(2, false)
+    );
+    drop(bookworm);
+
+    let stored = data_source.borrow().get_ref().clone();
+    let Err(err) = Bookworm::open(
+        256,
+        PageLayout::Checksummed(ChecksumAlgorithm::Sha256),
+        data_source.clone(),
+        swap.clone(),
+    ) else {
+        panic!("the data source was opened with the wrong page size");
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not open data source: its pages are 128 bytes long, not 256"
+    );
+    let Err(err) = Bookworm::open(
+        128,
+        PageLayout::Checksummed(ChecksumAlgorithm::Crc32),
+        data_source.clone(),
+        swap.clone(),
+    ) else {
+        panic!("the data source was opened with the wrong checksum algorithm");
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not open data source: it was written with the Checksummed(Sha256) layout, not \
+         Checksummed(Crc32)"
+    );
+    assert_eq!(data_source.borrow().get_ref(), &stored);
+    let mut unknown = stored.clone();
+    unknown[10] = 9;
+    let Err(err) =
+        Bookworm::open_existing(Rc::new(RefCell::new(Cursor::new(unknown))), swap.clone())
+    else {
+        panic!("the data source was opened with an unknown checksum algorithm");
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not open data source: unknown checksum algorithm 9"
+    );
+
+    let empty = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    assert!(Bookworm::open_existing(empty.clone(), swap).is_err());
+    assert!(empty.borrow().get_ref().is_empty());
+}
+fn test_count_source<H: Handles>() {
+    let open = |bytes: Vec<u8>| {
+        let data_source = H::wrap(Cursor::new(bytes));
+        let bookworm = H::open(
+            16,
+            PageLayout::LengthPrefixed,
+            data_source.clone(),
+            H::wrap(Cursor::new(Vec::new())),
+        )
+        .unwrap();
+        (bookworm, data_source)
+    };
+    let mut headerless = H::bookworm(
+        16,
+        H::wrap(Cursor::new(Vec::new())),
+        H::wrap(Cursor::new(Vec::new())),
+    );
+    let err = headerless
+        .set_count_source(CountSource::Header)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(headerless.open_integrity(), None);
+
+    for source in [CountSource::FileLength, CountSource::Header] {
+        let counted = source == CountSource::Header;
+
+        // preallocated slots are left out of the count only when the header keeps it
+        let (mut bookworm, data_source) = open(Vec::new());
+        bookworm.set_count_source(source).unwrap();
+        bookworm.pager.preallocate(4).unwrap();
+        bookworm.push(&1u32).unwrap();
+        bookworm.push(&2u32).unwrap();
+        assert_eq!(bookworm.len(), 2);
+        drop(bookworm);
+        assert_eq!(data_source.access().get_ref().len(), 16 + 4 * 16);
+        let (mut bookworm, data_source) = open(data_source.access().get_ref().clone());
+        assert_eq!(bookworm.count_source(), source);
+        let integrity = bookworm.open_integrity().unwrap();
+        assert_eq!(integrity.stored_pages, 4);
+        assert_eq!(integrity.torn_bytes, 0);
+        if counted {
+            assert_eq!(integrity.check, CountCheck::TrailingPages);
+            assert_eq!(integrity.recorded_pages, Some(2));
+            assert_eq!(bookworm.len(), 2);
+            bookworm.push(&3u32).unwrap();
+            assert_eq!(bookworm.to_vec::<u32>().unwrap(), [1, 2, 3]);
+            assert_eq!(data_source.access().get_ref().len(), 16 + 4 * 16);
+        } else {
+            assert_eq!(integrity.check, CountCheck::Unrecorded);
+            assert_eq!(bookworm.len(), 4);
+        }
+        drop(bookworm);
+
+        // a torn tail holds a whole page and a few bytes past the records
+        let (mut bookworm, data_source) = open(Vec::new());
+        bookworm.set_count_source(source).unwrap();
+        bookworm.push_all([1u32, 2, 3]).unwrap();
+        drop(bookworm);
+        let mut stored = data_source.access().get_ref().clone();
+        stored.extend_from_slice(&[0xff; 16 + 5]);
+        let (mut bookworm, _) = open(stored);
+        let integrity = bookworm.open_integrity().unwrap();
+        assert_eq!(integrity.stored_pages, 4);
+        assert_eq!(integrity.torn_bytes, 5);
+        if counted {
+            assert_eq!(integrity.check, CountCheck::TrailingPages);
+            assert_eq!(bookworm.to_vec::<u32>().unwrap(), [1, 2, 3]);
+            bookworm.push(&4u32).unwrap();
+            assert_eq!(bookworm.to_vec::<u32>().unwrap(), [1, 2, 3, 4]);
+        } else {
+            assert_eq!(integrity.check, CountCheck::Unrecorded);
+            assert_eq!(bookworm.len(), 4);
+            assert!(bookworm.get_page::<u32>(3).is_err());
+        }
+        drop(bookworm);
+
+        // pops, deletes and truncations shrink the recorded count along with the pages
+        let (mut bookworm, data_source) = open(Vec::new());
+        bookworm.set_count_source(source).unwrap();
+        bookworm.push_all([1u32, 2, 3, 4, 5, 6]).unwrap();
+        bookworm.pop().unwrap();
+        bookworm.delete(0).unwrap();
+        bookworm.truncate(3).unwrap();
+        assert_eq!(bookworm.to_vec::<u32>().unwrap(), [2, 3, 4]);
+        drop(bookworm);
+        let (mut bookworm, _) = open(data_source.access().get_ref().clone());
+        let integrity = bookworm.open_integrity().unwrap();
+        if counted {
+            assert_eq!(integrity.recorded_pages, Some(3));
+            assert_eq!(bookworm.to_vec::<u32>().unwrap(), [2, 3, 4]);
+        } else {
+            assert_eq!(bookworm.len(), integrity.stored_pages);
+        }
+
+        // a file cut short behind the store's back is told apart from one with spare pages
+        if counted {
+            let mut stored = data_source.access().get_ref().clone();
+            stored.truncate(16 + 2 * 16);
+            let (mut bookworm, _) = open(stored);
+            let integrity = bookworm.open_integrity().unwrap();
+            assert_eq!(integrity.check, CountCheck::MissingPages);
+            assert_eq!(integrity.recorded_pages, Some(3));
+            assert_eq!(integrity.stored_pages, 2);
+            assert_eq!(bookworm.len(), 3);
+            assert!(bookworm.get_page::<u32>(2).is_err());
+        }
+
+        // a push interrupted at any write reopens with the records that were there before, and
+        // the pushed one when it made it into the count
+        for fail_after in 0.. {
+            let data_source = H::wrap(CountingStorage::default());
+            let mut bookworm = H::open(
+                16,
+                PageLayout::LengthPrefixed,
+                data_source.clone(),
+                H::wrap(CountingStorage::default()),
+            )
+            .unwrap();
+            bookworm.set_count_source(source).unwrap();
+            bookworm.push_all([1u32, 2]).unwrap();
+            let writes = data_source.access().writes;
+            data_source.access().fail_writes_after = Some(writes + fail_after);
+            let pushed = bookworm.push(&3u32).is_ok();
+            drop(bookworm);
+            let (mut bookworm, _) = open(data_source.access().inner.get_ref().clone());
+            let records = bookworm.to_vec::<u32>().unwrap();
+            if pushed {
+                assert_eq!(records, [1, 2, 3]);
+                assert_eq!(bookworm.open_integrity().unwrap().torn_bytes, 0);
+                break;
+            }
+            let integrity = bookworm.open_integrity().unwrap();
+            if counted {
+                assert_eq!(records, [1, 2]);
+                assert!(
+                    integrity.check == CountCheck::Matches
+                        || integrity.check == CountCheck::TrailingPages
+                );
+            } else {
+                assert!(records == [1, 2] || records == [1, 2, 3]);
+            }
+        }
+    }
+
+    // another handle pushing over the same storage moves the recorded count refresh picks up
+    let (mut bookworm, data_source) = open(Vec::new());
+    bookworm.set_count_source(CountSource::Header).unwrap();
+    bookworm.pager.preallocate(4).unwrap();
+    bookworm.push(&1u32).unwrap();
+    let mut other = H::open(
+        16,
+        PageLayout::LengthPrefixed,
+        data_source.clone(),
+        H::wrap(Cursor::new(Vec::new())),
+    )
+    .unwrap();
+    other.push(&2u32).unwrap();
+    assert_eq!(bookworm.refresh().unwrap(), RefreshOutcome::Grew { by: 1 });
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), [1, 2]);
+    bookworm.set_count_source(CountSource::FileLength).unwrap();
+    drop(bookworm);
+    let (bookworm, _) = open(data_source.access().get_ref().clone());
+    assert_eq!(bookworm.count_source(), CountSource::FileLength);
+    assert_eq!(bookworm.len(), 4);
+}
+fn test_free_list<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap.clone());
+    bookworm
+        .push_all((1..=6).map(|count| TestData::new(count, true)))
+        .unwrap();
+    bookworm.set_free_list(true).unwrap();
+    bookworm.delete(1).unwrap();
+    bookworm.delete(3).unwrap();
+    assert_eq!(bookworm.len(), 4);
+    assert_eq!(bookworm.free_pages(), vec![1, 3]);
+    let counts = |records: Vec<TestData>| -> Vec<u8> {
+        records.into_iter().map(|record| record.count).collect()
+    };
+    assert_eq!(counts(bookworm.iter(0).collect()), vec![1, 3, 5, 6]);
+    assert_eq!(counts(bookworm.iter(0).rev().collect()), vec![6, 5, 3, 1]);
+    assert_eq!(bookworm.iter::<TestData>(0).len(), 4);
+    assert_eq!(
+        bookworm.iter::<TestData>(0).nth(1),
+        Some(TestData::new(3, true))
+    );
+    let decoded: BookwormResult<Vec<TestData>> = bookworm.try_iter().collect();
+    assert_eq!(counts(decoded.unwrap()), vec![1, 3, 5, 6]);
+    assert_eq!(
+        bookworm.get_page::<TestData>(1).unwrap_err().to_string(),
+        "Page 1 is free"
+    );
+    bookworm.insert(0, &TestData::new(0, true)).unwrap_err();
+    bookworm.set_free_list(false).unwrap_err();
+
+    // pushes fill the free pages in order before growing the data source
+    let storage_bytes = bookworm.storage_bytes().unwrap();
+    bookworm.push(&TestData::new(10, false)).unwrap();
+    bookworm.push(&TestData::new(11, false)).unwrap();
+    assert_eq!(bookworm.storage_bytes().unwrap(), storage_bytes);
+    assert!(bookworm.free_pages().is_empty());
+    assert_eq!(counts(bookworm.iter(0).collect()), vec![1, 10, 3, 11, 5, 6]);
+
+    bookworm.delete(5).unwrap();
+    bookworm.delete(2).unwrap();
+    assert_eq!(bookworm.len(), 4);
+    drop(bookworm);
+
+    // the free pages are found again once the free list is enabled
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    bookworm.set_free_list(true).unwrap();
+    assert_eq!(bookworm.free_pages(), vec![2]);
+    assert_eq!(bookworm.len(), 4);
+    let report = bookworm.compact().unwrap();
+    assert_eq!(report.pages_reclaimed, 1);
+    assert!(bookworm.free_pages().is_empty());
+    assert_eq!(counts(bookworm.to_vec().unwrap()), vec![1, 10, 11, 5]);
+    bookworm.set_free_list(false).unwrap();
+    bookworm.delete(0).unwrap();
+    assert_eq!(counts(bookworm.to_vec().unwrap()), vec![10, 11, 5]);
+}
+fn test_free_list_clear<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    bookworm.set_free_list(true).unwrap();
+    bookworm.push_all(0u32..4).unwrap();
+    bookworm.delete(0).unwrap();
+    bookworm.clear().unwrap();
+    assert!(bookworm.free_pages().is_empty());
+    assert_eq!(bookworm.len(), 0);
+    bookworm.push(&7u32).unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![7]);
+}
+fn test_free_list_readers<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    bookworm.set_free_list(true).unwrap();
+    bookworm.push_all([1u32, 2, 3, 4, 5, 6]).unwrap();
+    bookworm.delete(0).unwrap();
+    bookworm.delete(3).unwrap();
+
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![2, 3, 5, 6]);
+    let decoded: BookwormResult<Vec<u32>> = bookworm.try_iter().collect();
+    assert_eq!(decoded.unwrap(), vec![2, 3, 5, 6]);
+    assert_eq!(bookworm.first::<u32>().unwrap(), Some(2));
+    assert_eq!(bookworm.last::<u32>().unwrap(), Some(6));
+    assert_eq!(
+        bookworm.first_raw().unwrap(),
+        Some(bookworm.get_raw_page(1).unwrap())
+    );
+    assert_eq!(
+        bookworm.find_page(|record: &u32| record % 2 == 1).unwrap(),
+        Some((2, 3))
+    );
+    assert_eq!(
+        bookworm.rfind_page(|record: &u32| *record < 5).unwrap(),
+        Some((2, 3))
+    );
+    assert_eq!(
+        bookworm
+            .binary_search_by(|record: &u32| record.cmp(&5))
+            .unwrap(),
+        Ok(4)
+    );
+    assert_eq!(
+        bookworm
+            .binary_search_by(|record: &u32| record.cmp(&4))
+            .unwrap(),
+        Err(3)
+    );
+    let mut view = bookworm.pages();
+    let viewed: BookwormResult<Vec<u32>> = view.iter().collect();
+    assert_eq!(viewed.unwrap(), vec![2, 3, 5, 6]);
+    assert_eq!(view.iter_raw().count(), 4);
+    view.get::<u32>(0).unwrap_err();
+
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.set_free_list(true).unwrap();
+    for i in 0..3u32 {
+        bookworm.push_with_ttl(&i, Duration::from_secs(60)).unwrap();
+    }
+    bookworm.delete(0).unwrap();
+    let unexpired: Vec<u32> = bookworm.iter_unexpired().unwrap().collect();
+    assert_eq!(unexpired, vec![1, 2]);
+}
+fn test_delete_in_place<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(8, data_source.clone(), swap.clone());
+    let pages = COPY_BATCH_PAGES * 2 + 3;
+    bookworm
+        .push_all((0..pages).map(|page| page as u32))
+        .unwrap();
+    bookworm.delete_in_place(0).unwrap();
+    bookworm.delete_in_place(70).unwrap();
+    bookworm.delete_in_place(pages - 3).unwrap();
+    let mut expected: Vec<u32> = (1..pages as u32).collect();
+    expected.remove(70);
+    expected.pop();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), expected);
+    let stored = data_source.access().inner.get_ref().clone();
+    assert_eq!(stored.len(), pages * 8);
+    assert!(stored[(pages - 3) * 8..].iter().all(|byte| *byte == 0));
+    assert_eq!(swap.access().writes, 0);
+    assert!(swap.access().inner.get_ref().is_empty());
+
+    // chained records go along with their whole chain
+    let data_source = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(32, data_source, swap.clone());
+    bookworm.set_layout(PageLayout::Chained);
+    let big = vec![7u8; 50];
+    bookworm.push(&vec![1u8]).unwrap();
+    bookworm.push(&big).unwrap();
+    bookworm.push(&vec![2u8]).unwrap();
+    bookworm.delete_in_place(1).unwrap();
+    assert_eq!(bookworm.len(), 2);
+    assert_eq!(bookworm.get_page::<Vec<u8>>(1).unwrap(), vec![2]);
+    assert_eq!(swap.access().writes, 0);
+}
+
+/// Pages holding a payload, found by reading every one of them
+fn header_scan<S: Read + Write + Seek, H: SharedStorage<S>>(
+    bookworm: &mut Bookworm<S, BincodeCodec, H>,
+) -> Vec<usize> {
+    (0..bookworm.pager.pages_count)
+        .filter(|page| !bookworm.pager.is_slot_empty(*page).unwrap())
+        .collect()
+}
+fn test_live_bitmap<H: Handles>() {
+    let open = |data_source| {
+        H::open(
+            16,
+            PageLayout::LengthPrefixed,
+            data_source,
+            H::wrap(Cursor::new(Vec::new())),
+        )
+        .unwrap()
+    };
+    let mut headerless = H::bookworm(
+        16,
+        H::wrap(Cursor::new(Vec::new())),
+        H::wrap(Cursor::new(Vec::new())),
+    );
+    let err = headerless.reserve_live_bitmap(3).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = open(data_source.clone());
+    let err = bookworm.reserve_live_bitmap(2).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not reserve a live bitmap: 2 pages of 16 bytes can't hold it"
+    );
+    bookworm.reserve_live_bitmap(3).unwrap();
+    bookworm.set_free_list(false).unwrap_err();
+    bookworm.push_all(0u32..10).unwrap();
+    for page in [2, 5, 7] {
+        bookworm.delete(page).unwrap();
+    }
+    assert!(!bookworm.is_live(2));
+    assert!(bookworm.is_live(3));
+    assert!(!bookworm.is_live(10));
+    assert_eq!(bookworm.count_live(), 7);
+    assert_eq!(
+        bookworm.live_pages().collect::<Vec<_>>(),
+        [0, 1, 3, 4, 6, 8, 9]
+    );
+    bookworm.push(&10u32).unwrap();
+    assert!(bookworm.is_live(2));
+    assert_eq!(
+        bookworm.live_pages().collect::<Vec<_>>(),
+        header_scan(&mut bookworm)
+    );
+    drop(bookworm);
+    // the bits follow the 16 header bytes and 32 bytes of generations, count and checksum
+    assert!(
+        FileHeader::read_from(&mut *data_source.access())
+            .unwrap()
+            .live_bitmap
+    );
+    assert_eq!(&data_source.access().get_ref()[48..50], [0b0101_1111, 0b11]);
+    let stored = data_source.access().get_ref().clone();
+
+    // reopening loads the free list from the bitmap without reading the pages
+    let counting = H::wrap(CountingStorage {
+        inner: Cursor::new(stored.clone()),
+        ..Default::default()
+    });
+    let mut bookworm = H::open(
+        16,
+        PageLayout::LengthPrefixed,
+        counting.clone(),
+        H::wrap(CountingStorage::default()),
+    )
+    .unwrap();
+    assert!(counting.access().bytes_read <= 16 + 4 + 3 * 16);
+    assert_eq!(bookworm.free_pages(), [5, 7]);
+    assert_eq!(bookworm.count_live(), 8);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), [0, 1, 10, 3, 4, 6, 8, 9]);
+    drop(bookworm);
+
+    // a bit flipped behind the store's back is found by a rebuild, or by reopening since the
+    // checksum no longer matches
+    let mut bookworm = open(data_source.clone());
+    data_source.access().get_mut()[48] ^= 1 << 3;
+    assert_eq!(bookworm.rebuild_bitmap().unwrap(), 1);
+    assert_eq!(data_source.access().get_ref()[48], 0b0101_1111);
+    assert_eq!(
+        bookworm.live_pages().collect::<Vec<_>>(),
+        header_scan(&mut bookworm)
+    );
+    drop(bookworm);
+    data_source.access().get_mut()[49] = 0;
+    let mut bookworm = open(data_source.clone());
+    assert_eq!(bookworm.free_pages(), [5, 7]);
+    assert_eq!(
+        bookworm.live_pages().collect::<Vec<_>>(),
+        header_scan(&mut bookworm)
+    );
+    assert_eq!(bookworm.rebuild_bitmap().unwrap(), 0);
+    drop(bookworm);
+
+    // a delete interrupted at any write leaves generations that don't match, so reopening
+    // rebuilds the bitmap instead of trusting bits the pages no longer agree with
+    for fail_after in 0.. {
+        let data_source = H::wrap(CountingStorage {
+            inner: Cursor::new(stored.clone()),
+            ..Default::default()
+        });
+        let mut bookworm = H::open(
+            16,
+            PageLayout::LengthPrefixed,
+            data_source.clone(),
+            H::wrap(CountingStorage::default()),
+        )
+        .unwrap();
+        let writes = data_source.access().writes;
+        data_source.access().fail_writes_after = Some(writes + fail_after);
+        let deleted = bookworm.delete(3).is_ok();
+        drop(bookworm);
+        let mut bookworm = open(H::wrap(Cursor::new(
+            data_source.access().inner.get_ref().clone(),
+        )));
+        assert_eq!(
+            bookworm.live_pages().collect::<Vec<_>>(),
+            header_scan(&mut bookworm)
+        );
+        if deleted {
+            assert!(!bookworm.is_live(3));
+            break;
+        }
+    }
+
+    // pages past the ones the bitmap covers are read on open, compacting folds the free
+    // pages back
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = open(data_source.clone());
+    bookworm.reserve_live_bitmap(3).unwrap();
+    bookworm.push_all(0u32..140).unwrap();
+    bookworm.delete(100).unwrap();
+    bookworm.delete(135).unwrap();
+    bookworm.delete(139).unwrap();
+    assert_eq!(bookworm.len(), 137);
+    drop(bookworm);
+    let mut bookworm = open(data_source.clone());
+    assert_eq!(bookworm.free_pages(), [100, 135]);
+    assert_eq!(bookworm.count_live(), 137);
+    bookworm.compact().unwrap();
+    assert_eq!(bookworm.count_live(), 137);
+    drop(bookworm);
+    let mut bookworm = open(data_source.clone());
+    assert!(bookworm.free_pages().is_empty());
+    assert_eq!(bookworm.live_pages().count(), 137);
+    assert_eq!(bookworm.rebuild_bitmap().unwrap(), 0);
+}
+fn test_delete_shift_memory<H: Handles>() {
+    let mut stored = Vec::new();
+    for (threshold, read_back) in [(160, false), (159, true)] {
+        let data_source = H::wrap(CountingStorage::default());
+        let swap = H::wrap(CountingStorage::default());
+        let mut bookworm = H::bookworm(16, data_source.clone(), swap.clone());
+        bookworm.set_shift_memory(threshold);
+        bookworm.push_all(0..11u32).unwrap();
+        bookworm.delete(0).unwrap();
+        assert_eq!(
+            bookworm.to_vec::<u32>().unwrap(),
+            (1..11).collect::<Vec<_>>()
+        );
+        // the swap gets a copy either way, only one staged in memory isn't read back
+        assert!(swap.access().writes > 0);
+        assert_eq!(swap.access().bytes_read > 0, read_back);
+        stored.push(data_source.access().inner.get_ref().clone());
+    }
+    assert_eq!(stored[0], stored[1]);
+}
+
+fn test_error_source<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap);
+    bookworm.push_all(0..3u64).unwrap();
+    data_source.access().get_mut().truncate(40);
+    let Err(err) = bookworm.get_page::<u64>(2) else {
+        panic!("a truncated page was read");
+    };
+    assert_eq!(err.kind(), error::ErrorKind::Io);
+    assert_eq!(err.page(), Some(2));
+    assert!(err.to_string().starts_with("Could not read page 2: "));
+    let source = std::error::Error::source(&err)
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .unwrap();
+    assert_eq!(source.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(bookworm.get_page::<u64>(1).unwrap(), 1);
+
+    let err = error::BookwormError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+    assert_eq!(err.kind(), error::ErrorKind::Io);
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+fn test_iter_fallible<H: Handles>() {
+    let mut bookworm = store_with_corrupt_page::<H>();
+    let results: Vec<BookwormResult<TestData>> = bookworm.iter_fallible(0).collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().count, 0);
+    let err = results[1].as_ref().unwrap_err();
+    assert_eq!(err.kind(), error::ErrorKind::Deserialization);
+    assert_eq!(err.page(), Some(1));
+    assert_eq!(results[2].as_ref().unwrap().count, 2);
+    // the silent iterator stops at the corrupt page
+    assert_eq!(bookworm.iter::<TestData>(0).count(), 1);
+
+    // the raw iterator reports pages that can't be read
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap);
+    bookworm.set_layout(PageLayout::Checksummed(ChecksumAlgorithm::Crc32));
+    bookworm.push_all(0..3u32).unwrap();
+    data_source.access().get_mut()[16 + 9] ^= 0xFF;
+    let results: Vec<BookwormResult<Vec<u8>>> = bookworm.raw_iter_fallible(0).collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &0u32.to_le_bytes());
+    assert_eq!(results[1].as_ref().unwrap_err().mismatch().unwrap().page, 1);
+    assert_eq!(results[2].as_ref().unwrap(), &2u32.to_le_bytes());
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct BorrowedRecord<'a> {
+    name: &'a str,
+    bytes: &'a [u8],
+    count: u32,
+}
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct OwnedRecord {
+    name: String,
+    bytes: Vec<u8>,
+    count: u32,
+}
+fn test_get_page_ref<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(64, data_source, swap);
+    for count in 0..3 {
+        bookworm
+            .push(&BorrowedRecord {
+                name: &format!("record {}", count),
+                bytes: &[count as u8; 5],
+                count,
+            })
+            .unwrap();
+    }
+    let mut buf = Vec::new();
+    for page in 0..3 {
+        let owned: OwnedRecord = bookworm.get_page(page).unwrap();
+        let borrowed: BorrowedRecord = bookworm.get_page_ref(page, &mut buf).unwrap();
+        assert_eq!(borrowed.name, owned.name);
+        assert_eq!(borrowed.bytes, owned.bytes);
+        assert_eq!(borrowed.count, owned.count);
+        let name_at = borrowed.name.as_ptr() as usize;
+        let bytes_at = borrowed.bytes.as_ptr() as usize;
+        let range = buf.as_ptr() as usize..buf.as_ptr() as usize + buf.len();
+        assert!(range.contains(&name_at) && range.contains(&bytes_at));
+    }
+
+    // chained records are gathered into the buffer first
+    bookworm.clear().unwrap();
+    bookworm.set_layout(PageLayout::Chained);
+    let long = "a".repeat(150);
+    bookworm
+        .push(&BorrowedRecord {
+            name: &long,
+            bytes: &[],
+            count: 9,
+        })
+        .unwrap();
+    let borrowed: BorrowedRecord = bookworm.get_page_ref(0, &mut buf).unwrap();
+    assert_eq!(borrowed.name, long);
+    assert_eq!(
+        bookworm
+            .get_page_ref::<BorrowedRecord>(10, &mut buf)
+            .unwrap_err()
+            .kind(),
+        error::ErrorKind::PageOutOfRange
+    );
+}
+
+fn test_page_cache<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap).with_cache(2);
+    bookworm.push_all(0..4u32).unwrap();
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 1);
+    data_source.access().inner.set_position(0);
+    let (bytes_read, seeks) = {
+        let storage = data_source.access();
+        (storage.bytes_read, storage.seeks)
+    };
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 1);
+    assert_eq!(bookworm.get_raw_page(1).unwrap()[..4], 1u32.to_le_bytes());
+    assert_eq!(data_source.access().inner.position(), 0);
+    assert_eq!(data_source.access().bytes_read, bytes_read);
+    assert_eq!(data_source.access().seeks, seeks);
+    let metrics = bookworm.metrics();
+    assert_eq!((metrics.cache_hits, metrics.cache_misses), (2, 1));
+
+    // the least recently used page makes room
+    bookworm.get_page::<u32>(0).unwrap();
+    bookworm.get_page::<u32>(2).unwrap();
+    assert_eq!(bookworm.cached_pages(), 2);
+    bookworm.get_page::<u32>(1).unwrap();
+    assert_eq!(bookworm.metrics().cache_misses, 4);
+
+    // writes drop the copies of the pages they touch
+    bookworm.set(1, &10u32).unwrap();
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 10);
+    bookworm.pop().unwrap();
+    bookworm.push(&30u32).unwrap();
+    assert_eq!(bookworm.get_page::<u32>(3).unwrap(), 30);
+    bookworm.get_page::<u32>(2).unwrap();
+    bookworm.delete(0).unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![10, 2, 30]);
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 2);
+    bookworm.truncate(0).unwrap();
+    assert_eq!(bookworm.cached_pages(), 0);
+    bookworm.get_page::<u32>(0).unwrap_err();
+}
+
+fn test_write_back<H: Handles>() {
+    let data_source = H::wrap(CountingStorage::default());
+    let swap = H::wrap(CountingStorage::default());
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap.clone());
+    bookworm.push_all(0..5u32).unwrap();
+    bookworm.set_write_back(true).unwrap();
+    let writes = data_source.access().writes;
+    for value in 1..=5u32 {
+        bookworm.set(1, &(value * 10)).unwrap();
+    }
+    assert_eq!(data_source.access().writes, writes);
+    assert_eq!(bookworm.dirty_pages(), vec![1]);
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 50);
+    assert_eq!(
+        bookworm.iter_range::<u32>(..).collect::<Vec<_>>(),
+        vec![0, 50, 2, 3, 4]
+    );
+    bookworm.flush().unwrap();
+    assert_eq!(data_source.access().writes, writes + 1);
+    assert!(bookworm.dirty_pages().is_empty());
+
+    // adjacent dirty pages go back with a single write
+    bookworm.set(3, &30u32).unwrap();
+    bookworm.set(2, &20u32).unwrap();
+    bookworm.set(0, &1u32).unwrap();
+    assert_eq!(bookworm.dirty_pages(), vec![0, 2, 3]);
+    bookworm.flush().unwrap();
+    assert_eq!(data_source.access().writes, writes + 3);
+
+    // removing pages reads through the dirty ones and drops those it overwrites
+    bookworm.set(4, &40u32).unwrap();
+    bookworm.set(0, &2u32).unwrap();
+    bookworm.pop().unwrap();
+    assert_eq!(bookworm.dirty_pages(), vec![0]);
+    bookworm.delete(1).unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![2, 20, 30]);
+
+    // dropping writes the dirty pages back
+    bookworm.set(2, &300u32).unwrap();
+    drop(bookworm);
+    let mut bookworm = H::bookworm(16, data_source, swap);
+    assert_eq!(bookworm.get_page::<u32>(2).unwrap(), 300);
+}
+
+fn test_durability<H: Handles>() {
+    let counts = |durability| {
+        let data_source = H::wrap(CountingStorage::default());
+        let swap = H::wrap(CountingStorage::default());
+        let mut bookworm = H::bookworm(16, data_source.clone(), swap).with_durability(durability);
+        let mut counts = Vec::new();
+        let mut record = |bookworm: &mut Over<H, CountingStorage>| {
+            let storage = data_source.access();
+            counts.push((storage.flushes, storage.syncs));
+            drop(storage);
+            bookworm.get_page::<u32>(0).unwrap();
+        };
+        bookworm.push(&1u32).unwrap();
+        bookworm.push(&2u32).unwrap();
+        record(&mut bookworm);
+        bookworm.set(0, &3u32).unwrap();
+        record(&mut bookworm);
+        bookworm.pop().unwrap();
+        record(&mut bookworm);
+        bookworm.push(&4u32).unwrap();
+        // a delete persists the data source before clearing its marker from the swap
+        bookworm.delete(0).unwrap();
+        record(&mut bookworm);
+        bookworm.sync_all().unwrap();
+        record(&mut bookworm);
+        drop(bookworm);
+        let storage = data_source.access();
+        counts.push((storage.flushes, storage.syncs));
+        counts
+    };
+    assert_eq!(
+        counts(Durability::None),
+        vec![(0, 0), (0, 0), (0, 0), (1, 1), (2, 2), (3, 2)]
+    );
+    assert_eq!(
+        counts(Durability::FlushEveryWrite),
+        vec![(2, 0), (3, 0), (4, 0), (7, 1), (8, 2), (9, 2)]
+    );
+    assert_eq!(
+        counts(Durability::SyncEveryWrite),
+        vec![(2, 2), (3, 3), (4, 4), (7, 7), (8, 8), (9, 8)]
+    );
+    assert_eq!(
+        counts(Durability::Manual),
+        vec![(0, 0), (0, 0), (0, 0), (1, 1), (2, 2), (2, 2)]
+    );
+}
+
+fn test_transaction<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source, swap.clone());
+    for value in 0..5u32 {
+        bookworm.push(&value).unwrap();
+    }
+
+    let mut transaction = bookworm.begin();
+    transaction.set(0, &10u32).unwrap();
+    transaction.delete(2).unwrap();
+    transaction.push(&11u32).unwrap();
+    assert_eq!(transaction.len(), 5);
+    let Err(err) = transaction.set(5, &12u32) else {
+        panic!("staged a page past the end");
+    };
+    assert_eq!(err.kind(), ErrorKind::PageOutOfRange);
+    transaction.commit().unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![10, 1, 3, 4, 11]);
+    // The journal is cleared once applied
+    assert_eq!(swap.access().get_ref()[4], 0);
+
+    let mut transaction = bookworm.begin();
+    transaction.delete(0).unwrap();
+    transaction.push(&12u32).unwrap();
+    transaction.rollback();
+    {
+        let mut transaction = bookworm.begin();
+        transaction.set(1, &13u32).unwrap();
+    }
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![10, 1, 3, 4, 11]);
+
+    bookworm.set_layout(PageLayout::Chained);
+    let mut transaction = bookworm.begin();
+    transaction.push(&14u32).unwrap();
+    let Err(err) = transaction.commit() else {
+        panic!("committed chained records");
+    };
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_transaction_crash_recovery() {
+    let data_source = Rc::new(RefCell::new(CountingStorage::default()));
+    let swap = Rc::new(RefCell::new(CountingStorage::default()));
+    let mut bookworm = Bookworm::new(16, data_source.clone(), swap.clone());
+    for value in 0..4u32 {
+        bookworm.push(&value).unwrap();
+    }
+
+    // The journal reaches the swap, then the data source fails while it gets applied
+    let writes = data_source.borrow().writes;
+    data_source.borrow_mut().fail_writes_after = Some(writes + 1);
+    let mut transaction = bookworm.begin();
+    transaction.set(3, &20u32).unwrap();
+    transaction.delete(0).unwrap();
+    transaction.push(&21u32).unwrap();
+    assert!(transaction.commit().is_err());
+    let Err(err) = bookworm.get_page::<u32>(0) else {
+        panic!("read from a half applied transaction");
+    };
+    assert!(err.to_string().contains("poisoned"));
+
+    let snapshot = |storage: &Rc<RefCell<CountingStorage>>| {
+        Rc::new(RefCell::new(Cursor::new(
+            storage.borrow().inner.get_ref().clone(),
+        )))
+    };
+    let (data_source, swap) = (snapshot(&data_source), snapshot(&swap));
+    drop(bookworm);
+
+    let mut recovered = Bookworm::new(16, data_source.clone(), swap.clone());
+    assert_eq!(recovered.metrics().replayed_journals, 1);
+    assert_eq!(recovered.to_vec::<u32>().unwrap(), vec![1, 2, 20, 21]);
+    drop(recovered);
+
+    // Replaying twice does nothing
+    let mut reopened = Bookworm::new(16, data_source, swap);
+    assert_eq!(reopened.metrics().replayed_journals, 0);
+    assert_eq!(reopened.to_vec::<u32>().unwrap(), vec![1, 2, 20, 21]);
+}
+
+#[cfg(feature = "wal")]
+#[test]
+fn test_wal_mode() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(16, data_source.clone(), swap);
+    let expected_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let expected_swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut expected = Bookworm::new(16, expected_source.clone(), expected_swap);
+    for value in 0..3u32 {
+        bookworm.push(&value).unwrap();
+        expected.push(&value).unwrap();
+    }
+    let stored = data_source.borrow().get_ref().clone();
+
+    bookworm.set_wal_mode(true).unwrap();
+    bookworm.push(&3u32).unwrap();
+    assert_eq!(bookworm.get_page::<u32>(3).unwrap(), 3);
+    bookworm.set(1, &10u32).unwrap();
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 10);
+    bookworm.delete(0).unwrap();
+    assert_eq!(bookworm.get_page::<u32>(0).unwrap(), 10);
+    assert_eq!(bookworm.get_page::<u32>(2).unwrap(), 3);
+    bookworm.push(&4u32).unwrap();
+    bookworm.set(3, &11u32).unwrap();
+    assert_eq!(bookworm.len(), 4);
+    assert_eq!(bookworm.wal_entries(), 5);
+    let Err(err) = bookworm.get_page::<u32>(4) else {
+        panic!("read a page past the logged end");
+    };
+    assert_eq!(err.kind(), ErrorKind::PageOutOfRange);
+    // Nothing reached the data source yet
+    assert_eq!(data_source.borrow().get_ref(), &stored);
+
+    assert_eq!(bookworm.checkpoint().unwrap().entries_retired, 5);
+    assert_eq!(bookworm.wal_entries(), 0);
+    expected.push(&3u32).unwrap();
+    expected.set(1, &10u32).unwrap();
+    expected.delete(0).unwrap();
+    expected.push(&4u32).unwrap();
+    expected.set(3, &11u32).unwrap();
+    assert_eq!(
+        expected.to_vec::<u32>().unwrap(),
+        bookworm.to_vec::<u32>().unwrap()
+    );
+    assert_eq!(
+        data_source.borrow().get_ref(),
+        expected_source.borrow().get_ref()
+    );
+
+    // Operations that don't go through the log apply it first
+    bookworm.push(&5u32).unwrap();
+    bookworm.swap_pages(0, 1).unwrap();
+    assert_eq!(bookworm.wal_entries(), 0);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![2, 10, 3, 11, 5]);
+    bookworm.set_wal_mode(false).unwrap();
+    assert!(!bookworm.wal_mode());
+}
+
+#[cfg(feature = "wal")]
+#[test]
+fn test_wal_reads() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(32, data_source.clone(), swap);
+    bookworm.set_layout(PageLayout::LengthPrefixed);
+    bookworm.push_all(["a", "b", "c"]).unwrap();
+    let stored = data_source.borrow().get_ref().clone();
+    let stored_bytes = bookworm.storage_bytes().unwrap();
+
+    // reads look the log up instead of applying it
+    bookworm.set_wal_mode(true).unwrap();
+    bookworm.set(1, &"set").unwrap();
+    bookworm.delete(0).unwrap();
+    bookworm.push(&"pushed").unwrap();
+    assert_eq!(bookworm.wal_entries(), 3);
+    assert_eq!(
+        bookworm.get_raw_page(0).unwrap(),
+        bookworm.pager.serialize(&"set").unwrap()
+    );
+    let mut buf = vec![0; 32];
+    let len = bookworm.get_raw_page_into(1, &mut buf).unwrap();
+    assert_eq!(&buf[..len], bookworm.pager.serialize(&"c").unwrap());
+    bookworm.get_raw_page_into(1, &mut [0; 4]).unwrap_err();
+    assert_eq!(bookworm.first::<String>().unwrap().unwrap(), "set");
+    assert_eq!(bookworm.last::<String>().unwrap().unwrap(), "pushed");
+    assert_eq!(
+        bookworm.last_raw().unwrap().unwrap(),
+        bookworm.pager.serialize(&"pushed").unwrap()
+    );
+    let mut buf = Vec::new();
+    assert_eq!(
+        bookworm.get_page_ref::<&str>(2, &mut buf).unwrap(),
+        "pushed"
+    );
+    assert_eq!(bookworm.get_page_ref::<&str>(1, &mut buf).unwrap(), "c");
+    assert_eq!(bookworm.to_vec::<String>().unwrap(), ["set", "c", "pushed"]);
+    assert!(bookworm.verify_page_as::<String>(2).unwrap().is_clean());
+    assert_eq!(bookworm.storage_bytes().unwrap(), stored_bytes);
+    assert_eq!(bookworm.wal_entries(), 3);
+    assert_eq!(data_source.borrow().get_ref(), &stored);
+
+    // cached values follow logged writes
+    assert_eq!(*bookworm.get_page_cached::<String>(1).unwrap(), "c");
+    bookworm.set(1, &"again").unwrap();
+    assert_eq!(*bookworm.get_page_cached::<String>(1).unwrap(), "again");
+    assert_eq!(*bookworm.get_page_cached::<String>(2).unwrap(), "pushed");
+    bookworm.delete(0).unwrap();
+    assert_eq!(*bookworm.get_page_cached::<String>(1).unwrap(), "pushed");
+
+    // iterators read the data source, so the log gets applied first
+    bookworm.set(0, &"first").unwrap();
+    bookworm.push(&"last").unwrap();
+    let records: Vec<String> = bookworm.iter(0).collect();
+    assert_eq!(records, ["first", "pushed", "last"]);
+    assert_eq!(bookworm.wal_entries(), 0);
+    bookworm.set(2, &"end").unwrap();
+    let raw: Vec<Vec<u8>> = bookworm.raw_iter(1).collect();
+    assert_eq!(raw[1], bookworm.pager.serialize(&"end").unwrap());
+    bookworm.push(&"tail").unwrap();
+    assert_eq!(
+        bookworm.iter_range::<String>(3..).collect::<Vec<_>>(),
+        ["tail"]
+    );
+    bookworm.push(&"owned").unwrap();
+    let owned: Vec<String> = bookworm.into_iter().collect();
+    assert_eq!(owned, ["first", "pushed", "end", "tail", "owned"]);
+}
+
+#[cfg(feature = "wal")]
+#[test]
+fn test_page_guard_wal() {
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(16, data_source.clone(), swap);
+    bookworm.push_all(0..3u32).unwrap();
+    bookworm.set_wal_mode(true).unwrap();
+    let stored = data_source.borrow().get_ref().clone();
+
+    // the guard is logged like `set`, leaving the data source alone until a checkpoint
+    bookworm.set(0, &9u32).unwrap();
+    *bookworm.get_mut::<u32>(0).unwrap() += 1;
+    *bookworm.get_mut::<u32>(1).unwrap() = 10;
+    assert_eq!(bookworm.wal_entries(), 3);
+    assert_eq!(*data_source.borrow().get_ref(), stored);
+    assert_eq!(bookworm.get_page::<u32>(0).unwrap(), 10);
+    assert_eq!(bookworm.get_page::<u32>(1).unwrap(), 10);
+}
+#[cfg(feature = "wal")]
+#[test]
+fn test_wal_checkpoint() {
+    type Shared = Rc<RefCell<CountingStorage>>;
+    type Fault = Box<dyn Fn(&mut CountingStorage)>;
+    let stored = |bytes: &[u8]| {
+        Rc::new(RefCell::new(CountingStorage {
+            inner: Cursor::new(bytes.to_vec()),
+            ..Default::default()
+        }))
+    };
+    // four logged entries over four stored pages, not checkpointed yet
+    let logged = || {
+        let (data_source, swap): (Shared, Shared) = (stored(&[]), stored(&[]));
+        let mut bookworm = Bookworm::new(16, data_source.clone(), swap.clone())
+            .with_durability(Durability::Manual);
+        bookworm.push_all(0..4u32).unwrap();
+        bookworm.set_wal_mode(true).unwrap();
+        bookworm.set(1, &10u32).unwrap();
+        bookworm.delete(0).unwrap();
+        bookworm.push(&7u32).unwrap();
+        bookworm.set(0, &20u32).unwrap();
+        (bookworm, data_source, swap)
+    };
+    let expected = vec![20, 2, 3, 7];
+
+    let (mut bookworm, data_source, swap) = logged();
+    let log_pages = bookworm.wal_pages();
+    assert!(log_pages > 0);
+    let counts = |storage: &Shared| (storage.borrow().writes, storage.borrow().syncs);
+    let (data_before, swap_before) = (counts(&data_source), counts(&swap));
+    let report = bookworm.checkpoint().unwrap();
+    assert_eq!(
+        report,
+        CheckpointReport {
+            entries_retired: 4,
+            log_pages,
+        }
+    );
+    assert_eq!(bookworm.wal_entries(), 0);
+    assert_eq!(bookworm.checkpoint().unwrap().entries_retired, 0);
+    let data_writes = counts(&data_source).0 - data_before.0;
+    let swap_writes = counts(&swap).0 - swap_before.0;
+    assert!(data_writes > 0 && swap_writes > 2);
+    assert!(counts(&data_source).1 > data_before.1);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), expected);
+    let checkpointed = data_source.borrow().inner.get_ref().clone();
+
+    // a crash at any write or flush of the checkpoint is finished when reopening, and the log
+    // is only reset once the applied pages were synced
+    let mut faults: Vec<(bool, Fault)> = Vec::new();
+    for writes in 0..data_writes {
+        faults.push((
+            true,
+            Box::new(move |storage| storage.fail_writes_after = Some(storage.writes + writes)),
+        ));
+    }
+    for writes in 0..swap_writes {
+        faults.push((
+            false,
+            Box::new(move |storage| storage.fail_writes_after = Some(storage.writes + writes)),
+        ));
+    }
+    faults.push((true, Box::new(|storage| storage.fail_flush = true)));
+    faults.push((false, Box::new(|storage| storage.fail_flush = true)));
+    for (on_data_source, fault) in faults {
+        let (mut bookworm, data_source, swap) = logged();
+        let stored_before = data_source.borrow().inner.get_ref().clone();
+        let syncs = counts(&data_source).1;
+        fault(
+            &mut match on_data_source {
+                true => &data_source,
+                false => &swap,
+            }
+            .borrow_mut(),
+        );
+        bookworm.checkpoint().unwrap_err();
+        let data_bytes = data_source.borrow().inner.get_ref().clone();
+        if !on_data_source && data_bytes != stored_before {
+            assert_eq!(data_bytes, checkpointed);
+            assert!(counts(&data_source).1 > syncs);
+        }
+        let swap_bytes = swap.borrow().inner.get_ref().clone();
+        drop(bookworm);
+
+        let mut reopened = Bookworm::new(16, stored(&data_bytes), stored(&swap_bytes));
+        assert!(!reopened.is_poisoned());
+        assert!([0, 4].contains(&reopened.wal_entries()));
+        assert_eq!(reopened.to_vec::<u32>().unwrap(), expected);
+        reopened.checkpoint().unwrap();
+        assert_eq!(reopened.wal_entries(), 0);
+        assert_eq!(reopened.to_vec::<u32>().unwrap(), expected);
+    }
+
+    // past the configured size the log is checkpointed as entries are appended
+    let (mut bookworm, data_source, _) = logged();
+    bookworm.checkpoint().unwrap();
+    bookworm.set_wal_checkpoint_pages(Some(4));
+    let replayed = bookworm.metrics().replayed_journals;
+    for value in 0..10u32 {
+        bookworm.push(&value).unwrap();
+        assert!(bookworm.wal_pages() <= 4);
+    }
+    assert!(bookworm.metrics().replayed_journals >= replayed + 3);
+    assert!(bookworm.wal_entries() < 10);
+    let stored_pages = data_source.borrow().inner.get_ref().len() / 16;
+    assert!(stored_pages >= bookworm.len() - bookworm.wal_entries());
+    let mut records = expected.clone();
+    records.extend(0..10);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), records);
+}
+#[cfg(feature = "wal")]
+fn test_wal_torn_tail<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap.clone());
+    bookworm.push(&0u32).unwrap();
+    bookworm.set_wal_mode(true).unwrap();
+    bookworm.push(&1u32).unwrap();
+    bookworm.set(0, &2u32).unwrap();
+    bookworm.push(&3u32).unwrap();
+    drop(bookworm);
+
+    // The last entry only made it halfway
+    let mut log = swap.access().get_ref().clone();
+    log.truncate(log.len() - 3);
+    let swap = H::wrap(Cursor::new(log));
+    let mut recovered = H::bookworm(16, data_source.clone(), swap.clone());
+    assert!(recovered.wal_mode());
+    assert_eq!(recovered.wal_entries(), 2);
+    assert_eq!(recovered.len(), 2);
+    assert_eq!(recovered.get_page::<u32>(0).unwrap(), 2);
+    assert_eq!(recovered.get_page::<u32>(1).unwrap(), 1);
+
+    // Entries written after recovery replace the torn one
+    recovered.push(&4u32).unwrap();
+    drop(recovered);
+    let mut reopened = H::bookworm(16, data_source, swap);
+    assert_eq!(reopened.wal_entries(), 3);
+    reopened.checkpoint().unwrap();
+    assert_eq!(reopened.to_vec::<u32>().unwrap(), vec![2, 1, 4]);
+}
+
+#[test]
+fn test_interrupted_delete() {
+    // The first half of deleting page 1 by hand: the following pages staged in the swap, the
+    // marker written and the copy back stopped after one page
+    let data_source = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let swap = Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new(16, data_source.clone(), swap.clone());
+    for value in 0..4u32 {
+        bookworm.push(&value).unwrap();
+    }
+    let staged = bookworm.get_raw_page(2).unwrap();
+    let mut swap_bytes = DeleteMarker::new(1, 2, 4).to_bytes().to_vec();
+    for page in 2..4 {
+        swap_bytes.extend_from_slice(&bookworm.get_raw_page(page).unwrap());
+    }
+    bookworm.set_raw(1, &staged).unwrap();
+    drop(bookworm);
+    *swap.borrow_mut().get_mut() = swap_bytes;
+
+    let mut recovered = Bookworm::new(16, data_source, swap.clone());
+    assert!(!recovered.is_poisoned());
+    assert_eq!(recovered.to_vec::<u32>().unwrap(), vec![0, 2, 3]);
+    assert!(swap.borrow().get_ref()[..DELETE_MARKER_BYTES as usize]
+        .iter()
+        .all(|byte| *byte == 0));
+
+    // A delete failing while copying back is finished by reopening, or by `recover`, whether
+    // it copies back from memory as it does by default or from the swap
+    for shift_memory in [None, Some(0)] {
+        let data_source = Rc::new(RefCell::new(CountingStorage::default()));
+        let swap = Rc::new(RefCell::new(CountingStorage::default()));
+        let mut bookworm = Bookworm::new(16, data_source.clone(), swap.clone());
+        if let Some(bytes) = shift_memory {
+            bookworm.set_shift_memory(bytes);
+        }
+        for value in 0..6u32 {
+            bookworm.push(&value).unwrap();
+        }
+        let writes = data_source.borrow().writes;
+        data_source.borrow_mut().fail_writes_after = Some(writes);
+        bookworm.delete(1).unwrap_err();
+        assert!(bookworm.is_poisoned());
+
+        let snapshot = |storage: &Rc<RefCell<CountingStorage>>| {
+            Rc::new(RefCell::new(Cursor::new(
+                storage.borrow().inner.get_ref().clone(),
+            )))
+        };
+        // the crash may as well come after page 2 was copied over page 1
+        let torn = snapshot(&data_source);
+        torn.borrow_mut().get_mut().copy_within(32..48, 16);
+        let mut reopened = Bookworm::new(16, torn, snapshot(&swap));
+        assert!(!reopened.is_poisoned());
+        assert_eq!(reopened.to_vec::<u32>().unwrap(), vec![0, 2, 3, 4, 5]);
+
+        data_source.borrow_mut().fail_writes_after = None;
+        assert!(bookworm.recover().unwrap());
+        assert!(!bookworm.is_poisoned());
+        assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![0, 2, 3, 4, 5]);
+        assert!(!bookworm.recover().unwrap());
+    }
+}
+
+fn test_snapshot_restore<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap);
+    for value in 0..5u32 {
+        bookworm.push(&value).unwrap();
+    }
+    bookworm.pop().unwrap();
+    bookworm.set_write_back(true).unwrap();
+    bookworm.set(0, &9u32).unwrap();
+
+    let mut snapshot = Vec::new();
+    assert_eq!(bookworm.snapshot_to(&mut snapshot).unwrap(), 4 * 16);
+    assert_eq!(snapshot.len(), 4 * 16);
+    assert_eq!(snapshot[..4], 9u32.to_le_bytes());
+
+    bookworm.push(&5u32).unwrap();
+    bookworm.set(1, &10u32).unwrap();
+    bookworm.delete(2).unwrap();
+    assert_eq!(bookworm.restore_from(&mut &snapshot[..]).unwrap(), 4);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![9, 1, 2, 3]);
+    let mut again = Vec::new();
+    bookworm.snapshot_to(&mut again).unwrap();
+    assert_eq!(again, snapshot);
+    bookworm.flush().unwrap();
+    assert_eq!(data_source.access().get_ref()[..4 * 16], snapshot[..]);
+
+    // A snapshot cut partway through a page is refused before anything changes
+    let Err(err) = bookworm.restore_from(&mut &snapshot[..snapshot.len() - 3]) else {
+        panic!("restored a torn snapshot");
+    };
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![9, 1, 2, 3]);
+}
+
+fn test_snapshot_restore_header<H: Handles>() {
+    let open = |page_size| {
+        let data_source = H::wrap(Cursor::new(Vec::new()));
+        let swap = H::wrap(Cursor::new(Vec::new()));
+        H::open(page_size, PageLayout::LengthPrefixed, data_source, swap).unwrap()
+    };
+    let mut bookworm = open(16);
+    bookworm.push(&TestData::new(1, true)).unwrap();
+    bookworm.push(&TestData::new(2, false)).unwrap();
+    let mut snapshot = Vec::new();
+    assert_eq!(bookworm.snapshot_to(&mut snapshot).unwrap(), 16 + 2 * 16);
+    assert_eq!(&snapshot[..4], b"BKWM");
+
+    let mut copy = open(16);
+    assert_eq!(copy.restore_from(&mut &snapshot[..]).unwrap(), 2);
+    assert_eq!(
+        copy.to_vec::<TestData>().unwrap(),
+        bookworm.to_vec::<TestData>().unwrap()
+    );
+    let Err(err) = open(32).restore_from(&mut &snapshot[..]) else {
+        panic!("restored pages of another size");
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not restore: snapshot has 16 byte LengthPrefixed pages"
+    );
+}
+
+fn test_import_from<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut source = H::bookworm(32, data_source, swap);
+    for i in 0..70 {
+        source.push(&TestData::new(i, i % 2 == 0)).unwrap();
+    }
+    let mut exported = Vec::new();
+    for record in source.to_vec::<TestData>().unwrap() {
+        let serialized = bincode::serialize(&record).unwrap();
+        exported.extend_from_slice(&(serialized.len() as u32).to_le_bytes());
+        exported.extend_from_slice(&serialized);
+    }
+
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(32, data_source, swap);
+    bookworm.push(&TestData::new(200, false)).unwrap();
+    let imported = bookworm
+        .import_from::<TestData, _>(&exported[..], ImportFormat::LengthPrefixed)
+        .unwrap();
+    assert_eq!(imported, 70);
+    assert_eq!(bookworm.len(), 71);
+    assert!(bookworm.iter::<TestData>(1).eq(source.iter::<TestData>(0)));
+
+    // A record too large for a page stops the import after the ones before it
+    let mut stream = exported[..2 * 6].to_vec();
+    stream.extend_from_slice(&40u32.to_le_bytes());
+    stream.extend_from_slice(&[0; 40]);
+    stream.extend_from_slice(&exported[..6]);
+    let Err(err) = bookworm.import_from::<TestData, _>(&stream[..], ImportFormat::LengthPrefixed)
+    else {
+        panic!("imported a record bigger than a page");
+    };
+    assert_eq!(err.kind(), ErrorKind::DataTooLarge);
+    assert_eq!(
+        err.to_string(),
+        "Could not import record 2: Record is 40 bytes long but pages hold 32"
+    );
+    assert_eq!(bookworm.len(), 73);
+    assert_eq!(
+        bookworm.get_page::<TestData>(72).unwrap(),
+        TestData::new(1, false)
+    );
+
+    let Err(err) =
+        bookworm.import_from::<TestData, _>(&exported[..3], ImportFormat::LengthPrefixed)
+    else {
+        panic!("imported a torn length");
+    };
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(bookworm.len(), 73);
+}
+
+#[test]
+fn test_repage() {
+    let empty = || Rc::new(RefCell::new(Cursor::new(Vec::new())));
+    let records: Vec<String> = (0..20).map(|i| "x".repeat(i * 5)).collect();
+    for layout in [PageLayout::Padded, PageLayout::LengthPrefixed] {
+        let mut bookworm = Bookworm::new(1024, empty(), empty());
+        bookworm.set_layout(layout);
+        bookworm.push_all(&records).unwrap();
+        let mut repaged = bookworm.repage(128, empty(), empty()).unwrap();
+        assert_eq!(repaged.len(), 20);
+        assert_eq!(repaged.to_vec::<String>().unwrap(), records);
+        assert_eq!(bookworm.to_vec::<String>().unwrap(), records);
+    }
+
+    let mut bookworm = Bookworm::new(1024, empty(), empty());
+    bookworm.push_all(&records[..3]).unwrap();
+    bookworm.push(&"x".repeat(200)).unwrap();
+    let Err(err) = bookworm.repage(128, empty(), empty()) else {
+        panic!("repaged a record bigger than the new pages");
+    };
+    assert_eq!(err.kind(), ErrorKind::DataTooLarge);
+    assert_eq!(err.page(), Some(3));
+    assert_eq!(
+        err.to_string(),
+        "Could not repage page 3: Could not write data to page: data is bigger than page"
+    );
+    assert_eq!(bookworm.len(), 4);
+}
+
+#[test]
+fn test_new_shared() {
+    let data_source = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+    let swap = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+    let mut bookworm = Bookworm::new_shared(32, data_source.clone(), swap);
+    bookworm.push(&TestData::new(1, true)).unwrap();
+    let mut bookworm = std::thread::spawn(move || {
+        for i in 2..5 {
+            bookworm.push(&TestData::new(i, false)).unwrap();
+        }
+        bookworm
+    })
+    .join()
+    .unwrap();
+    assert_eq!(bookworm.len(), 4);
+    assert_eq!(
+        bookworm.get_page::<TestData>(3).unwrap(),
+        TestData::new(4, false)
+    );
+    drop(bookworm);
+
+    let mut reopened = Bookworm::new_shared(
+        32,
+        data_source,
+        Arc::new(Mutex::new(Cursor::new(Vec::new()))),
+    );
+    assert_eq!(reopened.len(), 4);
+    assert_eq!(
+        reopened.get_page::<TestData>(0).unwrap(),
+        TestData::new(1, true)
+    );
+}
+
+#[cfg(feature = "background-flush")]
+#[test]
+fn test_background_flush() {
+    let wait_clean = |bookworm: &Bookworm<CountingStorage, BincodeCodec, Arc<Mutex<_>>>| {
+        let deadline = SystemTime::now() + Duration::from_secs(5);
+        while !bookworm.dirty_pages().is_empty() {
+            assert!(SystemTime::now() < deadline, "dirty pages never flushed");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    };
+    let data_source = Arc::new(Mutex::new(CountingStorage::default()));
+    let swap = Arc::new(Mutex::new(CountingStorage::default()));
+    let mut bookworm =
+        Bookworm::new_shared(16, data_source.clone(), swap).with_durability(Durability::Manual);
+    bookworm.push_all(0..4u32).unwrap();
+    bookworm.set_write_back(true).unwrap();
+    let (writes, flushes, syncs) =
+        data_source.with(|storage| (storage.writes, storage.flushes, storage.syncs));
+    bookworm.set(1, &10u32).unwrap();
+    bookworm.set(2, &20u32).unwrap();
+    assert_eq!(bookworm.dirty_pages(), vec![1, 2]);
+
+    bookworm
+        .start_background_flush(Duration::from_millis(5))
+        .unwrap();
+    assert!(bookworm.background_flush_running());
+    bookworm
+        .start_background_flush(Duration::from_millis(5))
+        .unwrap_err();
+    wait_clean(&bookworm);
+    data_source.with(|storage| {
+        assert!(storage.writes > writes);
+        assert!(storage.flushes > flushes);
+        assert!(storage.syncs > syncs);
+    });
+    // the stream is left where the bookworm had it
+    bookworm.push(&40u32).unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![0, 10, 20, 3, 40]);
+
+    // pages dirtied after the thread stopped go out with the final flush
+    bookworm.stop_background_flush().unwrap();
+    assert!(!bookworm.background_flush_running());
+    bookworm.set(3, &30u32).unwrap();
+    bookworm.stop_background_flush().unwrap();
+    assert!(bookworm.dirty_pages().is_empty());
+    assert!(bookworm.take_flush_error().is_none());
+    drop(bookworm);
+    let stored = Arc::into_inner(data_source).unwrap().into_inner().unwrap();
+    let data_source = Arc::new(Mutex::new(stored));
+    let swap = Arc::new(Mutex::new(CountingStorage::default()));
+    let mut reopened = Bookworm::new_shared(16, data_source.clone(), swap);
+    assert_eq!(reopened.to_vec::<u32>().unwrap(), vec![0, 10, 20, 30, 40]);
+
+    // errors are kept until taken while the pages stay dirty
+    reopened.set_write_back(true).unwrap();
+    reopened.set(0, &1u32).unwrap();
+    data_source.with(|storage| storage.fail_writes_after = Some(storage.writes));
+    reopened
+        .start_background_flush(Duration::from_millis(1))
+        .unwrap();
+    let deadline = SystemTime::now() + Duration::from_secs(5);
+    let err = loop {
+        if let Some(err) = reopened.take_flush_error() {
+            break err;
+        }
+        assert!(SystemTime::now() < deadline, "flush never failed");
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(reopened.dirty_pages(), vec![0]);
+    data_source.with(|storage| storage.fail_writes_after = None);
+    wait_clean(&reopened);
+    reopened.stop_background_flush().unwrap();
+    assert_eq!(reopened.get_page::<u32>(0).unwrap(), 1);
+}
+
+#[test]
+fn test_from_owned() {
+    fn workload<H: SharedStorage<Cursor<Vec<u8>>>>(
+        bookworm: &mut Bookworm<Cursor<Vec<u8>>, BincodeCodec, H>,
+    ) -> Vec<TestData> {
+        bookworm.set_shift_memory(0);
+        for i in 0..6 {
+            bookworm.push(&TestData::new(i, i % 2 == 0)).unwrap();
+        }
+        bookworm.set(2, &TestData::new(20, true)).unwrap();
+        bookworm.delete(0).unwrap();
+        bookworm.pop().unwrap();
+        bookworm.to_vec().unwrap()
+    }
+    let empty = || Cursor::new(Vec::new());
+    let mut owned = Bookworm::from_owned(32, empty(), empty());
+    let mut shared = Bookworm::new(
+        32,
+        Rc::new(RefCell::new(empty())),
+        Rc::new(RefCell::new(empty())),
+    );
+    assert_eq!(workload(&mut owned), workload(&mut shared));
+    let (owned_source, owned_swap) = owned.into_owned().ok().unwrap();
+    let (shared_source, shared_swap) = shared.try_unwrap_inner().ok().unwrap();
+    assert_eq!(owned_source.get_ref(), shared_source.get_ref());
+    assert_eq!(owned_swap.get_ref(), shared_swap.get_ref());
+
+    let bytes = owned_source.get_ref().clone();
+    let bookworm = Bookworm::from_owned(32, owned_source, empty());
+    let mut iter = PageIterator::<_, TestData, _, _>::from(bookworm);
+    assert_eq!(iter.next(), Some(TestData::new(1, false)));
+    let data_source = Arc::try_unwrap(iter.into_parts()).ok().unwrap();
+    assert_eq!(data_source.into_inner().unwrap().into_inner(), bytes);
+
+    // a storage still shared hands the bookworm back untouched
+    let data_source = Arc::new(Mutex::new(empty()));
+    let mut bookworm = Bookworm::new_shared(32, data_source.clone(), Arc::new(Mutex::new(empty())));
+    bookworm.push(&TestData::new(3, true)).unwrap();
+    let Err(bookworm) = bookworm.into_owned() else {
+        panic!("the data source is still shared");
+    };
+    assert_eq!(bookworm.len(), 1);
+    drop(data_source);
+    let (data_source, _) = bookworm.into_owned().ok().unwrap();
+    assert_eq!(data_source.get_ref().len(), 32);
+}
+
+#[test]
+fn test_open_file() {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("bookworm-open-file-{}", std::process::id()));
+    let mut bookworm = Bookworm::open_file(&path, 32).unwrap();
+    assert!(bookworm.is_empty());
+    for i in 0..3 {
+        bookworm.push(&TestData::new(i, true)).unwrap();
+    }
+    drop(bookworm);
+
+    let mut reopened = Bookworm::open_file(&path, 32).unwrap();
+    assert_eq!(reopened.len(), 3);
+    assert_eq!(
+        reopened.get_page::<TestData>(2).unwrap(),
+        TestData::new(2, true)
+    );
+    reopened.delete(0).unwrap();
+    drop(reopened);
+    let Err(err) = Bookworm::open_file(&path, 40) else {
+        panic!("opened a file with the wrong page size");
+    };
+    assert_eq!(err.kind(), ErrorKind::Corrupted);
+    let swap_path = format!("{}.swap", path.display());
+    assert!(std::path::Path::new(&swap_path).exists());
+
+    let other_swap = dir.join(format!("bookworm-open-file-{}-swap", std::process::id()));
+    let mut reopened = Bookworm::open_with_swap(&path, &other_swap, 32).unwrap();
+    assert_eq!(
+        reopened.to_vec::<TestData>().unwrap()[0],
+        TestData::new(1, true)
+    );
+    drop(reopened);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(swap_path).unwrap();
+    std::fs::remove_file(other_swap).unwrap();
+}
+
+fn test_compact_and_sort_framed_layouts<H: Handles>() {
+    check_compact_and_sort::<H>(PageLayout::LengthPrefixed);
+    for algorithm in ChecksumAlgorithm::ALL {
+        check_compact_and_sort::<H>(PageLayout::Checksummed(algorithm));
+    }
+}
+
+/// Whole pages get moved around as they are, without framing them a second time
+fn check_compact_and_sort<H: Handles>(layout: PageLayout) {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(48, data_source, swap);
+    bookworm.set_layout(layout);
+    bookworm.push_all([5u32, 2, 8, 1, 6, 3, 9, 4]).unwrap();
+
+    assert_eq!(bookworm.retain(|record: &u32| *record != 8).unwrap(), 1);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![5, 2, 1, 6, 3, 9, 4]);
+    assert!(!bookworm.is_poisoned());
+
+    bookworm.set_raw(2, &[]).unwrap();
+    assert_eq!(bookworm.compact().unwrap().pages_reclaimed, 1);
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![5, 2, 6, 3, 9, 4]);
+
+    // Runs of two pages spill to the swap and get merged back
+    bookworm.set_sort_memory(2);
+    bookworm.sort_by_key(|record: &u32| *record).unwrap();
+    assert_eq!(bookworm.to_vec::<u32>().unwrap(), vec![2, 3, 4, 5, 6, 9]);
+    assert!(!bookworm.is_poisoned());
+}
+fn test_read_write_at_layouts<H: Handles>() {
+    check_read_write_at::<H>(PageLayout::LengthPrefixed);
+    check_read_write_at::<H>(PageLayout::Chained);
+    for algorithm in ChecksumAlgorithm::ALL {
+        check_read_write_at::<H>(PageLayout::Checksummed(algorithm));
+    }
+}
+
+/// Offsets count from the start of the payload and stop at what a page holds
+fn check_read_write_at<H: Handles>(layout: PageLayout) {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(48, data_source, swap);
+    bookworm.set_layout(layout);
+    bookworm.push_raw(b"abcd").unwrap();
+    bookworm.push_raw(b"efgh").unwrap();
+    let capacity = match layout {
+        PageLayout::LengthPrefixed => 44,
+        PageLayout::Chained => 39,
+        PageLayout::Checksummed(algorithm) => 43 - algorithm.digest_len(),
+        PageLayout::Padded => 48,
+    };
+
+    assert_eq!(bookworm.read_at(0, 0, 4).unwrap(), b"abcd");
+    bookworm.read_at(0, capacity - 2, 2).unwrap();
+    bookworm.read_at(0, capacity - 1, 2).unwrap_err();
+    if let PageLayout::Checksummed(_) = layout {
+        bookworm.write_at(0, 1, b"xy").unwrap_err();
+        assert_eq!(bookworm.get_raw_page(0).unwrap(), b"abcd");
+        return;
+    }
+    bookworm.write_at(0, 1, b"xy").unwrap();
+    assert_eq!(bookworm.read_at(0, 0, 4).unwrap(), b"axyd");
+    assert_eq!(bookworm.get_raw_page(0).unwrap(), b"axyd");
+    bookworm.write_at(0, capacity - 1, b"zz").unwrap_err();
+
+    // pages held in memory take the same offsets
+    bookworm.set_write_back(true).unwrap();
+    bookworm.set_raw(1, b"ijkl").unwrap();
+    bookworm.write_at(1, 2, b"mn").unwrap();
+    assert_eq!(bookworm.read_at(1, 0, 4).unwrap(), b"ijmn");
+    bookworm.set_write_back(false).unwrap();
+    assert_eq!(bookworm.get_raw_page(1).unwrap(), b"ijmn");
+}
+fn test_page_iterator_fused<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(4, data_source.clone(), swap.clone());
+    bookworm.push_all([true, false, true, true]).unwrap();
+    bookworm.set_raw(1, &[7]).unwrap();
+
+    let mut iter = bookworm.into_iter::<bool>();
+    assert_eq!(iter.next(), Some(true));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+
+    let mut iter = H::bookworm(4, data_source, swap).into_iter::<bool>();
+    assert_eq!(iter.nth(1), None);
+    assert_eq!(iter.next(), None);
+
+    // the borrowing iterators stop for good at a page whose length can't be right
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(8, data_source, swap);
+    for page in [[1, 0, 0, 0, 1], [0xff; 5], [1, 0, 0, 0, 1], [1, 0, 0, 0, 1]] {
+        bookworm.push_raw(&page).unwrap();
+    }
+    bookworm.set_layout(PageLayout::LengthPrefixed);
+
+    let mut iter = bookworm.raw_iter(0);
+    assert_eq!(iter.next(), Some(vec![1]));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+    let mut iter = bookworm.raw_iter(0);
+    assert_eq!(iter.next_back(), Some(vec![1]));
+    assert_eq!(iter.next_back(), Some(vec![1]));
+    assert_eq!(iter.next_back(), None);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+
+    let mut iter = bookworm.iter::<bool>(0);
+    assert_eq!(iter.next(), Some(true));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+    let mut iter = bookworm.iter::<bool>(0);
+    assert_eq!(iter.next_back(), Some(true));
+    assert_eq!(iter.next_back(), Some(true));
+    assert_eq!(iter.next_back(), None);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+}
+fn test_verify_page<H: Handles>() {
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::open(
+        32,
+        PageLayout::Checksummed(ChecksumAlgorithm::Crc32),
+        data_source.clone(),
+        swap,
+    )
+    .unwrap();
+    bookworm
+        .push_all((0..4).map(|count| TestData::new(count, true)))
+        .unwrap();
+
+    // a clean page passes every check, decoding only when asked to
+    let verification = bookworm.verify_page(0).unwrap();
+    assert_eq!(verification.header, Check::Passed);
+    assert_eq!(verification.checksum, Check::Passed);
+    assert_eq!(verification.decode, Check::Skipped);
+    assert!(verification.is_clean());
+    let verification = bookworm.verify_page_as::<TestData>(0).unwrap();
+    assert_eq!(verification.decode, Check::Passed);
+    let verification = bookworm.verify_page_as::<String>(0).unwrap();
+    assert!(matches!(verification.decode, Check::Failed(_)));
+    assert!(!verification.is_clean());
+    bookworm.verify_page(4).unwrap_err();
+
+    // a flipped payload byte fails the checksum
+    let page_at = |page: usize| 16 + page * 32;
+    data_source.access().get_mut()[page_at(1) + 9] ^= 0xFF;
+    // and a length past the page fails the header, leaving the checksum unchecked
+    data_source.access().get_mut()[page_at(2)] = 0xFF;
+    let stored = data_source.access().get_ref().clone();
+    let verification = bookworm.verify_page_as::<TestData>(1).unwrap();
+    assert_eq!(verification.header, Check::Passed);
+    assert!(matches!(verification.checksum, Check::Failed(_)));
+    assert_eq!(verification.decode, Check::Skipped);
+    assert_eq!(verification.mismatch.map(|mismatch| mismatch.page), Some(1));
+    let err = verification.into_result().unwrap_err();
+    assert_eq!(err.mismatch().map(|mismatch| mismatch.page), Some(1));
+    let verification = bookworm.verify_page(2).unwrap();
+    assert!(matches!(verification.header, Check::Failed(_)));
+    assert_eq!(verification.checksum, Check::Skipped);
+    assert_eq!(verification.mismatch, None);
+    assert_eq!(
+        verification.into_result().unwrap_err().kind(),
+        ErrorKind::Corrupted
+    );
+    assert_eq!(data_source.access().get_ref(), &stored);
+
+    // the full scan runs the same checks
+    let failed = bookworm.verify().unwrap();
+    assert_eq!(
+        failed.iter().map(|failed| failed.page).collect::<Vec<_>>(),
+        [1, 2]
+    );
+    assert_eq!(failed[0], bookworm.verify_page(1).unwrap());
+
+    // so does a file header that no longer matches the store
+    data_source.access().get_mut()[4] = 9;
+    let verification = bookworm.verify_page(0).unwrap();
+    assert!(matches!(verification.header, Check::Failed(_)));
+    assert_eq!(bookworm.verify().unwrap().len(), 4);
+    data_source.access().get_mut()[4] = stored[4];
+
+    // verify on read catches what a cached copy would hide
+    let data_source = H::wrap(Cursor::new(Vec::new()));
+    let swap = H::wrap(Cursor::new(Vec::new()));
+    let mut bookworm = H::bookworm(16, data_source.clone(), swap).with_cache(4);
+    bookworm.set_layout(PageLayout::Chained);
+    bookworm.push(&TestData::new(1, true)).unwrap();
+    bookworm.get_page::<TestData>(0).unwrap();
+    data_source.access().get_mut()[8] = 0x80;
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(1, true)
+    );
+    bookworm.set_verify_on_read(true);
+    let err = bookworm.get_page::<TestData>(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Corrupted);
+    assert_eq!(
+        err.to_string(),
+        "Could not read page: unknown chain flags 0x80"
+    );
+    data_source.access().get_mut()[8] = 0;
+    assert_eq!(
+        bookworm.get_page::<TestData>(0).unwrap(),
+        TestData::new(1, true)
+    );
+}
@@ -0,0 +1,291 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    rc::Rc,
+};
+
+use serde::Serialize;
+
+use crate::{
+    checksum::crc32,
+    codec::{BincodeCodec, Codec},
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+const JOURNAL_MAGIC: &[u8; 4] = b"BKWJ";
+/// Magic, commit marker, entries count, resulting pages count, checksum of the entries and
+/// generation of the log the journal follows
+const JOURNAL_HEADER_BYTES: usize = 40;
+const COMMITTED: u8 = 1;
+
+/// Where a journal starts within the swap. Transactions journal at the start, a checkpoint
+/// right past the write ahead log it folds, naming its generation so the journal can't be
+/// mistaken for a later log's.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct JournalSpot {
+    pub offset: u64,
+    pub generation: u64,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Starts staging changes that land together on `Transaction::commit`, or not at all
+    pub fn begin(&mut self) -> Transaction<'_, S, C, H> {
+        let len = self.len();
+        Transaction {
+            bookworm: self,
+            staged: Vec::new(),
+            len,
+        }
+    }
+    /// Finishes whatever an interrupted delete, commit or checkpoint left in the swap,
+    /// returning whether there was anything
+    pub(crate) fn recover_swap(&mut self) -> BookwormResult<bool> {
+        let finished_delete = self.finish_interrupted_delete()?;
+        #[cfg(feature = "wal")]
+        if let Some(spot) = self.recover_log()? {
+            let replayed = self.replay_journal(spot)?;
+            if replayed {
+                self.forget_log();
+            }
+            return Ok(finished_delete || replayed);
+        }
+        Ok(self.replay_journal(JournalSpot::default())? || finished_delete)
+    }
+    /// Applies the journal left at `spot` by a commit that was interrupted after it was
+    /// written, returning whether there was one
+    pub(crate) fn replay_journal(&mut self, spot: JournalSpot) -> BookwormResult<bool> {
+        if self.swap.unframed_len()? < spot.offset + JOURNAL_HEADER_BYTES as u64 {
+            return Ok(false);
+        }
+        let mut header = [0; JOURNAL_HEADER_BYTES];
+        self.swap.read_unframed(spot.offset, &mut header)?;
+        let generation = u64::from_le_bytes(header[32..40].try_into().unwrap());
+        if &header[..4] != JOURNAL_MAGIC || header[4] != COMMITTED || generation != spot.generation
+        {
+            return Ok(false);
+        }
+        let entries = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+        let pages_count = u64::from_le_bytes(header[16..24].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(header[24..28].try_into().unwrap());
+        let entry_bytes = 8 + self.pager.page_size;
+        let mut entry = vec![0; entry_bytes];
+        let mut checksums = Vec::with_capacity(entries * 4);
+        for index in 0..entries {
+            self.swap
+                .read_unframed(journal_offset(spot, index, entry_bytes), &mut entry)?;
+            checksums.extend_from_slice(&crc32(&entry).to_le_bytes());
+        }
+        if crc32(&checksums) != checksum {
+            return Err(BookwormError::new(
+                ErrorKind::Corrupted,
+                "Could not replay journal: entries don't match the checksum".to_owned(),
+            ));
+        }
+        self.poisoned = true;
+        for index in 0..entries {
+            self.swap
+                .read_unframed(journal_offset(spot, index, entry_bytes), &mut entry)?;
+            let page = u64::from_le_bytes(entry[..8].try_into().unwrap()) as usize;
+            if page < self.pager.pages_count {
+                self.pager.write_raw_pages(page, &entry[8..])?;
+            } else if page == self.pager.pages_count {
+                self.pager.append_pages(&entry[8..])?;
+            } else {
+                return Err(BookwormError::new(
+                    ErrorKind::Corrupted,
+                    format!("Could not replay journal: page {} leaves a gap", page),
+                ));
+            }
+        }
+        if pages_count < self.pager.pages_count {
+            self.pager.zero_pages(pages_count..self.pager.pages_count)?;
+            self.pager.pages_count = pages_count;
+        }
+        self.invalidate_decoded(..);
+        self.journal_barrier(true)?;
+        // The log goes first, a journal past it is only looked for while the log is there
+        self.swap.write_unframed(0, &[0; JOURNAL_HEADER_BYTES])?;
+        self.journal_barrier(false)?;
+        if spot.offset != 0 {
+            self.swap
+                .write_unframed(spot.offset, &[0; JOURNAL_HEADER_BYTES])?;
+            self.journal_barrier(false)?;
+        }
+        self.poisoned = false;
+        self.metrics.replayed_journals += 1;
+        Ok(true)
+    }
+    /// Journals the resulting pages from the lowest one the staged changes touch, skipping
+    /// the ones that stay as they are, then replays the journal onto the data source
+    pub(crate) fn commit_staged(
+        &mut self,
+        staged: Vec<Staged>,
+        spot: JournalSpot,
+    ) -> BookwormResult<()> {
+        let pages_count = self.pager.pages_count;
+        let Some(lowest) = staged
+            .iter()
+            .map(|change| match change {
+                Staged::Set(page, _) | Staged::Delete(page) => *page,
+                Staged::Push(_) => pages_count,
+            })
+            .min()
+        else {
+            return Ok(());
+        };
+        self.check_dense()?;
+        self.check_unchained("commit")?;
+        let mut sources: Vec<Source> = (lowest..pages_count).map(Source::Stored).collect();
+        for change in staged {
+            match change {
+                Staged::Set(page, raw_page) => sources[page - lowest] = Source::Staged(raw_page),
+                Staged::Push(raw_page) => sources.push(Source::Staged(raw_page)),
+                Staged::Delete(page) => {
+                    sources.remove(page - lowest);
+                }
+            }
+        }
+        let entry_bytes = 8 + self.pager.page_size;
+        let mut entry = vec![0; entry_bytes];
+        let mut checksums = Vec::new();
+        for (page, source) in (lowest..).zip(&sources) {
+            entry[..8].copy_from_slice(&(page as u64).to_le_bytes());
+            match source {
+                Source::Stored(from) if *from == page => continue,
+                Source::Stored(from) => self.pager.read_page_into(*from, &mut entry[8..])?,
+                Source::Staged(raw_page) => entry[8..].copy_from_slice(raw_page),
+            }
+            let offset = journal_offset(spot, checksums.len() / 4, entry_bytes);
+            self.swap.write_unframed(offset, &entry)?;
+            checksums.extend_from_slice(&crc32(&entry).to_le_bytes());
+        }
+        self.journal_barrier(false)?;
+        let mut header = [0; JOURNAL_HEADER_BYTES];
+        header[..4].copy_from_slice(JOURNAL_MAGIC);
+        header[4] = COMMITTED;
+        header[8..16].copy_from_slice(&(checksums.len() as u64 / 4).to_le_bytes());
+        header[16..24].copy_from_slice(&((lowest + sources.len()) as u64).to_le_bytes());
+        header[24..28].copy_from_slice(&crc32(&checksums).to_le_bytes());
+        header[32..40].copy_from_slice(&spot.generation.to_le_bytes());
+        self.swap.write_unframed(spot.offset, &header)?;
+        self.journal_barrier(false)?;
+        self.replay_journal(spot)?;
+        Ok(())
+    }
+    /// Flushes the swap, or the data source when `primary`, and syncs it when the bookworm
+    /// knows how to
+    pub(crate) fn journal_barrier(&mut self, primary: bool) -> BookwormResult<()> {
+        if primary {
+            self.pager.write_back()?;
+            self.pager.persist(self.sync)
+        } else {
+            self.swap.persist(self.sync)
+        }
+    }
+}
+
+/// Byte offset of a journal entry within the swap
+fn journal_offset(spot: JournalSpot, index: usize, entry_bytes: usize) -> u64 {
+    spot.offset + (JOURNAL_HEADER_BYTES + index * entry_bytes) as u64
+}
+
+/// Changes staged on a bookworm, none of which reach it before `commit`. Dropping the
+/// transaction rolls it back.
+pub struct Transaction<
+    'a,
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    staged: Vec<Staged>,
+    /// Pages count once the staged changes are applied
+    len: usize,
+}
+
+pub(crate) enum Staged {
+    Set(usize, Vec<u8>),
+    Push(Vec<u8>),
+    Delete(usize),
+}
+
+/// Where a page ends up taking its bytes from once the staged changes are applied
+enum Source {
+    Stored(usize),
+    Staged(Vec<u8>),
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Transaction<'_, S, C, H> {
+    /// Number of pages there will be once the staged changes are applied
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Stages overwriting a page, as numbered after the changes staged before
+    pub fn set<T: Serialize>(&mut self, page: usize, data: &T) -> BookwormResult<()> {
+        self.check_page(page)?;
+        let raw_page = self.stage(data)?;
+        self.staged.push(Staged::Set(page, raw_page));
+        Ok(())
+    }
+    pub fn push<T: Serialize>(&mut self, data: &T) -> BookwormResult<()> {
+        let raw_page = self.stage(data)?;
+        self.staged.push(Staged::Push(raw_page));
+        self.len += 1;
+        Ok(())
+    }
+    /// Stages removing a page and shifting the following ones back, as numbered after the
+    /// changes staged before
+    pub fn delete(&mut self, page: usize) -> BookwormResult<()> {
+        self.check_page(page)?;
+        self.staged.push(Staged::Delete(page));
+        self.len -= 1;
+        Ok(())
+    }
+    /// Journals the staged changes in the swap, then applies them. A crash after the journal
+    /// was written gets it replayed the next time a bookworm is created over the storages.
+    pub fn commit(self) -> BookwormResult<()> {
+        let staged = self.staged;
+        self.bookworm.in_context(OpKind::Write, |bookworm| {
+            bookworm.commit_staged(staged, JournalSpot::default())
+        })
+    }
+    /// Discards the staged changes, same as dropping the transaction
+    pub fn rollback(self) {}
+    fn check_page(&self, page: usize) -> BookwormResult<()> {
+        if page >= self.len {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                format!(
+                    "Page {} is out of range: only {} pages exist",
+                    page, self.len
+                ),
+            ));
+        }
+        Ok(())
+    }
+    fn stage<T: Serialize>(&self, data: &T) -> BookwormResult<Vec<u8>> {
+        let serialized = self.bookworm.pager.serialize(data)?;
+        self.bookworm.stage_whole_page(&serialized)
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Lays a record out as a single page, refusing records that would take more
+    pub(crate) fn stage_whole_page(&self, serialized: &[u8]) -> BookwormResult<Vec<u8>> {
+        let pager = &self.pager;
+        let mut raw_page = Vec::with_capacity(pager.page_size);
+        pager.stage_page(serialized, &mut raw_page)?;
+        if raw_page.len() != pager.page_size {
+            return Err(BookwormError::new(
+                ErrorKind::DataTooLarge,
+                "Could not stage data: it takes more than a page".to_owned(),
+            ));
+        }
+        Ok(raw_page)
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{Cursor, Read, Seek, Write},
+    rc::Rc,
+};
+
+use serde::Serialize;
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm, COPY_BATCH_PAGES,
+};
+
+/// Storages that can be cut down to a given length
+pub trait Truncate {
+    fn truncate(&mut self, len: u64) -> std::io::Result<()>;
+}
+
+impl Truncate for File {
+    fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+        self.set_len(len)
+    }
+}
+
+impl Truncate for Cursor<Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+        self.get_mut().truncate(len as usize);
+        Ok(())
+    }
+}
+
+impl<S: Read + Write + Seek + Truncate> Bookworm<S> {
+    /// Creates a bookworm over an empty data source holding every item, written in large
+    /// sequential chunks. When an item doesn't fit, the error names its page, which is also
+    /// its index, and the data source is cut back to nothing.
+    pub fn from_iter<T: Serialize>(
+        page_size: usize,
+        data_source: Rc<RefCell<S>>,
+        swap: Rc<RefCell<S>>,
+        items: impl IntoIterator<Item = T>,
+    ) -> BookwormResult<Self> {
+        let mut bookworm = Self::new(page_size, data_source, swap);
+        if bookworm.pager.stored_bytes()? != 0 {
+            return Err(BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Could not load items: data source is not empty".to_owned(),
+            ));
+        }
+        if let Err(err) = bookworm.push_all(items) {
+            bookworm.pager.shrink_to(0)?;
+            return Err(err);
+        }
+        Ok(bookworm)
+    }
+}
+
+impl<S: Read + Write + Seek + Truncate, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Moves the pages from `at` on into a new bookworm over an empty data source, then cuts
+    /// them off this one's data source so reopening it doesn't bring them back
+    pub fn split_off<S2: Read + Write + Seek>(
+        &mut self,
+        at: usize,
+        new_source: Rc<RefCell<S2>>,
+        new_swap: Rc<RefCell<S2>>,
+    ) -> BookwormResult<Bookworm<S2, C>> {
+        self.in_context(OpKind::Truncate, |bookworm| {
+            bookworm.check_dense()?;
+            let page_size = bookworm.pager.page_size;
+            let pages_count = bookworm.pager.pages_count;
+            if at != pages_count {
+                bookworm.check_page(at)?;
+            }
+            let codec = bookworm.pager.codec.clone();
+            let mut tail = Bookworm::with_codec(page_size, new_source, new_swap, codec);
+            if tail.pager.stored_bytes()? != 0 {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not split off: new data source is not empty".to_owned(),
+                ));
+            }
+            tail.set_layout(bookworm.pager.layout);
+            if bookworm.pager.has_header() {
+                tail.pager.count_source = bookworm.pager.count_source;
+                tail.pager.open_header()?;
+            }
+            let mut buf = vec![0; page_size * COPY_BATCH_PAGES.min(pages_count - at)];
+            for start in (at..pages_count).step_by(COPY_BATCH_PAGES) {
+                let run = &mut buf[..page_size * COPY_BATCH_PAGES.min(pages_count - start)];
+                bookworm.pager.read_pages_into(start, run)?;
+                tail.pager.append_pages(run)?;
+            }
+            tail.pager.record_count()?;
+            bookworm.invalidate_decoded(at..);
+            bookworm.pager.shrink_to(at)?;
+            Ok(tail)
+        })
+    }
+    /// Removes every page by cutting the data source and the swap down to nothing, so a
+    /// bookworm opened over them later starts empty as well
+    pub fn clear(&mut self) -> BookwormResult<()> {
+        self.in_context(OpKind::Truncate, |bookworm| {
+            bookworm.invalidate_decoded(..);
+            bookworm.pager.shrink_to(0)?;
+            bookworm.swap.shrink_to(0)?;
+            // No delete is in progress, the room for its marker goes too
+            bookworm
+                .swap
+                .data_source
+                .access()
+                .truncate(0)
+                .map_err(|err| {
+                    BookwormError::new(ErrorKind::Io, "Could not truncate swap".to_owned())
+                        .with_source(err)
+                })
+        })
+    }
+}
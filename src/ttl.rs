@@ -0,0 +1,98 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    marker::PhantomData,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    codec::{BincodeCodec, Codec},
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager,
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Records pushed with a ttl are stored as `(expires_at, data)`, where `expires_at` is the
+/// expiration in milliseconds since the unix epoch, as the leading field of the record
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Replaces the clock used to stamp records pushed with a ttl
+    pub fn set_clock(&mut self, clock: impl Fn() -> SystemTime + Send + 'static) {
+        self.clock = Box::new(clock);
+    }
+    pub fn push_with_ttl<T: Serialize>(&mut self, data: &T, ttl: Duration) -> BookwormResult<()> {
+        let expires_at = to_millis((self.clock)() + ttl)?;
+        self.push(&(expires_at, data))
+    }
+    /// Removes every expired page in a single compaction pass, returning how many were removed.
+    /// Only the expiration of each record is decoded, failures are handled like in `retain`.
+    pub fn purge_expired(&mut self, now: SystemTime) -> BookwormResult<usize> {
+        let now = to_millis(now)?;
+        self.in_context(OpKind::Compact, |bookworm| {
+            bookworm.compact_decoded(|expires_at: u64| expires_at > now)
+        })
+    }
+    /// Iterates over the records that haven't expired according to the clock, without
+    /// removing the expired ones
+    pub fn iter_unexpired<T: DeserializeOwned>(
+        &mut self,
+    ) -> BookwormResult<UnexpiredIter<'_, S, T, C, H>> {
+        let now = to_millis((self.clock)())?;
+        Ok(UnexpiredIter {
+            bookworm: self,
+            curr_pos: 0,
+            now,
+            _marker: PhantomData,
+        })
+    }
+}
+
+fn to_millis(time: SystemTime) -> BookwormResult<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .map_err(|err| {
+            BookwormError::new(
+                ErrorKind::InvalidInput,
+                "Time is before the unix epoch".to_string(),
+            )
+            .with_source(err)
+        })
+}
+
+pub struct UnexpiredIter<
+    'a,
+    S: Read + Write + Seek,
+    T: DeserializeOwned,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    curr_pos: usize,
+    now: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<S, T, C, H> Iterator for UnexpiredIter<'_, S, T, C, H>
+where
+    S: Read + Write + Seek,
+    H: SharedStorage<S>,
+    C: Codec,
+    T: DeserializeOwned,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let stored = &mut self.bookworm.pager;
+        loop {
+            self.curr_pos = pager::first_live(&stored.free, self.curr_pos, stored.pages_count);
+            let (expires_at, data) = stored.get_page::<(u64, T)>(self.curr_pos).ok()?;
+            self.curr_pos += 1;
+            if expires_at > self.now {
+                return Some(data);
+            }
+        }
+    }
+}
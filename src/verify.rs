@@ -0,0 +1,174 @@
+use std::io::{Read, Seek, Write};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    codec::Codec,
+    error::{BookwormError, BookwormResult, ChecksumMismatch, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+/// Outcome of one of the checks run over a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Check {
+    Passed,
+    Failed(String),
+    /// Nothing to check, like the checksum of pages laid out without one
+    Skipped,
+}
+
+/// What checking a single page found, see `Bookworm::verify_page`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageVerification {
+    pub page: usize,
+    /// The file header still matches the store and the page header holds a length that fits
+    pub header: Check,
+    /// Skipped when the header failed
+    pub checksum: Check,
+    /// Skipped unless a type to decode the page as was given and the other checks passed
+    pub decode: Check,
+    /// The digests involved when the checksum failed
+    pub mismatch: Option<ChecksumMismatch>,
+}
+
+impl PageVerification {
+    pub(crate) fn new(page: usize) -> Self {
+        Self {
+            page,
+            header: Check::Skipped,
+            checksum: Check::Skipped,
+            decode: Check::Skipped,
+            mismatch: None,
+        }
+    }
+    pub fn is_clean(&self) -> bool {
+        ![&self.header, &self.checksum, &self.decode]
+            .into_iter()
+            .any(|check| matches!(check, Check::Failed(_)))
+    }
+    /// The first failure as the error a read would have given for it
+    pub fn into_result(self) -> BookwormResult<()> {
+        if let Some(mismatch) = self.mismatch {
+            return Err(BookwormError::checksum_mismatch(
+                mismatch.page,
+                mismatch.stored,
+                mismatch.computed,
+            ));
+        }
+        for (check, kind) in [
+            (self.header, ErrorKind::Corrupted),
+            (self.checksum, ErrorKind::Corrupted),
+            (self.decode, ErrorKind::Deserialization),
+        ] {
+            if let Check::Failed(reason) = check {
+                return Err(BookwormError::new(kind, reason).at_page(self.page));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// Checks the file header, the header of a page and its checksum without changing
+    /// anything. Corruption is reported in the verification, errors are left to reads that
+    /// can't be done at all.
+    pub fn verify_page(&mut self, page: usize) -> BookwormResult<PageVerification> {
+        self.verify_with(page, None::<fn(&mut Self) -> BookwormResult<()>>)
+    }
+    /// Same as `verify_page`, also decoding the page as `T` when the other checks pass
+    pub fn verify_page_as<T: DeserializeOwned>(
+        &mut self,
+        page: usize,
+    ) -> BookwormResult<PageVerification> {
+        self.verify_with(
+            page,
+            Some(|bookworm: &mut Self| {
+                #[cfg(feature = "wal")]
+                if bookworm.wal_mode() {
+                    return bookworm.logged_page::<T>(page).map(drop);
+                }
+                bookworm.pager.get_page::<T>(page).map(drop)
+            }),
+        )
+    }
+    /// Runs the checks of `verify_page` over every live page, returning the pages that failed
+    pub fn verify(&mut self) -> BookwormResult<Vec<PageVerification>> {
+        self.in_context(OpKind::Scan, |bookworm| {
+            let mut failed = Vec::new();
+            for page in 0..bookworm.pager.pages_count {
+                if bookworm.pager.free.contains(&page) {
+                    continue;
+                }
+                let verification = bookworm.verify_stored(page)?;
+                if !verification.is_clean() {
+                    failed.push(verification);
+                }
+            }
+            Ok(failed)
+        })
+    }
+    /// Makes `get_page` run the checks of `verify_page` first and fail with the corruption
+    /// they found, even for pages served from the cache. Off by default, as it costs a read of
+    /// the file header and of the page.
+    pub fn set_verify_on_read(&mut self, enabled: bool) {
+        self.verify_on_read = enabled;
+    }
+    /// Fails with what `verify_page` found when verify on read is enabled
+    pub(crate) fn verify_read(&mut self, page: usize) -> BookwormResult<()> {
+        match self.verify_on_read {
+            true => self.verify_stored(page)?.into_result(),
+            false => Ok(()),
+        }
+    }
+    fn verify_with(
+        &mut self,
+        page: usize,
+        decode: Option<impl FnOnce(&mut Self) -> BookwormResult<()>>,
+    ) -> BookwormResult<PageVerification> {
+        let verify = |bookworm: &mut Self| {
+            let mut verification = bookworm.verify_stored(page)?;
+            if let Some(decode) = decode.filter(|_| verification.is_clean()) {
+                verification.decode = match decode(bookworm) {
+                    Ok(()) => Check::Passed,
+                    Err(err) if err.kind() == ErrorKind::Io => return Err(err),
+                    Err(err) => Check::Failed(err.to_string()),
+                };
+            }
+            Ok(verification)
+        };
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            return self.in_logged_context(OpKind::Read, verify);
+        }
+        self.in_context(OpKind::Read, verify)
+    }
+    /// Checks a page as the data source holds it, or as it would once the write ahead log is
+    /// applied
+    fn verify_stored(&mut self, page: usize) -> BookwormResult<PageVerification> {
+        self.pager.check_live(page)?;
+        let file_header = match self.pager.has_header() {
+            true => match self.pager.check_file_header() {
+                Ok(()) => None,
+                Err(err) if err.kind() == ErrorKind::Io => return Err(err),
+                Err(err) => Some(err.to_string()),
+            },
+            false => None,
+        };
+        let mut raw_page = vec![0; self.pager.page_size];
+        #[cfg(feature = "wal")]
+        if self.wal_mode() {
+            self.logged_whole_page(page, &mut raw_page)?;
+        } else {
+            self.pager.read_unverified_into(page, &mut raw_page)?;
+        }
+        #[cfg(not(feature = "wal"))]
+        self.pager.read_unverified_into(page, &mut raw_page)?;
+        if let Some(reason) = file_header {
+            let mut verification = PageVerification::new(page);
+            verification.header = Check::Failed(reason);
+            return Ok(verification);
+        }
+        Ok(self.pager.verify_raw_page(page, &raw_page))
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Seek, Write},
+    ops::{Bound, RangeBounds},
+    rc::Rc,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    codec::{BincodeCodec, Codec},
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    storage::SharedStorage,
+    Bookworm,
+};
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// A view over every page, holding the bookworm borrowed so nothing changes underneath it
+    pub fn pages(&mut self) -> PagesView<'_, S, C, H> {
+        let end = self.pager.pages_count;
+        PagesView {
+            bookworm: self,
+            start: 0,
+            end,
+        }
+    }
+}
+
+/// A contiguous run of pages, indexed from the start of the run
+pub struct PagesView<
+    'a,
+    S: Read + Write + Seek,
+    C: Codec = BincodeCodec,
+    H: SharedStorage<S> = Rc<RefCell<S>>,
+> {
+    bookworm: &'a mut Bookworm<S, C, H>,
+    start: usize,
+    end: usize,
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> PagesView<'_, S, C, H> {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+    pub fn get<T: DeserializeOwned>(&mut self, index: usize) -> BookwormResult<T> {
+        let page = self.page_of(index)?;
+        self.bookworm
+            .in_context(OpKind::Read, |bookworm| bookworm.pager.get_page(page))
+    }
+    pub fn get_raw(&mut self, index: usize) -> BookwormResult<Vec<u8>> {
+        let page = self.page_of(index)?;
+        self.bookworm
+            .in_context(OpKind::Read, |bookworm| bookworm.pager.get_raw_page(page))
+    }
+    /// Narrows the view to `range`, given relative to this view
+    pub fn range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+    ) -> BookwormResult<PagesView<'_, S, C, H>> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        if start > end || end > self.len() {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                format!(
+                    "Range {}..{} is out of bounds of a view of {} pages",
+                    start,
+                    end,
+                    self.len()
+                ),
+            ));
+        }
+        Ok(PagesView {
+            bookworm: self.bookworm,
+            start: self.start + start,
+            end: self.start + end,
+        })
+    }
+    /// Iterates over the records of the pages in the view, skipping free ones
+    pub fn iter<T: DeserializeOwned>(&mut self) -> impl Iterator<Item = BookwormResult<T>> + '_ {
+        let bookworm = &mut *self.bookworm;
+        let free = bookworm.pager.free.clone();
+        (self.start..self.end)
+            .filter(move |page| !free.contains(page))
+            .map(move |page| {
+                bookworm.in_context(OpKind::Read, |bookworm| bookworm.pager.get_page(page))
+            })
+    }
+    pub fn iter_raw(&mut self) -> impl Iterator<Item = BookwormResult<Vec<u8>>> + '_ {
+        let bookworm = &mut *self.bookworm;
+        let free = bookworm.pager.free.clone();
+        (self.start..self.end)
+            .filter(move |page| !free.contains(page))
+            .map(move |page| {
+                bookworm.in_context(OpKind::Read, |bookworm| bookworm.pager.get_raw_page(page))
+            })
+    }
+    fn page_of(&self, index: usize) -> BookwormResult<usize> {
+        if index >= self.len() {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                format!(
+                    "Page {} is out of bounds of a view of {} pages",
+                    index,
+                    self.len()
+                ),
+            ));
+        }
+        Ok(self.start + index)
+    }
+}
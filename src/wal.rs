@@ -0,0 +1,429 @@
+use std::{
+    io::{Read, Seek, Write},
+    time::UNIX_EPOCH,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    checksum::crc32,
+    codec::Codec,
+    durability::Durability,
+    error::{BookwormError, BookwormResult, ErrorKind, OpKind},
+    pager::{payload_of, PageLayout},
+    storage::SharedStorage,
+    transaction::{JournalSpot, Staged},
+    Bookworm,
+};
+
+const LOG_MAGIC: &[u8; 4] = b"BKWL";
+/// Magic and generation
+const LOG_HEADER_BYTES: u64 = 16;
+/// Length and checksum of the body of an entry
+const ENTRY_HEADER_BYTES: u64 = 8;
+/// Opcode and page index leading the body of an entry
+const BODY_HEADER_BYTES: u64 = 9;
+const SET: u8 = 1;
+const PUSH: u8 = 2;
+const DELETE: u8 = 3;
+
+/// What a checkpoint applied to the data source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointReport {
+    /// Log entries applied and dropped from the log
+    pub entries_retired: usize,
+    /// Pages of the swap the log took before it was reset
+    pub log_pages: usize,
+}
+
+/// The write ahead log kept in the swap, along with an index of its entries
+pub(crate) struct Wal {
+    /// Tells this log's entries apart from the ones of earlier logs left past its end
+    generation: u64,
+    entries: Vec<LogEntry>,
+    /// Byte offset right past the last entry
+    end: u64,
+    /// Pages count once the entries are applied
+    len: usize,
+}
+
+struct LogEntry {
+    opcode: u8,
+    page: usize,
+    /// Byte offset of the serialized record within the swap, along with its length
+    payload: (u64, usize),
+}
+
+/// Where the freshest version of a page is
+enum Lookup {
+    Logged(usize),
+    Stored(usize),
+}
+
+impl Wal {
+    fn new(generation: u64, len: usize) -> Self {
+        Self {
+            generation,
+            entries: Vec::new(),
+            end: LOG_HEADER_BYTES,
+            len,
+        }
+    }
+    /// Follows a page back through the entries to the one that last wrote it, or to the page
+    /// it was stored at before any of them
+    fn lookup(&self, mut page: usize) -> Lookup {
+        let mut len = self.len;
+        for (index, entry) in self.entries.iter().enumerate().rev() {
+            match entry.opcode {
+                SET if entry.page == page => return Lookup::Logged(index),
+                PUSH => {
+                    len -= 1;
+                    if page == len {
+                        return Lookup::Logged(index);
+                    }
+                }
+                DELETE => {
+                    len += 1;
+                    if page >= entry.page {
+                        page += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Lookup::Stored(page)
+    }
+}
+
+impl<S: Read + Write + Seek, C: Codec, H: SharedStorage<S>> Bookworm<S, C, H> {
+    /// In write ahead log mode `push`, `set` and `delete` append an entry to a log kept in the
+    /// swap instead of touching the data source, and page reads such as `get_page`,
+    /// `get_raw_page`, `first` or `to_vec` look the log up first. `checkpoint` applies the log,
+    /// as do iterators and any other operation before they run. Turning the mode off
+    /// checkpoints.
+    pub fn set_wal_mode(&mut self, enabled: bool) -> BookwormResult<()> {
+        self.in_context(OpKind::Flush, |bookworm| {
+            if !enabled {
+                bookworm.checkpoint_log()?;
+                bookworm.wal = None;
+                return Ok(());
+            }
+            if bookworm.wal.is_some() {
+                return Ok(());
+            }
+            bookworm.check_dense()?;
+            if bookworm.free_list || bookworm.pager.layout == PageLayout::Chained {
+                return Err(BookwormError::new(
+                    ErrorKind::InvalidInput,
+                    "Could not log writes: pages must shift on delete and hold a record each"
+                        .to_owned(),
+                ));
+            }
+            let generation = (bookworm.clock)()
+                .duration_since(UNIX_EPOCH)
+                .map_or(1, |elapsed| elapsed.as_nanos() as u64);
+            bookworm.wal = Some(Wal::new(generation, bookworm.pager.pages_count));
+            Ok(())
+        })
+    }
+    pub fn wal_mode(&self) -> bool {
+        self.wal.is_some()
+    }
+    /// Number of log entries not applied to the data source yet
+    pub fn wal_entries(&self) -> usize {
+        self.wal.as_ref().map_or(0, |wal| wal.entries.len())
+    }
+    /// Size of the write ahead log in pages of the swap
+    pub fn wal_pages(&self) -> usize {
+        self.wal.as_ref().map_or(0, |wal| {
+            wal.end.div_ceil(self.swap.page_size as u64) as usize
+        })
+    }
+    /// Checkpoints on its own once appending an entry makes the log take more than `pages`
+    /// pages of the swap, `None` leaves the log growing until `checkpoint` is called
+    pub fn set_wal_checkpoint_pages(&mut self, pages: Option<usize>) {
+        self.wal_checkpoint_pages = pages;
+    }
+    /// Applies the write ahead log to the data source and empties it. The applied pages are
+    /// journaled first, then written, the data source synced and only then the log reset, so
+    /// that a crash at any point gets it finished the next time the storages are opened.
+    pub fn checkpoint(&mut self) -> BookwormResult<CheckpointReport> {
+        self.in_logged_context(OpKind::Write, Self::checkpoint_log)
+    }
+    pub(crate) fn checkpoint_log(&mut self) -> BookwormResult<CheckpointReport> {
+        let log_pages = self.wal_pages();
+        let Some(wal) = self.wal.as_ref().filter(|wal| !wal.entries.is_empty()) else {
+            return Ok(CheckpointReport {
+                entries_retired: 0,
+                log_pages,
+            });
+        };
+        let entries_retired = wal.entries.len();
+        let spot = JournalSpot {
+            offset: wal.end,
+            generation: wal.generation,
+        };
+        let mut staged = Vec::with_capacity(wal.entries.len());
+        for index in 0..wal.entries.len() {
+            let wal = self.wal.as_ref().expect("checked above");
+            let (opcode, page) = (wal.entries[index].opcode, wal.entries[index].page);
+            if opcode == DELETE {
+                staged.push(Staged::Delete(page));
+                continue;
+            }
+            let payload = self.logged_payload(index)?;
+            let raw_page = self.stage_whole_page(&payload)?;
+            staged.push(match opcode {
+                SET => Staged::Set(page, raw_page),
+                _ => Staged::Push(raw_page),
+            });
+        }
+        // replaying the journal writes the pages, syncs the data source, then resets the log
+        self.commit_staged(staged, spot)?;
+        self.forget_log();
+        Ok(CheckpointReport {
+            entries_retired,
+            log_pages,
+        })
+    }
+    /// Starts a new, empty log once the old one was applied
+    pub(crate) fn forget_log(&mut self) {
+        let len = self.pager.pages_count;
+        if let Some(wal) = &mut self.wal {
+            *wal = Wal::new(wal.generation.wrapping_add(1), len);
+        }
+    }
+    /// Picks up the log found in the swap, dropping a torn entry at its end, and returns
+    /// where a checkpoint of it would have put its journal
+    pub(crate) fn recover_log(&mut self) -> BookwormResult<Option<JournalSpot>> {
+        let stored = self.swap.unframed_len()?;
+        if stored < LOG_HEADER_BYTES {
+            return Ok(None);
+        }
+        let mut header = [0; LOG_HEADER_BYTES as usize];
+        self.swap.read_unframed(0, &mut header)?;
+        if &header[..4] != LOG_MAGIC {
+            return Ok(None);
+        }
+        let generation = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let mut wal = Wal::new(generation, self.pager.pages_count);
+        let mut entry_header = [0; ENTRY_HEADER_BYTES as usize];
+        while wal.end + ENTRY_HEADER_BYTES <= stored {
+            self.swap.read_unframed(wal.end, &mut entry_header)?;
+            let body_len = u32::from_le_bytes(entry_header[..4].try_into().unwrap()) as u64;
+            let checksum = u32::from_le_bytes(entry_header[4..].try_into().unwrap());
+            let body_at = wal.end + ENTRY_HEADER_BYTES;
+            if body_len < BODY_HEADER_BYTES || body_at + body_len > stored {
+                break;
+            }
+            let mut body = vec![0; body_len as usize];
+            self.swap.read_unframed(body_at, &mut body)?;
+            if entry_checksum(generation, &body) != checksum {
+                break;
+            }
+            let opcode = body[0];
+            let page = u64::from_le_bytes(body[1..9].try_into().unwrap()) as usize;
+            match opcode {
+                SET if page < wal.len => {}
+                PUSH => wal.len += 1,
+                DELETE if page < wal.len => wal.len -= 1,
+                _ => break,
+            }
+            wal.entries.push(LogEntry {
+                opcode,
+                page,
+                payload: (
+                    body_at + BODY_HEADER_BYTES,
+                    body.len() - BODY_HEADER_BYTES as usize,
+                ),
+            });
+            wal.end = body_at + body_len;
+        }
+        let spot = JournalSpot {
+            offset: wal.end,
+            generation,
+        };
+        self.wal = Some(wal);
+        Ok(Some(spot))
+    }
+    /// `push` in write ahead log mode
+    pub(crate) fn log_push<T: Serialize>(&mut self, data: &T) -> BookwormResult<()> {
+        let serialized = self.pager.serialize(data)?;
+        let page = self.len();
+        self.log(PUSH, page, &serialized)
+    }
+    /// `set` in write ahead log mode
+    pub(crate) fn log_set<T: Serialize>(&mut self, page: usize, data: &T) -> BookwormResult<()> {
+        self.check_logged_page(page)?;
+        let serialized = self.pager.serialize(data)?;
+        self.invalidate_decoded(page..=page);
+        self.log(SET, page, &serialized)
+    }
+    /// `delete` in write ahead log mode
+    pub(crate) fn log_delete(&mut self, page: usize) -> BookwormResult<()> {
+        self.check_logged_page(page)?;
+        self.invalidate_decoded(page..);
+        self.log(DELETE, page, &[])
+    }
+    /// `get_page` in write ahead log mode
+    pub(crate) fn logged_page<T: DeserializeOwned>(&mut self, page: usize) -> BookwormResult<T> {
+        match self.lookup(page)? {
+            Lookup::Logged(index) => {
+                let payload = self.logged_payload(index)?;
+                self.pager
+                    .decode_record(&payload)
+                    .map_err(|err| err.on_parse(page))
+            }
+            Lookup::Stored(stored) => self.pager.get_page(stored).map_err(|err| err.at_page(page)),
+        }
+    }
+    /// `get_page_ref` in write ahead log mode
+    pub(crate) fn logged_page_ref<'de, T: Deserialize<'de>>(
+        &mut self,
+        page: usize,
+        buf: &'de mut Vec<u8>,
+    ) -> BookwormResult<T> {
+        match self.lookup(page)? {
+            Lookup::Logged(index) => {
+                *buf = self.logged_payload(index)?;
+                self.pager
+                    .decode_borrowed(buf)
+                    .map_err(|err| err.on_parse(page))
+            }
+            Lookup::Stored(stored) => self
+                .pager
+                .get_page_ref(stored, buf)
+                .map_err(|err| err.at_page(page)),
+        }
+    }
+    /// `get_raw_page_into` in write ahead log mode, logged records are framed the way the
+    /// data source would hold them
+    pub(crate) fn logged_raw_page_into(
+        &mut self,
+        page: usize,
+        buf: &mut [u8],
+    ) -> BookwormResult<usize> {
+        match self.lookup(page)? {
+            Lookup::Logged(index) => {
+                self.pager.check_page_buffer(buf)?;
+                let payload = self.logged_payload(index)?;
+                let raw_page = self.stage_whole_page(&payload)?;
+                let data = payload_of(self.pager.layout, &raw_page)?;
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            }
+            Lookup::Stored(stored) => self
+                .pager
+                .get_raw_page_into(stored, buf)
+                .map_err(|err| err.at_page(page)),
+        }
+    }
+    /// `get_raw_page` in write ahead log mode
+    pub(crate) fn logged_raw_page(&mut self, page: usize) -> BookwormResult<Vec<u8>> {
+        let mut buf = vec![0; self.pager.page_size];
+        let len = self.logged_raw_page_into(page, &mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+    /// The whole page as the data source will hold it once the log is applied, stored pages
+    /// are read without checking their checksum
+    pub(crate) fn logged_whole_page(&mut self, page: usize, buf: &mut [u8]) -> BookwormResult<()> {
+        match self.lookup(page)? {
+            Lookup::Logged(index) => {
+                self.pager.check_page_buffer(buf)?;
+                let payload = self.logged_payload(index)?;
+                buf.copy_from_slice(&self.stage_whole_page(&payload)?);
+                Ok(())
+            }
+            Lookup::Stored(stored) => self
+                .pager
+                .read_unverified_into(stored, buf)
+                .map_err(|err| err.at_page(page)),
+        }
+    }
+    /// Where the freshest version of a page in range is
+    fn lookup(&self, page: usize) -> BookwormResult<Lookup> {
+        self.check_logged_page(page)?;
+        Ok(self
+            .wal
+            .as_ref()
+            .expect("in write ahead log mode")
+            .lookup(page))
+    }
+    fn check_logged_page(&self, page: usize) -> BookwormResult<()> {
+        let len = self.len();
+        if page >= len {
+            return Err(BookwormError::new(
+                ErrorKind::PageOutOfRange,
+                format!("Page {} is out of range: only {} pages exist", page, len),
+            ));
+        }
+        Ok(())
+    }
+    /// Reads the serialized record of a log entry back from the swap
+    fn logged_payload(&mut self, index: usize) -> BookwormResult<Vec<u8>> {
+        let wal = self.wal.as_ref().expect("in write ahead log mode");
+        let (offset, len) = wal.entries[index].payload;
+        let mut payload = vec![0; len];
+        self.swap.read_unframed(offset, &mut payload)?;
+        Ok(payload)
+    }
+    /// Appends an entry to the log, writing its header first when it's empty
+    fn log(&mut self, opcode: u8, page: usize, payload: &[u8]) -> BookwormResult<()> {
+        let Bookworm { wal, swap, .. } = self;
+        let wal = wal.as_mut().expect("in write ahead log mode");
+        if wal.entries.is_empty() {
+            let mut header = [0; LOG_HEADER_BYTES as usize];
+            header[..4].copy_from_slice(LOG_MAGIC);
+            header[8..].copy_from_slice(&wal.generation.to_le_bytes());
+            swap.write_unframed(0, &header)?;
+        }
+        let mut body = Vec::with_capacity(BODY_HEADER_BYTES as usize + payload.len());
+        body.push(opcode);
+        body.extend_from_slice(&(page as u64).to_le_bytes());
+        body.extend_from_slice(payload);
+        let mut entry = Vec::with_capacity(ENTRY_HEADER_BYTES as usize + body.len());
+        entry.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&entry_checksum(wal.generation, &body).to_le_bytes());
+        entry.extend_from_slice(&body);
+        swap.write_unframed(wal.end, &entry)?;
+        wal.entries.push(LogEntry {
+            opcode,
+            page,
+            payload: (
+                wal.end + ENTRY_HEADER_BYTES + BODY_HEADER_BYTES,
+                payload.len(),
+            ),
+        });
+        wal.end += entry.len() as u64;
+        match opcode {
+            PUSH => wal.len += 1,
+            DELETE => wal.len -= 1,
+            _ => {}
+        }
+        match self.durability {
+            Durability::FlushEveryWrite => self.swap.flush()?,
+            Durability::SyncEveryWrite => self.journal_barrier(false)?,
+            Durability::None | Durability::Manual => {}
+        }
+        if self
+            .wal_checkpoint_pages
+            .is_some_and(|pages| self.wal_pages() > pages)
+        {
+            self.checkpoint_log()?;
+        }
+        Ok(())
+    }
+    /// Pages count in write ahead log mode
+    pub(crate) fn logged_len(&self) -> Option<usize> {
+        self.wal.as_ref().map(|wal| wal.len)
+    }
+}
+
+/// Checksum of an entry body, tied to the log generation so stale entries don't pass
+fn entry_checksum(generation: u64, body: &[u8]) -> u32 {
+    let mut bytes = Vec::with_capacity(8 + body.len());
+    bytes.extend_from_slice(&generation.to_le_bytes());
+    bytes.extend_from_slice(body);
+    crc32(&bytes)
+}